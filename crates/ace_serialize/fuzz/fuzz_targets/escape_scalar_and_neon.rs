@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    use weakauras_codec_ace_serialize::escape::{arch::aarch64::neon, scalar};
+
+    let escape_at = unsafe { neon::find_escape(data) };
+    assert!(
+        escape_at == scalar::find_escape(data),
+        "Scalar and Neon implementations of find_escape returned different results"
+    );
+
+    let delimiter_at = unsafe { neon::find_delimiter(data) };
+    assert!(
+        delimiter_at == scalar::find_delimiter(data),
+        "Scalar and Neon implementations of find_delimiter returned different results"
+    );
+});
@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    use weakauras_codec_ace_serialize::escape::{arch::x86_64::sse2, scalar};
+
+    let escape_at = unsafe { sse2::find_escape(data) };
+    assert!(
+        escape_at == scalar::find_escape(data),
+        "Scalar and SSE2 implementations of find_escape returned different results"
+    );
+
+    let delimiter_at = unsafe { sse2::find_delimiter(data) };
+    assert!(
+        delimiter_at == scalar::find_delimiter(data),
+        "Scalar and SSE2 implementations of find_delimiter returned different results"
+    );
+});
@@ -1,13 +1,30 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT
 
+mod number;
+mod parser;
 mod reader;
+mod select;
+mod value;
 
-use crate::{error::DeserializationError, macros::check_recursion};
-use reader::StrReader;
-use weakauras_codec_lua_value::{LuaMapKey, LuaValue};
+use crate::{
+    error::{DeserializationError, DeserializationErrorKind},
+    macros::check_recursion,
+};
+use core::marker::PhantomData;
+pub(crate) use number::tonumber;
+pub use parser::{Event, Parser};
+pub use value::Value;
+use weakauras_codec_lua_value::{
+    LuaValue, LuaValueRef, MapBackend, NonFiniteReport, NormalizationPolicy, PathSegment,
+    ResourceBudget, ResourceLimits,
+};
 
-/// A structure for deserializing strings produced by AceSerialize.
+/// A structure for deserializing strings produced by AceSerialize,
+/// built on top of [Parser].
+///
+/// Produces [LuaValue]s by default, see [Deserializer::into_borrowed]
+/// for producing [LuaValueRef]s.
 ///
 /// # Example
 ///
@@ -24,137 +41,377 @@
 ///     Ok(())
 /// }
 /// ```
-pub struct Deserializer<'s> {
+pub struct Deserializer<'s, V: Value<'s> = LuaValue> {
     remaining_depth: usize,
-    reader: StrReader<'s>,
+    parser: Parser<'s>,
+    normalization: NormalizationPolicy,
+    map_backend: MapBackend,
+    budget: ResourceBudget,
+    non_finite: NonFiniteReport,
+    recovery: bool,
+    stopped: Option<DeserializationError>,
+    _value: PhantomData<V>,
 }
 
 impl<'s> Deserializer<'s> {
     /// Create a deserializer from a string slice.
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(slice: &'s str) -> Self {
+        Self::from_bytes(slice.as_bytes())
+    }
+
+    /// Create a deserializer from a slice of bytes.
+    ///
+    /// Unlike [Deserializer::from_str], this does not require the input to be valid UTF-8,
+    /// strings are deserialized as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{DeserializationError, Deserializer, LuaValue};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let value = Deserializer::from_bytes(b"^1^S\xff\xfe^^")
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///
+    ///     assert!(matches!(value, LuaValue::String(ref s) if s.as_bytes() == b"\xff\xfe"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_bytes(slice: &'s [u8]) -> Self {
         Self {
             remaining_depth: 128,
-            reader: StrReader::new(slice),
+            parser: Parser::new(slice),
+            normalization: NormalizationPolicy::PreferArrays,
+            map_backend: MapBackend::BTreeMap,
+            budget: ResourceBudget::new(ResourceLimits::default()),
+            non_finite: NonFiniteReport::default(),
+            recovery: false,
+            stopped: None,
+            _value: PhantomData,
         }
     }
 
-    /// Deserialize all values.
-    pub fn deserialize_all(mut self) -> Result<Vec<LuaValue>, DeserializationError> {
-        self.reader.read_identifier().and_then(|v| {
-            if v == "^1" {
-                Ok(())
-            } else {
-                Err(DeserializationError::InvalidPrefix)
-            }
-        })?;
+    /// Make the deserializer produce [LuaValueRef]s, whose strings borrow from the input
+    /// unless they contain escape sequences.
+    ///
+    /// Tables are produced as [LuaValueRef::Array]s if the policy is
+    /// [NormalizationPolicy::PreferArrays] and their keys are `1..=n` in this order,
+    /// and as [LuaValueRef::Map]s otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use weakauras_codec_ace_serialize::{DeserializationError, Deserializer, LuaValueRef};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let values = Deserializer::from_str("^1^SHello^SHello,~`world!^^")
+    ///         .into_borrowed()
+    ///         .deserialize_all()?;
+    ///
+    ///     assert!(matches!(values[0], LuaValueRef::String(Cow::Borrowed(b"Hello"))));
+    ///     assert!(matches!(values[1], LuaValueRef::String(Cow::Owned(_))));
+    ///     assert_eq!(values[1].to_owned(), "Hello, world!".into());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_borrowed(self) -> Deserializer<'s, LuaValueRef<'s>> {
+        Deserializer {
+            remaining_depth: self.remaining_depth,
+            parser: self.parser,
+            normalization: self.normalization,
+            map_backend: self.map_backend,
+            budget: self.budget,
+            non_finite: self.non_finite,
+            recovery: self.recovery,
+            stopped: self.stopped,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<'s, V: Value<'s>> Deserializer<'s, V> {
+    /// Set the policy for choosing between [LuaValue::Array] and [LuaValue::Map].
+    ///
+    /// AceSerialize does not distinguish between arrays and maps, so
+    /// [NormalizationPolicy::Preserve] produces maps. The default is
+    /// [NormalizationPolicy::PreferArrays], like in the LibSerialize deserializer,
+    /// so that the same table has the same shape in both formats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{Deserializer, DeserializationError, LuaValue, NormalizationPolicy};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let input = "^1^T^N1^Sa^N2^Sb^t^^";
+    ///
+    ///     let value = Deserializer::from_str(input).deserialize_first()?.unwrap();
+    ///     assert!(matches!(value, LuaValue::Array(_)));
+    ///
+    ///     let value = Deserializer::from_str(input)
+    ///         .with_normalization(NormalizationPolicy::PreferMaps)
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///     assert!(matches!(value, LuaValue::Map(_)));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_normalization(mut self, policy: NormalizationPolicy) -> Self {
+        self.normalization = policy;
+        self
+    }
+
+    /// Set the implementation of produced [LuaValue::Map]s.
+    ///
+    /// The default is [MapBackend::BTreeMap].
+    pub fn with_map_backend(mut self, backend: MapBackend) -> Self {
+        self.map_backend = backend;
+        self
+    }
+
+    /// Set the limits on resources consumed by deserialization.
+    ///
+    /// The default is [ResourceLimits::default].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{DeserializationErrorKind, Deserializer};
+    /// use weakauras_codec_lua_value::{ResourceLimits, error::ResourceLimitExceeded};
+    ///
+    /// let limits = ResourceLimits::default().with_max_string_bytes(4);
+    ///
+    /// assert_eq!(
+    ///     Deserializer::from_str("^1^SHello^^")
+    ///         .with_limits(limits)
+    ///         .deserialize_first()
+    ///         .map_err(|e| e.kind()),
+    ///     Err(DeserializationErrorKind::ResourceLimitExceeded(
+    ///         ResourceLimitExceeded::StringBytes
+    ///     ))
+    /// );
+    /// ```
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.budget = ResourceBudget::new(limits);
+        self
+    }
+
+    /// Make the deserializer recover from errors instead of failing.
+    ///
+    /// Once an error occurs, deserialization stops and the tables being deserialized
+    /// are closed with the entries read so far, so a truncated or damaged input
+    /// still produces the part of the value that precedes the damage.
+    /// The error is available from [Deserializer::recovery_error] afterwards.
+    ///
+    /// Exceeding the recursion limit is still an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{
+    ///     DeserializationError, DeserializationErrorKind, Deserializer, LuaValue,
+    /// };
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let mut deserializer = Deserializer::from_str("^1^T^N1^Sa^N2^Sb^N3^S").with_recovery();
+    ///
+    ///     let Some(LuaValue::Array(elements)) = deserializer.deserialize_next()? else {
+    ///         unreachable!()
+    ///     };
+    ///     assert_eq!(elements, ["a".into(), "b".into()]);
+    ///
+    ///     let error = deserializer.recovery_error().unwrap();
+    ///     assert_eq!(error.kind(), DeserializationErrorKind::UnexpectedEof);
+    ///     assert_eq!(error.location().unwrap().to_string().lines().next(), Some("at offset 19 in [3]"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_recovery(mut self) -> Self {
+        self.recovery = true;
+        self
+    }
 
+    /// Deserialize all values.
+    pub fn deserialize_all(mut self) -> Result<Vec<V>, DeserializationError> {
         let mut result = Vec::new();
 
-        while self.reader.peek_identifier().is_ok() {
-            if let Some(v) = self.deserialize_helper()? {
-                result.push(v);
-            }
+        while let Some(v) = self.deserialize_next()? {
+            result.push(v);
         }
 
         Ok(result)
     }
 
     /// Deserialize the first value.
-    pub fn deserialize_first(mut self) -> Result<Option<LuaValue>, DeserializationError> {
-        self.reader.read_identifier().and_then(|v| {
-            if v == "^1" {
-                Ok(())
-            } else {
-                Err(DeserializationError::InvalidPrefix)
-            }
-        })?;
-
-        self.deserialize_helper()
-    }
-
-    fn deserialize_helper(&mut self) -> Result<Option<LuaValue>, DeserializationError> {
-        Ok(Some(match self.reader.read_identifier()? {
-            "^^" => return Ok(None),
-            "^Z" => LuaValue::Null,
-            "^B" => LuaValue::Boolean(true),
-            "^b" => LuaValue::Boolean(false),
-            "^S" => LuaValue::String(String::from(self.reader.parse_str()?)),
-            "^N" => LuaValue::Number(
-                self.reader
-                    .read_until_next()
-                    .and_then(Self::deserialize_number)?,
-            ),
-            "^F" => {
-                let mantissa = self
-                    .reader
-                    .read_until_next()
-                    .and_then(|v| v.parse::<f64>().map_err(Into::into))?;
-                let exponent = match self.reader.read_identifier()? {
-                    "^f" => self
-                        .reader
-                        .read_until_next()
-                        .and_then(|v| v.parse::<f64>().map_err(Into::into))?,
-                    _ => return Err(DeserializationError::MissingExponent),
-                };
-
-                LuaValue::Number(mantissa * (2f64.powf(exponent)))
+    pub fn deserialize_first(mut self) -> Result<Option<V>, DeserializationError> {
+        self.deserialize_next()
+    }
+
+    /// Deserialize the next value, returns `None` if there are no values left.
+    ///
+    /// Unlike [Deserializer::deserialize_first], this keeps the deserializer,
+    /// e.g. to inspect [Deserializer::non_finite_report] afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{DeserializationError, Deserializer, LuaValue};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let mut deserializer = Deserializer::from_str("^1^T^N1^N1^N2^N1.#INF^t^^");
+    ///
+    ///     assert!(matches!(deserializer.deserialize_next()?, Some(LuaValue::Array(_))));
+    ///     assert_eq!(deserializer.non_finite_report().positive_infinity(), 1);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_next(&mut self) -> Result<Option<V>, DeserializationError> {
+        if self.stopped.is_some() {
+            return Ok(None);
+        }
+
+        let result = self.deserialize_helper();
+        let result = match self.recover(result) {
+            Ok(result) => result.flatten(),
+            Err(e) => return Err(self.locate(e)),
+        };
+        if let Some(e) = self.stopped.take() {
+            self.stopped = Some(self.locate(e));
+        }
+
+        Ok(result)
+    }
+
+    /// Get the non-finite numbers among the values deserialized so far.
+    ///
+    /// Numbers in the parts of values that a [Selector](crate::Selector) leaves out are not counted.
+    pub fn non_finite_report(&self) -> NonFiniteReport {
+        self.non_finite
+    }
+
+    /// Get the error that stopped deserialization in recovery mode, see [Deserializer::with_recovery].
+    pub fn recovery_error(&self) -> Option<&DeserializationError> {
+        self.stopped.as_ref()
+    }
+
+    /// Reports `error` at the last event unless it already has an offset.
+    #[cold]
+    fn locate(&self, error: DeserializationError) -> DeserializationError {
+        error.located_at(self.parser.input(), self.parser.offset())
+    }
+
+    /// In recovery mode, stores `result`'s error and returns `None` instead of failing.
+    fn recover<T>(
+        &mut self,
+        result: Result<T, DeserializationError>,
+    ) -> Result<Option<T>, DeserializationError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.recovery => {
+                self.stopped = Some(e);
+                Ok(None)
             }
-            "^T" => {
-                let mut keys = Vec::with_capacity(16);
-                let mut values = Vec::with_capacity(16);
-                loop {
-                    match self.reader.peek_identifier()? {
-                        "^t" => {
-                            let _ = self.reader.read_identifier();
-                            break;
-                        }
-                        _ => {
-                            check_recursion!(self, DeserializationError, {
-                                let key = LuaMapKey::try_from(
-                                    self.deserialize_helper()?
-                                        .ok_or(DeserializationError::UnclosedMap)?,
-                                )?;
-
-                                let value = match self.reader.peek_identifier()? {
-                                    "^t" => {
-                                        return Err(DeserializationError::MapMissingValue);
-                                    }
-                                    _ => self
-                                        .deserialize_helper()?
-                                        .ok_or(DeserializationError::UnclosedMap)?,
-                                };
-                                keys.push(key);
-                                values.push(value);
-                            });
-                        }
-                    }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [Deserializer::recover], but for a table entry at `segment`.
+    #[inline(always)]
+    fn entry<T>(
+        &mut self,
+        result: Result<T, DeserializationError>,
+        segment: impl FnOnce() -> PathSegment,
+    ) -> Result<Option<T>, DeserializationError> {
+        match result {
+            Ok(value) => {
+                // The value is a table that was cut short by an error within it.
+                if let Some(e) = self.stopped.take() {
+                    self.stopped = Some(e.with_parent(segment()));
                 }
+                Ok(Some(value))
+            }
+            Err(e) => self.recover(Err(e.with_parent(segment()))),
+        }
+    }
 
-                debug_assert_eq!(keys.len(), values.len());
-                let is_array = keys.iter().enumerate().all(|(i, key)| {
-                    if let LuaValue::Number(key) = key.as_value() {
-                        *key == (i + 1) as f64
-                    } else {
-                        false
-                    }
-                });
+    fn deserialize_helper(&mut self) -> Result<Option<V>, DeserializationError> {
+        match self.parser.next_event()? {
+            Some((_, event)) => self.event_value(event).map(Some),
+            None => Ok(None),
+        }
+    }
 
-                if is_array {
-                    LuaValue::Array(values)
-                } else {
-                    LuaValue::Map(keys.into_iter().zip(values).collect())
-                }
+    #[inline(always)]
+    fn event_value(&mut self, event: Event<'s>) -> Result<V, DeserializationError> {
+        self.budget.spend_nodes(1)?;
+
+        Ok(match event {
+            Event::Nil => V::null(),
+            Event::Bool(b) => V::boolean(b),
+            Event::Number(n) => {
+                self.non_finite.record(n);
+                V::number(n)
+            }
+            Event::String(s) => {
+                self.budget.spend_string_bytes(s.len())?;
+                V::string(s)
             }
-            _ => return Err(DeserializationError::InvalidIdentifier),
-        }))
+            Event::TableStart => self.deserialize_table()?,
+            Event::Key | Event::TableEnd => unreachable!("tables are consumed as a whole"),
+        })
     }
 
-    fn deserialize_number(data: &str) -> Result<f64, DeserializationError> {
-        match data {
-            "1.#INF" | "inf" => Ok(f64::INFINITY),
-            "-1.#INF" | "-inf" => Ok(f64::NEG_INFINITY),
-            v => v.parse().map_err(Into::into),
+    #[inline(always)]
+    fn extract_value(&mut self) -> Result<V, DeserializationError> {
+        match self.deserialize_helper() {
+            Ok(Some(value)) => Ok(value),
+            // The parser reports unterminated tables, this is unreachable in practice.
+            Ok(None) => Err(DeserializationErrorKind::UnclosedMap.into()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn deserialize_table(&mut self) -> Result<V, DeserializationError> {
+        let mut keys = Vec::with_capacity(16);
+        let mut values = Vec::with_capacity(16);
+        let mut len = 0;
+
+        while self.stopped.is_none() {
+            let event = self.parser.next_event();
+            match self.recover(event)? {
+                Some(Some((_, Event::Key))) => {}
+                Some(Some((_, Event::TableEnd))) | None => break,
+                _ => unreachable!("a table consists of key-value pairs"),
+            }
+
+            len += 1;
+            self.budget.check_collection_len(len)?;
+
+            check_recursion!(self, DeserializationErrorKind, {
+                let key = self.extract_value().and_then(V::key);
+                // A key that was cut short is dropped along with its entry.
+                if let Some(key) = self.recover(key)?.filter(|_| self.stopped.is_none()) {
+                    let value = self.extract_value();
+                    // Lua tables cannot hold `nil` values.
+                    if let Some(value) = self
+                        .entry(value, || V::path_segment(&key))?
+                        .filter(|value| !value.is_null())
+                    {
+                        keys.push(key);
+                        values.push(value);
+                    }
+                }
+            });
         }
+
+        Ok(self.finish_table(keys, values))
+    }
+
+    #[inline(always)]
+    fn finish_table(&self, keys: Vec<V::Key>, values: Vec<V>) -> V {
+        V::table(keys, values, self.normalization, self.map_backend)
     }
 }
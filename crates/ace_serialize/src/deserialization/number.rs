@@ -0,0 +1,158 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use core::str;
+
+/// Converts `data` into a number the way Lua 5.1's `tonumber` does.
+///
+/// Lua uses C99's `strtod`, which accepts decimal and hexadecimal numbers,
+/// `inf`, `infinity`, `nan` and `nan(...)` regardless of case, and allows surrounding whitespace.
+pub(crate) fn tonumber(data: &[u8]) -> Option<f64> {
+    // Lua passes strings to `strtod` as C strings, which end at the first NUL.
+    let data = data.split(|&b| b == 0).next().unwrap_or_default();
+
+    let start = data.iter().position(|&b| !is_space(b))?;
+    let end = data.iter().rposition(|&b| !is_space(b))? + 1;
+    let (negative, body) = match &data[start..end] {
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        body => (false, body),
+    };
+
+    let value = match body {
+        [b'0', b'x' | b'X', hex @ ..] => parse_hex(hex)?,
+        [b'+' | b'-', ..] => return None,
+        _ if is_nan_with_payload(body) => f64::NAN,
+        // Rust's grammar is the same as the decimal one of `strtod`, with `inf`,
+        // `infinity` and `nan` included.
+        _ => str::from_utf8(body).ok()?.parse().ok()?,
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+/// The characters `isspace` accepts in the "C" locale.
+#[inline(always)]
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r')
+}
+
+/// Checks for `nan(...)`, where the parentheses contain alphanumerics and underscores.
+fn is_nan_with_payload(body: &[u8]) -> bool {
+    match body {
+        [n, a, n2, b'(', payload @ .., b')'] => {
+            [*n, *a, *n2].eq_ignore_ascii_case(b"nan")
+                && payload
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        }
+        _ => false,
+    }
+}
+
+/// Parses the part of a hexadecimal number after `0x`, rounding to nearest, ties to even.
+fn parse_hex(data: &[u8]) -> Option<f64> {
+    let mut mantissa = 0u64;
+    // Whether any of the digits that do not fit into `mantissa` is non-zero.
+    let mut sticky = false;
+    let mut exponent = 0i64;
+    let mut digits = 0;
+    let mut seen_point = false;
+
+    let mut rest = data;
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'.' && !seen_point {
+            seen_point = true;
+        } else if let Some(digit) = (b as char).to_digit(16) {
+            digits += 1;
+            if mantissa >> 60 == 0 {
+                mantissa = mantissa << 4 | digit as u64;
+                if seen_point {
+                    exponent -= 4;
+                }
+            } else {
+                sticky |= digit != 0;
+                if !seen_point {
+                    exponent += 4;
+                }
+            }
+        } else {
+            break;
+        }
+        rest = tail;
+    }
+
+    if digits == 0 {
+        return None;
+    }
+
+    if let [b'p' | b'P', tail @ ..] = rest {
+        let (negative, tail) = match tail {
+            [b'-', tail @ ..] => (true, tail),
+            [b'+', tail @ ..] => (false, tail),
+            tail => (false, tail),
+        };
+        if tail.is_empty() || !tail.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+
+        // Anything beyond this over- or underflows anyway.
+        let binary_exponent = tail
+            .iter()
+            .fold(0i64, |acc, b| (acc * 10 + (b - b'0') as i64).min(1 << 20));
+        exponent += if negative {
+            -binary_exponent
+        } else {
+            binary_exponent
+        };
+    } else if !rest.is_empty() {
+        return None;
+    }
+
+    if mantissa == 0 {
+        return Some(0.0);
+    }
+    Some(round_to_f64(mantissa, sticky, exponent))
+}
+
+/// Rounds `(mantissa + sticky) * 2^exponent` to the nearest `f64`, ties to even,
+/// where `sticky` stands for a non-zero fraction smaller than one.
+fn round_to_f64(mantissa: u64, sticky: bool, exponent: i64) -> f64 {
+    let shift = mantissa.leading_zeros();
+    let mantissa = (mantissa << shift) as u128;
+    // The value is in `[2^top, 2^(top + 1))`.
+    let top = exponent + 63 - shift as i64;
+
+    if top > 1023 {
+        return f64::INFINITY;
+    }
+    // The amount of significant bits that fit, fewer for subnormals.
+    let kept_bits = if top >= -1022 { 53 } else { top + 1075 };
+    if kept_bits < 0 {
+        return 0.0;
+    }
+
+    let dropped_bits = 64 - kept_bits as u32;
+    let mut kept = mantissa >> dropped_bits;
+    let remainder = mantissa & ((1 << dropped_bits) - 1);
+    let half = 1 << (dropped_bits - 1);
+    if remainder > half || (remainder == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+    }
+
+    ldexp(kept as f64, (top + 1 - kept_bits) as i32)
+}
+
+/// Computes `value * 2^exponent`, which is exact unless the result over- or underflows.
+fn ldexp(mut value: f64, mut exponent: i32) -> f64 {
+    while exponent > 1023 {
+        value *= f64::from_bits(2046 << 52);
+        exponent -= 1023;
+    }
+    while exponent < -1022 {
+        value *= f64::MIN_POSITIVE;
+        exponent += 1022;
+    }
+
+    value * f64::from_bits(((exponent + 1023) as u64) << 52)
+}
@@ -0,0 +1,212 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{number::tonumber, reader::SliceReader};
+use crate::error::{DeserializationError, DeserializationErrorKind};
+use std::borrow::Cow;
+
+/// An event produced by [Parser].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'s> {
+    /// The start of a table.
+    ///
+    /// AceSerialize does not store the lengths of tables, so a table is a sequence
+    /// of a [Event::Key] and the events of the key and the value for each pair,
+    /// followed by [Event::TableEnd].
+    TableStart,
+    /// The start of a key-value pair, the next value is the key.
+    Key,
+    /// The end of the innermost table.
+    TableEnd,
+    /// `nil`.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string, borrowed from the input unless it contains escape sequences.
+    String(Cow<'s, [u8]>),
+}
+
+/// The position within a table.
+#[derive(Clone, Copy)]
+enum Entry {
+    Key,
+    KeyValue,
+    Value,
+}
+
+/// A pull parser for strings produced by AceSerialize.
+///
+/// Yields [Event]s along with the offsets in the input they start at,
+/// without building any values. Once an error has been returned, the parser yields nothing.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_ace_serialize::{DeserializationError, Parser, deserialization::Event};
+///
+/// fn main() -> Result<(), DeserializationError> {
+///     let events = Parser::new(b"^1^T^Sid^N1^t^^").collect::<Result<Vec<_>, _>>()?;
+///
+///     assert_eq!(
+///         events,
+///         [
+///             (2, Event::TableStart),
+///             (4, Event::Key),
+///             (4, Event::String(b"id".into())),
+///             (8, Event::Number(1.0)),
+///             (11, Event::TableEnd),
+///         ]
+///     );
+///     Ok(())
+/// }
+/// ```
+pub struct Parser<'s> {
+    reader: SliceReader<'s>,
+    prefix_checked: bool,
+    done: bool,
+    /// The offset of the last event, errors are reported there.
+    offset: usize,
+
+    stack: Vec<Entry>,
+}
+
+impl<'s> Parser<'s> {
+    /// Create a parser from a slice of bytes.
+    pub fn new(slice: &'s [u8]) -> Self {
+        Self {
+            reader: SliceReader::new(slice),
+            prefix_checked: false,
+            done: false,
+            offset: 0,
+
+            stack: Vec::new(),
+        }
+    }
+
+    /// Parse the next event, returns `None` once the input's terminating `^^` has been reached.
+    pub fn next_event(&mut self) -> Result<Option<(usize, Event<'s>)>, DeserializationError> {
+        self.next_with(true)
+            .map_err(|e| e.located_at(self.reader.input(), self.offset))
+    }
+
+    /// Get the whole input.
+    pub(super) fn input(&self) -> &'s [u8] {
+        self.reader.input()
+    }
+
+    /// Get the offset of the last event.
+    pub(super) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Parse the next event, strings are validated but not decoded and are always empty.
+    pub(super) fn skip_event(
+        &mut self,
+    ) -> Result<Option<(usize, Event<'s>)>, DeserializationError> {
+        self.next_with(false)
+    }
+
+    fn next_with(
+        &mut self,
+        decode_strings: bool,
+    ) -> Result<Option<(usize, Event<'s>)>, DeserializationError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let result = self.parse_event(decode_strings);
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result
+    }
+
+    fn parse_event(
+        &mut self,
+        decode_strings: bool,
+    ) -> Result<Option<(usize, Event<'s>)>, DeserializationError> {
+        if !self.prefix_checked {
+            if self.reader.read_identifier()? != b"^1" {
+                return Err(DeserializationErrorKind::InvalidPrefix.into());
+            }
+            self.prefix_checked = true;
+        }
+
+        let offset = self.reader.position();
+        self.offset = offset;
+
+        if let Some(entry) = self.stack.last_mut() {
+            match *entry {
+                Entry::Key => {
+                    if self.reader.peek_identifier()? == b"^t" {
+                        let _ = self.reader.read_identifier();
+                        self.stack.pop();
+                        return Ok(Some((offset, Event::TableEnd)));
+                    }
+
+                    *entry = Entry::KeyValue;
+                    return Ok(Some((offset, Event::Key)));
+                }
+                Entry::KeyValue => *entry = Entry::Value,
+                Entry::Value => {
+                    if self.reader.peek_identifier()? == b"^t" {
+                        return Err(DeserializationErrorKind::MapMissingValue.into());
+                    }
+                    *entry = Entry::Key;
+                }
+            }
+        }
+
+        let event = match self.reader.read_identifier()? {
+            b"^^" if self.stack.is_empty() => return Ok(None),
+            b"^^" => return Err(DeserializationErrorKind::UnclosedMap.into()),
+            b"^Z" => Event::Nil,
+            b"^B" => Event::Bool(true),
+            b"^b" => Event::Bool(false),
+            b"^S" if decode_strings => Event::String(self.reader.parse_str()?),
+            b"^S" => {
+                self.reader.skip_str()?;
+                Event::String(Cow::Borrowed(&[]))
+            }
+            b"^N" => Event::Number(self.reader.read_until_next().and_then(deserialize_number)?),
+            b"^F" => {
+                let mantissa = self.reader.read_until_next().and_then(parse_number)?;
+                let exponent = match self.reader.read_identifier()? {
+                    b"^f" => self.reader.read_until_next().and_then(parse_number)?,
+                    _ => return Err(DeserializationErrorKind::MissingExponent.into()),
+                };
+
+                Event::Number(mantissa * (2f64.powf(exponent)))
+            }
+            b"^T" => {
+                self.stack.push(Entry::Key);
+                Event::TableStart
+            }
+            _ => return Err(DeserializationErrorKind::InvalidIdentifier.into()),
+        };
+
+        Ok(Some((offset, event)))
+    }
+}
+
+impl<'s> Iterator for Parser<'s> {
+    type Item = Result<(usize, Event<'s>), DeserializationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+fn deserialize_number(data: &[u8]) -> Result<f64, DeserializationError> {
+    match data {
+        b"1.#INF" | b"inf" => Ok(f64::INFINITY),
+        b"-1.#INF" | b"-inf" => Ok(f64::NEG_INFINITY),
+        v => parse_number(v),
+    }
+}
+
+fn parse_number(data: &[u8]) -> Result<f64, DeserializationError> {
+    tonumber(data).ok_or(DeserializationErrorKind::InvalidFloatNumber.into())
+}
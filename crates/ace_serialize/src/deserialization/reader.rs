@@ -4,21 +4,20 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::error::DeserializationError;
+use crate::{
+    error::{DeserializationError, DeserializationErrorKind},
+    escape,
+};
+use std::borrow::Cow;
 
-pub(crate) struct StrReader<'s> {
+pub(crate) struct SliceReader<'s> {
     slice: &'s [u8],
     index: usize,
-    scratch: Vec<u8>,
 }
 
-impl<'s> StrReader<'s> {
-    pub(crate) fn new(slice: &'s str) -> Self {
-        Self {
-            slice: slice.as_bytes(),
-            index: 0,
-            scratch: Vec::new(),
-        }
+impl<'s> SliceReader<'s> {
+    pub(crate) fn new(slice: &'s [u8]) -> Self {
+        Self { slice, index: 0 }
     }
 
     #[allow(dead_code)]
@@ -47,92 +46,78 @@ fn discard(&mut self) {
         self.index += 1;
     }
 
-    #[allow(dead_code)]
+    /// Skips the bytes of a string up to the next `^` or `~`.
+    #[inline]
+    fn skip_plain_bytes(&mut self) {
+        self.index += escape::find_delimiter(&self.slice[self.index..]);
+    }
+
+    #[inline]
+    pub(crate) fn input(&self) -> &'s [u8] {
+        self.slice
+    }
+
     #[inline]
     pub(crate) fn position(&self) -> usize {
         self.index
     }
 
-    pub(crate) fn read_identifier(&mut self) -> Result<&str, DeserializationError> {
+    pub(crate) fn read_identifier(&mut self) -> Result<&'s [u8], DeserializationError> {
         if self.index + 1 < self.slice.len() {
-            // Matching against the second byte to ensure
-            // we don't break up a multibyte character.
             match (self.slice[self.index], self.slice[self.index + 1]) {
                 (b'^', 0x00..=0x79) => {
-                    let result = unsafe {
-                        core::str::from_utf8_unchecked(&self.slice[self.index..self.index + 2])
-                    };
+                    let result = &self.slice[self.index..self.index + 2];
                     self.index += 2;
                     Ok(result)
                 }
-                _ => Err(DeserializationError::InvalidIdentifier),
+                _ => Err(DeserializationErrorKind::InvalidIdentifier.into()),
             }
         } else {
-            Err(DeserializationError::UnexpectedEof)
+            Err(DeserializationErrorKind::UnexpectedEof.into())
         }
     }
 
-    pub(crate) fn peek_identifier(&self) -> Result<&str, DeserializationError> {
+    pub(crate) fn peek_identifier(&self) -> Result<&'s [u8], DeserializationError> {
         if self.index + 1 < self.slice.len() {
-            // Matching against the second byte to ensure
-            // we don't break up a multibyte character.
             match (self.slice[self.index], self.slice[self.index + 1]) {
-                (b'^', 0x00..=0x79) => Ok(unsafe {
-                    core::str::from_utf8_unchecked(&self.slice[self.index..self.index + 2])
-                }),
-                _ => Err(DeserializationError::InvalidIdentifier),
+                (b'^', 0x00..=0x79) => Ok(&self.slice[self.index..self.index + 2]),
+                _ => Err(DeserializationErrorKind::InvalidIdentifier.into()),
             }
         } else {
-            Err(DeserializationError::UnexpectedEof)
+            Err(DeserializationErrorKind::UnexpectedEof.into())
         }
     }
 
-    pub(crate) fn read_until_next(&mut self) -> Result<&str, DeserializationError> {
+    pub(crate) fn read_until_next(&mut self) -> Result<&'s [u8], DeserializationError> {
         let start = self.index;
 
         loop {
             match self.peek() {
-                None => return Err(DeserializationError::UnexpectedEof),
-                Some(b'^') => {
-                    // SAFETY: As long as `start` does not point at the middle
-                    // of a multibyte character, this should be safe.
-                    // Public API does not allow the reader to end up in such a state.
-                    return Ok(unsafe {
-                        core::str::from_utf8_unchecked(&self.slice[start..self.index])
-                    });
-                }
+                None => return Err(DeserializationErrorKind::UnexpectedEof.into()),
+                Some(b'^') => return Ok(&self.slice[start..self.index]),
                 _ => self.discard(),
             }
         }
     }
 
-    pub(crate) fn parse_str(&mut self) -> Result<&str, DeserializationError> {
-        self.scratch.clear();
-
+    /// Reads a string, borrowing it from the input unless it contains escape sequences.
+    pub(crate) fn parse_str(&mut self) -> Result<Cow<'s, [u8]>, DeserializationError> {
+        let mut scratch = Vec::new();
         let mut copy_from = self.index;
 
         loop {
             match self.peek() {
-                None => return Err(DeserializationError::UnexpectedEof),
+                None => return Err(DeserializationErrorKind::UnexpectedEof.into()),
                 Some(b'^') => {
-                    if self.scratch.is_empty() {
-                        // SAFETY: As long as `copy_from` does not point at the middle
-                        // of a multibyte character, this should be safe.
-                        // Public API does not allow the reader to end up in such a state.
-                        return Ok(unsafe {
-                            core::str::from_utf8_unchecked(&self.slice[copy_from..self.index])
-                        });
+                    if scratch.is_empty() {
+                        return Ok(Cow::Borrowed(&self.slice[copy_from..self.index]));
                     } else {
-                        // SAFETY: None of the replaced bytes and their replacements
-                        // has the most significant bit set to 1.
-                        self.scratch
-                            .extend_from_slice(&self.slice[copy_from..self.index]);
-                        return Ok(unsafe { core::str::from_utf8_unchecked(&self.scratch) });
+                        scratch.extend_from_slice(&self.slice[copy_from..self.index]);
+                        return Ok(Cow::Owned(scratch));
                     }
                 }
                 Some(b'~') => {
-                    self.scratch
-                        .extend_from_slice(&self.slice[copy_from..self.index]);
+                    scratch.extend_from_slice(&self.slice[copy_from..self.index]);
 
                     self.discard();
 
@@ -142,15 +127,34 @@ pub(crate) fn parse_str(&mut self) -> Result<&str, DeserializationError> {
                         Some(0x7B) => 0x7F,
                         Some(0x7C) => 0x7E,
                         Some(0x7D) => 0x5E,
-                        _ => return Err(DeserializationError::InvalidEscapeCharacter),
+                        _ => return Err(DeserializationErrorKind::InvalidEscapeCharacter.into()),
                     };
 
                     self.discard();
-                    self.scratch.push(replacement);
+                    scratch.push(replacement);
 
                     copy_from = self.index;
                 }
-                _ => self.discard(),
+                _ => self.skip_plain_bytes(),
+            }
+        }
+    }
+
+    /// Validates a string without decoding it.
+    pub(crate) fn skip_str(&mut self) -> Result<(), DeserializationError> {
+        loop {
+            match self.peek() {
+                None => return Err(DeserializationErrorKind::UnexpectedEof.into()),
+                Some(b'^') => return Ok(()),
+                Some(b'~') => {
+                    self.discard();
+
+                    match self.peek() {
+                        Some(0x40..=0x5D | 0x5F..=0x60 | 0x7A..=0x7D) => self.discard(),
+                        _ => return Err(DeserializationErrorKind::InvalidEscapeCharacter.into()),
+                    }
+                }
+                _ => self.skip_plain_bytes(),
             }
         }
     }
@@ -0,0 +1,207 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{Deserializer, Event, Value};
+use crate::{
+    error::{DeserializationError, DeserializationErrorKind},
+    macros::check_recursion,
+};
+use weakauras_codec_lua_value::{PathSegment, Selector, error::TryFromLuaValueError};
+
+impl<'s, V: Value<'s>> Deserializer<'s, V> {
+    /// Deserialize the selected parts of the first value, see [Selector].
+    ///
+    /// Everything else is skipped without being built, which is considerably faster
+    /// when only a small part of a large value is needed. AceSerialize has no references,
+    /// so every part of the input is parsed once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{
+    ///     DeserializationError, Deserializer, LuaValue, PathSegment, Selector,
+    /// };
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     // {c = {{id = "a", x = 1}, {id = "b", x = 2}}}
+    ///     let input = "^1^T^Sc^T^N1^T^Sid^Sa^Sx^N1^t^N2^T^Sid^Sb^Sx^N2^t^t^t^^";
+    ///     let selector = Selector::new().with_path([
+    ///         PathSegment::from("c"),
+    ///         PathSegment::Any,
+    ///         PathSegment::from("id"),
+    ///     ]);
+    ///
+    ///     let value = Deserializer::from_str(input)
+    ///         .deserialize_selected(&selector)?
+    ///         .unwrap();
+    ///     // {c = {{id = "a"}, {id = "b"}}}
+    ///     let expected = Deserializer::from_str("^1^T^Sc^T^N1^T^Sid^Sa^t^N2^T^Sid^Sb^t^t^t^^")
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(format!("{value:?}"), format!("{expected:?}"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_selected(
+        mut self,
+        selector: &Selector,
+    ) -> Result<Option<V>, DeserializationError> {
+        if selector.selects_all() {
+            return self.deserialize_first();
+        }
+
+        self.select_first(selector).map_err(|e| self.locate(e))
+    }
+
+    fn select_first(&mut self, selector: &Selector) -> Result<Option<V>, DeserializationError> {
+        match self.parser.skip_event()? {
+            Some((_, Event::TableStart)) => {
+                self.budget.spend_nodes(1)?;
+                self.select_table(selector).map(Some)
+            }
+            Some(_) => Ok(Some(V::null())),
+            None => Ok(None),
+        }
+    }
+
+    /// Deserializes the selected parts of the next value, or skips it if `selector` is `None`.
+    fn select_value(&mut self, selector: Option<&Selector>) -> Result<V, DeserializationError> {
+        if selector.is_some_and(Selector::selects_all) {
+            return self.extract_value();
+        }
+
+        match self.parser.skip_event()? {
+            Some((_, Event::TableStart)) => match selector {
+                Some(selector) => {
+                    self.budget.spend_nodes(1)?;
+                    self.select_table(selector)
+                }
+                None => {
+                    self.skip_table()?;
+                    Ok(V::null())
+                }
+            },
+            Some(_) => Ok(V::null()),
+            // The parser reports unterminated tables, this is unreachable in practice.
+            None => Err(DeserializationErrorKind::UnclosedMap.into()),
+        }
+    }
+
+    fn select_table(&mut self, selector: &Selector) -> Result<V, DeserializationError> {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        let mut len = 0;
+
+        loop {
+            match self.parser.next_event()? {
+                Some((_, Event::Key)) => {}
+                Some((_, Event::TableEnd)) => break,
+                _ => unreachable!("a table consists of key-value pairs"),
+            }
+
+            len += 1;
+            self.budget.check_collection_len(len)?;
+
+            check_recursion!(self, DeserializationErrorKind, {
+                if let Some((key, child)) = self.select_key(selector)? {
+                    let value = self
+                        .select_value(Some(child))
+                        .map_err(|e| e.with_parent(V::path_segment(&key)))?;
+
+                    // Lua tables cannot hold `nil` values.
+                    if !value.is_null() {
+                        keys.push(key);
+                        values.push(value);
+                    }
+                }
+            });
+        }
+
+        Ok(self.finish_table(keys, values))
+    }
+
+    /// Deserializes a key if the value of its entry is selected,
+    /// returns it along with the selection within the value.
+    /// Skips the entry otherwise.
+    fn select_key<'a>(
+        &mut self,
+        selector: &'a Selector,
+    ) -> Result<Option<(V::Key, &'a Selector)>, DeserializationError> {
+        let Some((_, event)) = self.parser.next_event()? else {
+            return Err(DeserializationErrorKind::UnclosedMap.into());
+        };
+        check_key(&event)?;
+
+        let child = match event {
+            Event::String(ref s) => selector.string_child(s),
+            Event::Number(n) => selector.number_child(n),
+            _ => selector.any_child(),
+        };
+
+        match (child, event) {
+            (Some(child), event) => {
+                let key = self.event_value(event)?.key()?;
+                Ok(Some((key, child)))
+            }
+            (None, Event::TableStart) => {
+                self.skip_table()?;
+                self.select_value(None)
+                    .map_err(|e| e.with_parent(PathSegment::Any))?;
+                Ok(None)
+            }
+            (None, event) => {
+                self.select_value(None).map_err(|e| {
+                    e.with_parent(match event {
+                        Event::String(s) => PathSegment::from(&*s),
+                        Event::Number(n) => PathSegment::Index(n),
+                        _ => PathSegment::Any,
+                    })
+                })?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Skips the rest of a table whose [Event::TableStart] has been consumed.
+    fn skip_table(&mut self) -> Result<(), DeserializationError> {
+        let mut depth = 1;
+        let mut at_key = false;
+
+        while depth > 0 {
+            let Some((_, event)) = self.parser.skip_event()? else {
+                return Err(DeserializationErrorKind::UnclosedMap.into());
+            };
+
+            if at_key {
+                check_key(&event)?;
+                at_key = false;
+            }
+
+            match event {
+                Event::TableStart => depth += 1,
+                Event::TableEnd => depth -= 1,
+                Event::Key => {
+                    // Entries of nested tables count towards the limit,
+                    // just like they do when deserializing.
+                    if depth >= self.remaining_depth {
+                        return Err(DeserializationErrorKind::RecursionLimitExceeded.into());
+                    }
+                    at_key = true;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reports keys that Lua tables cannot hold.
+fn check_key(event: &Event) -> Result<(), DeserializationError> {
+    match *event {
+        Event::Nil => Err(TryFromLuaValueError::KeyCannotBeNull.into()),
+        Event::Number(n) if n.is_nan() => Err(TryFromLuaValueError::KeyCannotBeNan.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::error::DeserializationError;
+use std::borrow::Cow;
+use weakauras_codec_lua_value::{
+    LuaMap, LuaMapKey, LuaValue, LuaValueRef, MapBackend, NormalizationPolicy, PathSegment,
+    error::TryFromLuaValueError,
+};
+
+/// A type that [Deserializer](super::Deserializer) can produce
+/// from input that lives for `'s`.
+///
+/// This trait is sealed and implemented for [LuaValue] and [LuaValueRef].
+pub trait Value<'s>: private::Value<'s> {}
+
+impl Value<'_> for LuaValue {}
+impl<'s> Value<'s> for LuaValueRef<'s> {}
+
+pub(super) mod private {
+    use super::*;
+
+    pub trait Value<'s>: Sized {
+        type Key;
+
+        fn null() -> Self;
+        fn boolean(value: bool) -> Self;
+        fn number(value: f64) -> Self;
+        fn string(value: Cow<'s, [u8]>) -> Self;
+        fn is_null(&self) -> bool;
+
+        /// Fails if Lua tables cannot hold `self` as a key.
+        fn key(self) -> Result<Self::Key, DeserializationError>;
+        /// Returns the path segment of the entry that has `key` as its key.
+        fn path_segment(key: &Self::Key) -> PathSegment;
+        /// Expects `values` not to contain `nil`s.
+        fn table(
+            keys: Vec<Self::Key>,
+            values: Vec<Self>,
+            policy: NormalizationPolicy,
+            backend: MapBackend,
+        ) -> Self;
+    }
+}
+
+impl private::Value<'_> for LuaValue {
+    type Key = LuaMapKey;
+
+    #[inline(always)]
+    fn null() -> Self {
+        LuaValue::Null
+    }
+
+    #[inline(always)]
+    fn boolean(value: bool) -> Self {
+        LuaValue::Boolean(value)
+    }
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        LuaValue::Number(value)
+    }
+
+    #[inline(always)]
+    fn string(value: Cow<'_, [u8]>) -> Self {
+        LuaValue::String(value.into_owned().into())
+    }
+
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        LuaValue::is_null(self)
+    }
+
+    #[inline(always)]
+    fn key(self) -> Result<LuaMapKey, DeserializationError> {
+        Ok(LuaMapKey::try_from(self)?)
+    }
+
+    fn path_segment(key: &LuaMapKey) -> PathSegment {
+        match key.as_value() {
+            LuaValue::String(s) => PathSegment::Key(s.clone()),
+            LuaValue::Number(n) => PathSegment::Index(*n),
+            _ => PathSegment::Any,
+        }
+    }
+
+    fn table(
+        keys: Vec<LuaMapKey>,
+        values: Vec<Self>,
+        policy: NormalizationPolicy,
+        backend: MapBackend,
+    ) -> Self {
+        debug_assert_eq!(keys.len(), values.len());
+        let is_array = policy == NormalizationPolicy::PreferArrays
+            && keys.iter().enumerate().all(|(i, key)| {
+                if let LuaValue::Number(key) = key.as_value() {
+                    *key == (i + 1) as f64
+                } else {
+                    false
+                }
+            });
+
+        if is_array {
+            return LuaValue::Array(values);
+        }
+
+        let mut m = LuaMap::with_capacity_and_backend(keys.len(), backend);
+        m.extend(keys.into_iter().zip(values));
+
+        let mut m = LuaValue::Map(m);
+        // Might still be an array with holes.
+        m.normalize_shallow(policy);
+        m
+    }
+}
+
+impl<'s> private::Value<'s> for LuaValueRef<'s> {
+    type Key = Self;
+
+    #[inline(always)]
+    fn null() -> Self {
+        LuaValueRef::Null
+    }
+
+    #[inline(always)]
+    fn boolean(value: bool) -> Self {
+        LuaValueRef::Boolean(value)
+    }
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        LuaValueRef::Number(value)
+    }
+
+    #[inline(always)]
+    fn string(value: Cow<'s, [u8]>) -> Self {
+        LuaValueRef::String(value)
+    }
+
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        LuaValueRef::is_null(self)
+    }
+
+    #[inline(always)]
+    fn key(self) -> Result<Self, DeserializationError> {
+        match self {
+            LuaValueRef::Null => Err(TryFromLuaValueError::KeyCannotBeNull.into()),
+            LuaValueRef::Number(n) if n.is_nan() => {
+                Err(TryFromLuaValueError::KeyCannotBeNan.into())
+            }
+            key => Ok(key),
+        }
+    }
+
+    fn path_segment(key: &Self) -> PathSegment {
+        match key {
+            LuaValueRef::String(s) => PathSegment::from(&**s),
+            LuaValueRef::Number(n) => PathSegment::Index(*n),
+            _ => PathSegment::Any,
+        }
+    }
+
+    fn table(
+        keys: Vec<Self>,
+        values: Vec<Self>,
+        policy: NormalizationPolicy,
+        _backend: MapBackend,
+    ) -> Self {
+        debug_assert_eq!(keys.len(), values.len());
+        let is_array = policy == NormalizationPolicy::PreferArrays
+            && keys.iter().enumerate().all(|(i, key)| {
+                if let LuaValueRef::Number(key) = *key {
+                    key == (i + 1) as f64
+                } else {
+                    false
+                }
+            });
+
+        if is_array {
+            LuaValueRef::Array(values)
+        } else {
+            LuaValueRef::Map(keys.into_iter().zip(values).collect())
+        }
+    }
+}
@@ -0,0 +1,13 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+pub mod neon;
+
+#[allow(unused_imports)]
+use crate::escape::scalar;
+
+#[cfg(target_feature = "neon")]
+pub use neon::{find_delimiter, find_escape};
+
+#[cfg(not(target_feature = "neon"))]
+pub use scalar::{find_delimiter, find_escape};
@@ -0,0 +1,56 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::escape::scalar;
+use core::arch::aarch64::*;
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[target_feature(enable = "neon")]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    // Refer to the SSE2 version for a detailed explanation.
+    // NEON has no movemask, so the index is found by the scalar version.
+
+    let space = vdupq_n_u8(0x20);
+    let caret = vdupq_n_u8(0x5e);
+    let one = vdupq_n_u8(0x01);
+    let del = vdupq_n_u8(0x7f);
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { vld1q_u8(input.as_ptr().add(offset)) };
+
+        let low = vcleq_u8(src, space);
+        let is_caret = vceqq_u8(src, caret);
+        let is_tilde_or_del = vceqq_u8(vorrq_u8(src, one), del);
+
+        if vmaxvq_u8(vorrq_u8(vorrq_u8(low, is_caret), is_tilde_or_del)) != 0 {
+            return offset + scalar::find_escape(&input[offset..offset + 16]);
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_escape(&input[offset..])
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[target_feature(enable = "neon")]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    let caret = vdupq_n_u8(b'^');
+    let tilde = vdupq_n_u8(b'~');
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { vld1q_u8(input.as_ptr().add(offset)) };
+
+        if vmaxvq_u8(vorrq_u8(vceqq_u8(src, caret), vceqq_u8(src, tilde))) != 0 {
+            return offset + scalar::find_delimiter(&input[offset..offset + 16]);
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_delimiter(&input[offset..])
+}
@@ -0,0 +1,28 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+#[cfg(target_arch = "aarch64")]
+pub mod aarch64;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub mod x86_64;
+
+#[cfg(target_arch = "wasm32")]
+pub mod wasm32;
+
+#[cfg(target_arch = "aarch64")]
+pub use aarch64::{find_delimiter, find_escape};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub use x86_64::{find_delimiter, find_escape};
+
+#[cfg(target_arch = "wasm32")]
+pub use wasm32::{find_delimiter, find_escape};
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "wasm32"
+)))]
+pub use crate::escape::scalar::{find_delimiter, find_escape};
@@ -0,0 +1,13 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+pub mod simd128;
+
+#[allow(unused_imports)]
+use crate::escape::scalar;
+
+#[cfg(target_feature = "simd128")]
+pub use simd128::{find_delimiter, find_escape};
+
+#[cfg(not(target_feature = "simd128"))]
+pub use scalar::{find_delimiter, find_escape};
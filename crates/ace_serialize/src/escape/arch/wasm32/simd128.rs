@@ -0,0 +1,57 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::escape::scalar;
+use core::arch::wasm32::*;
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[target_feature(enable = "simd128")]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    // Refer to the SSE2 version for a detailed explanation.
+
+    let space = u8x16_splat(0x20);
+    let caret = u8x16_splat(0x5e);
+    let one = u8x16_splat(0x01);
+    let del = u8x16_splat(0x7f);
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { v128_load(input.as_ptr().add(offset).cast()) };
+
+        let low = u8x16_le(src, space);
+        let is_caret = u8x16_eq(src, caret);
+        let is_tilde_or_del = u8x16_eq(v128_or(src, one), del);
+
+        let mask = u8x16_bitmask(v128_or(v128_or(low, is_caret), is_tilde_or_del));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_escape(&input[offset..])
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[target_feature(enable = "simd128")]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    let caret = u8x16_splat(b'^');
+    let tilde = u8x16_splat(b'~');
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { v128_load(input.as_ptr().add(offset).cast()) };
+
+        let mask = u8x16_bitmask(v128_or(u8x16_eq(src, caret), u8x16_eq(src, tilde)));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_delimiter(&input[offset..])
+}
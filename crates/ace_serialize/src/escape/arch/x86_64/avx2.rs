@@ -0,0 +1,66 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::escape::scalar;
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[target_feature(enable = "avx2")]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    // Refer to the SSE2 version for a detailed explanation.
+
+    let space = _mm256_set1_epi8(0x20);
+    let caret = _mm256_set1_epi8(0x5e);
+    let one = _mm256_set1_epi8(0x01);
+    let del = _mm256_set1_epi8(0x7f);
+
+    let mut offset = 0;
+    while input.len() - offset >= 32 {
+        // SAFETY: There's at least 32 bytes of input left.
+        let src = unsafe { _mm256_loadu_si256(input.as_ptr().add(offset).cast()) };
+
+        let low = _mm256_cmpeq_epi8(_mm256_max_epu8(src, space), space);
+        let is_caret = _mm256_cmpeq_epi8(src, caret);
+        let is_tilde_or_del = _mm256_cmpeq_epi8(_mm256_or_si256(src, one), del);
+
+        let mask = _mm256_movemask_epi8(_mm256_or_si256(
+            _mm256_or_si256(low, is_caret),
+            is_tilde_or_del,
+        ));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 32;
+    }
+
+    offset + scalar::find_escape(&input[offset..])
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[target_feature(enable = "avx2")]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    let caret = _mm256_set1_epi8(b'^' as i8);
+    let tilde = _mm256_set1_epi8(b'~' as i8);
+
+    let mut offset = 0;
+    while input.len() - offset >= 32 {
+        // SAFETY: There's at least 32 bytes of input left.
+        let src = unsafe { _mm256_loadu_si256(input.as_ptr().add(offset).cast()) };
+
+        let mask = _mm256_movemask_epi8(_mm256_or_si256(
+            _mm256_cmpeq_epi8(src, caret),
+            _mm256_cmpeq_epi8(src, tilde),
+        ));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 32;
+    }
+
+    offset + scalar::find_delimiter(&input[offset..])
+}
@@ -0,0 +1,71 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+pub mod avx2;
+pub mod sse2;
+
+#[allow(unused_imports)]
+use crate::escape::scalar;
+#[allow(unused_imports)]
+use weakauras_codec_runtime_dispatch::unsafe_runtime_dispatch;
+
+#[cfg(target_feature = "avx2")]
+pub use avx2::{find_delimiter, find_escape};
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[cfg(all(target_feature = "sse2", not(target_feature = "avx2")))]
+#[inline(always)]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    unsafe_runtime_dispatch!(
+        find_escape,
+        usize,
+        (input: &[u8]),
+        is_x86_feature_detected,
+        ("avx2", avx2),
+        sse2,
+    )
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[cfg(all(target_feature = "sse2", not(target_feature = "avx2")))]
+#[inline(always)]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    unsafe_runtime_dispatch!(
+        find_delimiter,
+        usize,
+        (input: &[u8]),
+        is_x86_feature_detected,
+        ("avx2", avx2),
+        sse2,
+    )
+}
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[cfg(not(any(target_feature = "sse2", target_feature = "avx2")))]
+#[inline(always)]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    unsafe_runtime_dispatch!(
+        find_escape,
+        usize,
+        (input: &[u8]),
+        is_x86_feature_detected,
+        ("avx2", avx2),
+        ("sse2", sse2),
+        scalar,
+    )
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[cfg(not(any(target_feature = "sse2", target_feature = "avx2")))]
+#[inline(always)]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    unsafe_runtime_dispatch!(
+        find_delimiter,
+        usize,
+        (input: &[u8]),
+        is_x86_feature_detected,
+        ("avx2", avx2),
+        ("sse2", sse2),
+        scalar,
+    )
+}
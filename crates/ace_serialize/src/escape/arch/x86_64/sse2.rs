@@ -0,0 +1,63 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::escape::scalar;
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+#[target_feature(enable = "sse2")]
+pub unsafe fn find_escape(input: &[u8]) -> usize {
+    let space = _mm_set1_epi8(0x20);
+    let caret = _mm_set1_epi8(0x5e);
+    let one = _mm_set1_epi8(0x01);
+    let del = _mm_set1_epi8(0x7f);
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { _mm_loadu_si128(input.as_ptr().add(offset).cast()) };
+
+        // Unsigned `src <= 0x20`, i.e. control characters and spaces.
+        let low = _mm_cmpeq_epi8(_mm_max_epu8(src, space), space);
+        let is_caret = _mm_cmpeq_epi8(src, caret);
+        // `~` (0x7E) and DEL (0x7F) only differ in the lowest bit.
+        let is_tilde_or_del = _mm_cmpeq_epi8(_mm_or_si128(src, one), del);
+
+        let mask = _mm_movemask_epi8(_mm_or_si128(_mm_or_si128(low, is_caret), is_tilde_or_del));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_escape(&input[offset..])
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+#[target_feature(enable = "sse2")]
+pub unsafe fn find_delimiter(input: &[u8]) -> usize {
+    let caret = _mm_set1_epi8(b'^' as i8);
+    let tilde = _mm_set1_epi8(b'~' as i8);
+
+    let mut offset = 0;
+    while input.len() - offset >= 16 {
+        // SAFETY: There's at least 16 bytes of input left.
+        let src = unsafe { _mm_loadu_si128(input.as_ptr().add(offset).cast()) };
+
+        let mask = _mm_movemask_epi8(_mm_or_si128(
+            _mm_cmpeq_epi8(src, caret),
+            _mm_cmpeq_epi8(src, tilde),
+        ));
+        if mask != 0 {
+            return offset + mask.trailing_zeros() as usize;
+        }
+
+        offset += 16;
+    }
+
+    offset + scalar::find_delimiter(&input[offset..])
+}
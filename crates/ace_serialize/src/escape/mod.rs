@@ -0,0 +1,28 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+// No guarantees about following semver there.
+// Both modules are public for fuzzing.
+#[doc(hidden)]
+pub mod arch;
+#[doc(hidden)]
+pub mod scalar;
+
+/// Returns the offset of the first byte of `input` that has to be escaped
+/// in a serialized string, or `input.len()` if there is none.
+#[inline(always)]
+#[allow(unused_unsafe)]
+pub fn find_escape(input: &[u8]) -> usize {
+    // SAFETY: The implementation is chosen based on available CPU features.
+    unsafe { arch::find_escape(input) }
+}
+
+/// Returns the offset of the first `^` or `~` in `input`, i.e. the first byte
+/// that ends a serialized string or starts an escape sequence,
+/// or `input.len()` if there is none.
+#[inline(always)]
+#[allow(unused_unsafe)]
+pub fn find_delimiter(input: &[u8]) -> usize {
+    // SAFETY: The implementation is chosen based on available CPU features.
+    unsafe { arch::find_delimiter(input) }
+}
@@ -0,0 +1,24 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+/// Whether AceSerialize escapes `byte` in strings.
+#[inline(always)]
+pub fn needs_escape(byte: u8) -> bool {
+    matches!(byte, 0x00..=0x20 | 0x5E | 0x7E | 0x7F)
+}
+
+// Refer to the reexport for documentation, crate::escape::find_escape.
+pub fn find_escape(input: &[u8]) -> usize {
+    input
+        .iter()
+        .position(|&b| needs_escape(b))
+        .unwrap_or(input.len())
+}
+
+// Refer to the reexport for documentation, crate::escape::find_delimiter.
+pub fn find_delimiter(input: &[u8]) -> usize {
+    input
+        .iter()
+        .position(|&b| b == b'^' || b == b'~')
+        .unwrap_or(input.len())
+}
@@ -32,17 +32,20 @@
 //! fn main() -> Result<(), SerializationError> {
 //!     assert_eq!(
 //!         Serializer::serialize_one(&"Hello, world!".into(), None)?,
-//!         "^1^SHello,~`world!^^"
+//!         b"^1^SHello,~`world!^^"
 //!     );
 //!     Ok(())
 //! }
 //! ```
 //!
+//! `Serializer::serialize_one` returns bytes, since Lua strings are not always valid UTF-8.
+//! Earlier versions returned a `String`; `Serializer::serialize_one_to_string` still does.
+//!
 //! # Crate features
 //!
 //! * **lua-value-arbitrary** - Implement `arbitrary::Arbitrary` for [`LuaValue`]. **Disabled** by default.
-//! * **lua-value-fnv** - Use `fnv` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
-//! * **lua-value-indexmap** - Use `indexmap` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
+//! * **lua-value-fnv** - Make `fnv` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
+//! * **lua-value-indexmap** - Make `indexmap` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
 //! * **serde** - Allow serializing and deserializing [`LuaValue`] using `serde`. **Disabled** by default.
 
 #![deny(missing_docs)]
@@ -51,11 +54,21 @@
 pub mod deserialization;
 /// Error types.
 pub mod error;
+// No guarantees about following semver there.
+// Public for fuzzing.
+#[doc(hidden)]
+pub mod escape;
 pub(crate) mod macros;
 /// Serialization.
 pub mod serialization;
 
-pub use deserialization::Deserializer;
+pub use deserialization::{Deserializer, Parser};
 pub use error::*;
-pub use serialization::Serializer;
-pub use weakauras_codec_lua_value::LuaValue;
+pub use serialization::{Emitter, SerializeOptions, Serializer};
+pub use weakauras_codec_lua_value::{
+    LuaValue, LuaValueRef, MapBackend, NonFinitePolicy, NonFiniteReport, NormalizationPolicy,
+    PathSegment, ResourceLimits, Selector,
+};
+
+#[cfg(test)]
+mod tests;
@@ -11,7 +11,7 @@ macro_rules! check_recursion {
     ($self:ident.$counter:ident, $error:ident, $($body:tt)*) => {
         $self.$counter -= 1;
         if $self.$counter == 0 {
-            return Err($error::RecursionLimitExceeded);
+            return Err($error::RecursionLimitExceeded.into());
         }
 
         $($body)*
@@ -1,29 +1,32 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT
 
-use crate::{error::SerializationError, macros::check_recursion};
-use weakauras_codec_lua_value::LuaValue;
-
-fn f64_to_parts(v: f64) -> (u64, i16, i8) {
-    let bits = v.to_bits();
-    let sign: i8 = if bits >> 63 == 0 { 1 } else { -1 };
-    let mut exponent: i16 = ((bits >> 52) & 0x7ff) as i16;
-    let mantissa = if exponent == 0 {
-        (bits & 0xfffffffffffff) << 1
-    } else {
-        (bits & 0xfffffffffffff) | 0x10000000000000
-    };
-    exponent -= 1023 + 52;
-    (mantissa, exponent, sign)
-}
+mod emitter;
+mod number;
+mod options;
+
+use crate::{
+    deserialization::tonumber,
+    error::{SerializationError, WriteError},
+    escape,
+    macros::check_recursion,
+};
+use core::fmt;
+pub use emitter::Emitter;
+pub use options::SerializeOptions;
+use std::io;
+use weakauras_codec_lua_value::{LuaValue, NonFinitePolicy, format_g14};
+
+/// The amount of buffered output after which it is passed to the writer.
+const FLUSH_THRESHOLD: usize = 8 * 1024;
 
-fn write_integer_to_a_string<I>(string: &mut String, value: I)
+fn write_integer<I>(result: &mut Vec<u8>, value: I)
 where
     I: itoa::Integer,
 {
     let mut buffer = itoa::Buffer::new();
     let serialized = buffer.format(value);
-    string.push_str(serialized)
+    result.extend_from_slice(serialized.as_bytes())
 }
 
 /// A structure for serializing [LuaValues](LuaValue).
@@ -36,120 +39,366 @@ fn write_integer_to_a_string<I>(string: &mut String, value: I)
 /// fn main() -> Result<(), SerializationError> {
 ///     assert_eq!(
 ///         Serializer::serialize_one(&"Hello, world!".into(), None)?,
-///         "^1^SHello,~`world!^^"
+///         b"^1^SHello,~`world!^^"
 ///     );
 ///     Ok(())
 /// }
 /// ```
-pub struct Serializer {
+pub struct Serializer<'w> {
     remaining_depth: usize,
-    result: String,
+    result: Vec<u8>,
+    writer: Option<&'w mut dyn io::Write>,
+    float_buffer: String,
+    nan_policy: NonFinitePolicy,
+    infinity_policy: NonFinitePolicy,
 }
 
-impl Serializer {
+impl<'w> Serializer<'w> {
     /// Serialize a single value.
+    ///
+    /// Since Lua strings are bytes, the output is returned as bytes as well.
+    /// This used to return a [String]: use [Serializer::serialize_one_to_string]
+    /// to keep getting one.
     pub fn serialize_one(
         value: &LuaValue,
         approximate_len: Option<usize>,
-    ) -> Result<String, SerializationError> {
-        let mut serializer = Self {
-            remaining_depth: 128,
-            result: String::with_capacity(approximate_len.unwrap_or(1024)),
+    ) -> Result<Vec<u8>, SerializationError> {
+        let mut options = SerializeOptions::new();
+        options.approximate_len = approximate_len;
+        Self::serialize_one_with(value, options)
+    }
+
+    /// Serialize a single value into a [String].
+    ///
+    /// Fails with an [io::Error] of kind [io::ErrorKind::InvalidData] if the output
+    /// is not valid UTF-8, which happens when a string is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{Serializer, WriteError};
+    ///
+    /// fn main() -> Result<(), WriteError> {
+    ///     assert_eq!(
+    ///         Serializer::serialize_one_to_string(&"Hello, world!".into(), None)?,
+    ///         "^1^SHello,~`world!^^"
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_one_to_string(
+        value: &LuaValue,
+        approximate_len: Option<usize>,
+    ) -> Result<String, WriteError> {
+        let output = Self::serialize_one(value, approximate_len)?;
+        String::from_utf8(output).map_err(|_| invalid_utf8().into())
+    }
+
+    /// Serialize a single value using `options`.
+    pub fn serialize_one_with(
+        value: &LuaValue,
+        options: SerializeOptions,
+    ) -> Result<Vec<u8>, SerializationError> {
+        let capacity = options.approximate_len.unwrap_or(1024);
+        Self::serialize(value, capacity, options, None).map_err(|e| match e {
+            WriteError::SerializationError(e) => e,
+            WriteError::IoError(_) => unreachable!("there is no writer to fail"),
+        })
+    }
+
+    /// Serialize a single value into `writer`.
+    ///
+    /// The output is passed to the writer in chunks as it is produced,
+    /// instead of being held in memory as a whole.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{Serializer, WriteError};
+    ///
+    /// fn main() -> Result<(), WriteError> {
+    ///     let mut output = Vec::new();
+    ///     Serializer::serialize_one_into(&"Hello, world!".into(), &mut output)?;
+    ///
+    ///     assert_eq!(output, b"^1^SHello,~`world!^^");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_one_into<W: io::Write>(value: &LuaValue, writer: W) -> Result<(), WriteError> {
+        Self::serialize_one_into_with(value, writer, SerializeOptions::new())
+    }
+
+    /// Serialize a single value into `writer` using `options`, see [Serializer::serialize_one_into].
+    ///
+    /// The approximate length is ignored, since the output is flushed in chunks.
+    pub fn serialize_one_into_with<W: io::Write>(
+        value: &LuaValue,
+        mut writer: W,
+        options: SerializeOptions,
+    ) -> Result<(), WriteError> {
+        Serializer::serialize(value, FLUSH_THRESHOLD * 2, options, Some(&mut writer)).map(drop)
+    }
+
+    /// Serialize a single value into `writer`, see [Serializer::serialize_one_into].
+    ///
+    /// Fails with an [io::Error] of kind [io::ErrorKind::InvalidData] if the output
+    /// is not valid UTF-8, which happens when a string is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_ace_serialize::{Serializer, WriteError};
+    ///
+    /// fn main() -> Result<(), WriteError> {
+    ///     let mut output = String::new();
+    ///     Serializer::serialize_one_into_fmt(&"Hello, world!".into(), &mut output)?;
+    ///
+    ///     assert_eq!(output, "^1^SHello,~`world!^^");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_one_into_fmt<W: fmt::Write>(
+        value: &LuaValue,
+        writer: W,
+    ) -> Result<(), WriteError> {
+        let mut adapter = FmtAdapter {
+            writer,
+            incomplete: Vec::new(),
         };
 
-        serializer.result.push_str("^1");
+        Self::serialize_one_into(value, &mut adapter)?;
+        if adapter.incomplete.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_utf8().into())
+        }
+    }
+
+    /// Serializes `value` into `writer` if there is one, or into the returned buffer otherwise.
+    fn serialize(
+        value: &LuaValue,
+        capacity: usize,
+        options: SerializeOptions,
+        writer: Option<&'w mut dyn io::Write>,
+    ) -> Result<Vec<u8>, WriteError> {
+        let mut serializer = Self::new(capacity, writer);
+        serializer.nan_policy = options.nan_policy;
+        serializer.infinity_policy = options.infinity_policy;
+
+        serializer.result.extend_from_slice(b"^1");
         serializer.serialize_helper(value)?;
-        serializer.result.push_str("^^");
+        serializer.result.extend_from_slice(b"^^");
+
+        if let Some(writer) = serializer.writer {
+            writer.write_all(&serializer.result)?;
+            serializer.result.clear();
+        }
 
         Ok(serializer.result)
     }
 
-    fn serialize_helper(&mut self, value: &LuaValue) -> Result<(), SerializationError> {
+    fn new(capacity: usize, writer: Option<&'w mut dyn io::Write>) -> Self {
+        Self {
+            remaining_depth: 128,
+            result: Vec::with_capacity(capacity),
+            writer,
+            float_buffer: String::new(),
+            nan_policy: NonFinitePolicy::Error,
+            infinity_policy: NonFinitePolicy::Write,
+        }
+    }
+
+    fn flush_if_needed(&mut self) -> Result<(), WriteError> {
+        if self.result.len() >= FLUSH_THRESHOLD {
+            if let Some(writer) = self.writer.as_mut() {
+                writer.write_all(&self.result)?;
+                self.result.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn serialize_helper(&mut self, value: &LuaValue) -> Result<(), WriteError> {
+        self.flush_if_needed()?;
+
         match *value {
-            LuaValue::Null => self.result.push_str("^Z"),
-            LuaValue::Boolean(b) => self.result.push_str(if b { "^B" } else { "^b" }),
+            LuaValue::Null => self.result.extend_from_slice(b"^Z"),
+            LuaValue::Boolean(b) => self.result.extend_from_slice(if b { b"^B" } else { b"^b" }),
             LuaValue::String(ref s) => {
-                self.result.push_str("^S");
+                self.result.extend_from_slice(b"^S");
                 self.serialize_string(s)
             }
-            LuaValue::Number(n) => self.serialize_number(n)?,
+            LuaValue::Number(n) => match self.apply_non_finite_policy(n)? {
+                Some(n) => self.serialize_number(n),
+                None => self.result.extend_from_slice(b"^Z"),
+            },
             LuaValue::Array(ref v) => {
                 self.result.reserve(v.len() * 6 + 4);
 
-                self.result.push_str("^T");
+                self.result.extend_from_slice(b"^T");
+                // Holes are skipped, just like `pairs()` does in Lua.
                 for (value, index) in v.iter().zip(1..) {
-                    self.serialize_number(index as f64)?;
-                    self.serialize_helper(value)?;
+                    if self.is_nil(value) {
+                        continue;
+                    }
+
+                    check_recursion!(self, SerializationError, {
+                        self.serialize_number(index as f64);
+                        self.serialize_helper(value)?;
+                    });
                 }
-                self.result.push_str("^t");
+                self.result.extend_from_slice(b"^t");
             }
             LuaValue::Map(ref m) => {
                 self.result.reserve(m.len() * 6 + 4);
 
-                self.result.push_str("^T");
+                self.result.extend_from_slice(b"^T");
+                // Entries whose key or value become `nil` are left out.
                 for (key, value) in m.iter() {
+                    if self.is_nil(key.as_value()) || self.is_nil(value) {
+                        continue;
+                    }
+
                     check_recursion!(self, SerializationError, {
                         self.serialize_helper(key.as_value())?;
                         self.serialize_helper(value)?;
                     });
                 }
-                self.result.push_str("^t");
+                self.result.extend_from_slice(b"^t");
             }
         }
 
         Ok(())
     }
 
-    fn serialize_number(&mut self, value: f64) -> Result<(), SerializationError> {
+    /// Applies the policy for non-finite numbers, returning `None` if `value` becomes `nil`.
+    fn apply_non_finite_policy(&self, value: f64) -> Result<Option<f64>, SerializationError> {
+        let policy = if value.is_nan() {
+            self.nan_policy
+        } else if value.is_infinite() {
+            self.infinity_policy
+        } else {
+            return Ok(Some(value));
+        };
+
+        match policy {
+            NonFinitePolicy::Error if value.is_nan() => Err(SerializationError::NanEncountered),
+            NonFinitePolicy::Error => Err(SerializationError::InfinityEncountered),
+            NonFinitePolicy::ReplaceWithNil => Ok(None),
+            NonFinitePolicy::ReplaceWithZero => Ok(Some(0.0)),
+            NonFinitePolicy::Write | NonFinitePolicy::PreserveBits => Ok(Some(value)),
+        }
+    }
+
+    /// Whether `value` is written as `nil`.
+    fn is_nil(&self, value: &LuaValue) -> bool {
+        match *value {
+            LuaValue::Null => true,
+            LuaValue::Number(n) if n.is_nan() => self.nan_policy == NonFinitePolicy::ReplaceWithNil,
+            LuaValue::Number(n) if n.is_infinite() => {
+                self.infinity_policy == NonFinitePolicy::ReplaceWithNil
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes a number the way AceSerialize does: as `tostring(value)` if that converts back
+    /// into `value`, and as the mantissa and the exponent produced by `frexp` otherwise.
+    fn serialize_number(&mut self, value: f64) {
         if value.is_nan() {
-            return Err(SerializationError::NanEncountered);
+            // `frexp` returns NaNs as they are, and `%.0f` prints them as `nan` or `-nan`.
+            self.result.extend_from_slice(if value.is_sign_negative() {
+                b"^F-nan^f-53"
+            } else {
+                b"^Fnan^f-53"
+            });
         } else if !value.is_finite() {
-            self.result.push_str("^N");
+            self.result.extend_from_slice(b"^N");
             self.result
-                .push_str(if value > 0.0 { "1.#INF" } else { "-1.#INF" })
+                .extend_from_slice(if value > 0.0 { b"1.#INF" } else { b"-1.#INF" })
         } else {
-            let mut buffer = ryu::Buffer::new();
-            let str_value = buffer.format_finite(value);
+            format_g14(value, &mut self.float_buffer);
 
-            if str_value.parse::<f64>().unwrap() == value {
-                self.result.reserve(str_value.len() + 2);
-                self.result.push_str("^N");
-                self.result.push_str(str_value);
+            if tonumber(self.float_buffer.as_bytes()) == Some(value) {
+                self.result.reserve(self.float_buffer.len() + 2);
+                self.result.extend_from_slice(b"^N");
+                self.result.extend_from_slice(self.float_buffer.as_bytes());
             } else {
-                let (mantissa, exponent, sign) = f64_to_parts(value);
-                self.result.push_str("^F");
-                if sign < 0 {
-                    self.result.push('-');
-                }
-                write_integer_to_a_string(&mut self.result, mantissa);
-                self.result.push_str("^f");
-                write_integer_to_a_string(&mut self.result, exponent);
+                let (mantissa, exponent) = number::frexp_parts(value);
+                self.result.extend_from_slice(b"^F");
+                write_integer(&mut self.result, mantissa);
+                self.result.extend_from_slice(b"^f");
+                write_integer(&mut self.result, exponent);
             }
         }
-
-        Ok(())
     }
 
-    fn serialize_string(&mut self, value: &str) {
+    fn serialize_string(&mut self, value: &[u8]) {
         self.result.reserve(value.len());
 
         let mut copy_from = 0;
-        for (i, byte) in value.bytes().enumerate() {
+        loop {
+            let i = copy_from + escape::find_escape(&value[copy_from..]);
+            self.result.extend_from_slice(&value[copy_from..i]);
+
+            let Some(&byte) = value.get(i) else {
+                break;
+            };
             let replacement = match byte {
                 v @ 0x00..=0x1D | v @ 0x1F..=0x20 => v + 64,
                 0x1E => 0x7A,
                 0x5E => 0x7D,
                 0x7E => 0x7C,
                 0x7F => 0x7B,
-                _ => continue,
+                _ => unreachable!("only bytes that need escaping are found"),
             };
 
-            self.result.push_str(&value[copy_from..i]);
-            self.result.push('~');
-            self.result.push(replacement as char);
+            self.result.push(b'~');
+            self.result.push(replacement);
             copy_from = i + 1;
         }
+    }
+}
+
+/// Passes UTF-8 output to a [fmt::Write], holding back incomplete characters.
+struct FmtAdapter<W> {
+    writer: W,
+    incomplete: Vec<u8>,
+}
+
+impl<W: fmt::Write> io::Write for FmtAdapter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let data = if self.incomplete.is_empty() {
+            buf
+        } else {
+            self.incomplete.extend_from_slice(buf);
+            &self.incomplete
+        };
 
-        self.result.push_str(&value[copy_from..]);
+        let (valid, rest) = match core::str::from_utf8(data) {
+            Ok(valid) => (valid, &[][..]),
+            // The data ends in the middle of a character.
+            Err(e) if e.error_len().is_none() => {
+                let (valid, rest) = data.split_at(e.valid_up_to());
+                (core::str::from_utf8(valid).unwrap(), rest)
+            }
+            Err(_) => return Err(invalid_utf8()),
+        };
+
+        self.writer
+            .write_str(valid)
+            .map_err(|_| io::Error::other("formatter error"))?;
+        self.incomplete = rest.to_vec();
+
+        Ok(buf.len())
     }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "output is not valid UTF-8")
 }
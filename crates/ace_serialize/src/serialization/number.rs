@@ -0,0 +1,25 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+/// Splits a finite non-zero `value` into `mantissa * 2^exponent` the way AceSerialize does,
+/// i.e. `frexp(value)` with the mantissa scaled by `2^53`, so that it has 53 significant bits.
+pub(crate) fn frexp_parts(value: f64) -> (i64, i32) {
+    let bits = value.to_bits();
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & 0xf_ffff_ffff_ffff;
+
+    let (mantissa, exponent) = if biased_exponent == 0 {
+        // Subnormals are normalized by `frexp`.
+        let shift = fraction.leading_zeros() - 11;
+        (fraction << shift, -1074 - shift as i32)
+    } else {
+        (fraction | 1 << 52, biased_exponent - 1075)
+    };
+
+    let mantissa = mantissa as i64;
+    if value < 0.0 {
+        (-mantissa, exponent)
+    } else {
+        (mantissa, exponent)
+    }
+}
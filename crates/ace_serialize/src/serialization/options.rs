@@ -0,0 +1,73 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use weakauras_codec_lua_value::NonFinitePolicy;
+
+/// Options for [Serializer](super::Serializer).
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_ace_serialize::{
+///     LuaValue, NonFinitePolicy, SerializationError, SerializeOptions, Serializer,
+/// };
+///
+/// let value = LuaValue::Array(vec![1.0.into(), f64::NAN.into()]);
+///
+/// assert_eq!(
+///     Serializer::serialize_one_with(&value, SerializeOptions::new()),
+///     Err(SerializationError::NanEncountered)
+/// );
+///
+/// let options = SerializeOptions::new().with_nan_policy(NonFinitePolicy::ReplaceWithNil);
+/// assert_eq!(
+///     Serializer::serialize_one_with(&value, options).unwrap(),
+///     b"^1^T^N1^N1^t^^"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SerializeOptions {
+    pub(super) approximate_len: Option<usize>,
+    pub(super) nan_policy: NonFinitePolicy,
+    pub(super) infinity_policy: NonFinitePolicy,
+}
+
+impl SerializeOptions {
+    /// Create options with default values.
+    pub fn new() -> Self {
+        Self {
+            approximate_len: None,
+            nan_policy: NonFinitePolicy::Error,
+            infinity_policy: NonFinitePolicy::Write,
+        }
+    }
+
+    /// Set the expected length of the output, used to preallocate the buffer.
+    pub fn with_approximate_len(mut self, value: usize) -> Self {
+        self.approximate_len = Some(value);
+        self
+    }
+
+    /// Set the policy for serializing NaNs. The default is [NonFinitePolicy::Error],
+    /// because AceSerialize itself produces output that cannot be deserialized.
+    ///
+    /// NaN keys are always rejected with
+    /// [SerializationError::UnserializableKey](crate::SerializationError::UnserializableKey)
+    /// unless they are replaced.
+    pub fn with_nan_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
+
+    /// Set the policy for serializing infinities. The default is [NonFinitePolicy::Write].
+    pub fn with_infinity_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.infinity_policy = policy;
+        self
+    }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
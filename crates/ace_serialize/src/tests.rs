@@ -0,0 +1,707 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    DeserializationErrorKind, Deserializer, EmitError, Emitter, LuaValue, LuaValueRef,
+    NonFinitePolicy, Parser, PathSegment, Selector, SerializationError, SerializeOptions,
+    Serializer, WriteError, deserialization::Event, escape,
+};
+use std::{borrow::Cow, io};
+use weakauras_codec_lua_value::{LuaMapKey, ResourceLimits, error::ResourceLimitExceeded};
+
+#[test]
+fn holes_survive_decode_and_encode() {
+    // {1, nil, 3}
+    let input = "^1^T^N1^N1^N3^N3^t^^";
+
+    let value = Deserializer::from_str(input)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let LuaValue::Array(ref v) = value else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        *v,
+        [LuaValue::from(1.0), LuaValue::Null, LuaValue::from(3.0)]
+    );
+
+    let serialized = Serializer::serialize_one(&value, None).unwrap();
+    let value = Deserializer::from_bytes(&serialized)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(value, LuaValue::Array(ref w) if w == v));
+}
+
+#[test]
+fn explicit_nulls_are_holes() {
+    let value = Deserializer::from_str("^1^T^N1^Sa^N2^Z^N3^Sc^N4^Z^t^^")
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let LuaValue::Array(ref v) = value else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        *v,
+        [LuaValue::from("a"), LuaValue::Null, LuaValue::from("c")]
+    );
+}
+
+#[test]
+fn sparse_tables_stay_maps() {
+    let value = Deserializer::from_str("^1^T^N1^Sa^N10^Sb^t^^")
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(value, LuaValue::Map(ref m) if m.len() == 2));
+}
+
+#[test]
+fn byte_strings_survive_roundtrip() {
+    let bytes: &[u8] = b"Interface\\Icons\\\xff\xfe^~\x7f\x00";
+    let serialized = Serializer::serialize_one(&bytes.into(), None).unwrap();
+
+    let value = Deserializer::from_bytes(&serialized)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(value, LuaValue::String(ref s) if s.as_bytes() == bytes));
+}
+
+#[test]
+fn escape_scanning_matches_scalar() {
+    let check = |input: &[u8]| {
+        assert_eq!(
+            escape::find_escape(input),
+            escape::scalar::find_escape(input)
+        );
+        assert_eq!(
+            escape::find_delimiter(input),
+            escape::scalar::find_delimiter(input)
+        );
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            use crate::escape::arch::x86_64::{avx2, sse2};
+
+            if std::is_x86_feature_detected!("sse2") {
+                assert_eq!(
+                    unsafe { sse2::find_escape(input) },
+                    escape::scalar::find_escape(input)
+                );
+                assert_eq!(
+                    unsafe { sse2::find_delimiter(input) },
+                    escape::scalar::find_delimiter(input)
+                );
+            }
+            if std::is_x86_feature_detected!("avx2") {
+                assert_eq!(
+                    unsafe { avx2::find_escape(input) },
+                    escape::scalar::find_escape(input)
+                );
+                assert_eq!(
+                    unsafe { avx2::find_delimiter(input) },
+                    escape::scalar::find_delimiter(input)
+                );
+            }
+        }
+    };
+
+    // Every byte at every position of the SIMD blocks and the scalar tail.
+    for byte in 0..=u8::MAX {
+        for at in 0..70 {
+            let mut input = vec![b'a'; 70];
+            input[at] = byte;
+            check(&input);
+            check(&input[..at + 1]);
+        }
+    }
+    check(b"");
+
+    let bytes: Vec<u8> = (0..=u8::MAX).cycle().take(1000).collect();
+    let serialized = Serializer::serialize_one(&bytes.as_slice().into(), None).unwrap();
+    let value = Deserializer::from_bytes(&serialized)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(value, LuaValue::String(ref s) if s.as_bytes() == bytes));
+}
+
+#[test]
+fn numbers_are_written_like_ace_serialize() {
+    for (value, expected) in [
+        (1.0, "^N1"),
+        (-0.0, "^N-0"),
+        (0.1, "^N0.1"),
+        (-1.5e-7, "^N-1.5e-07"),
+        (1e301, "^N1e+301"),
+        (f64::INFINITY, "^N1.#INF"),
+        (f64::NEG_INFINITY, "^N-1.#INF"),
+        (1.0 / 3.0, "^F6004799503160661^f-54"),
+        (-123456789012345.0, "^F-7901234496790080^f-6"),
+    ] {
+        let serialized = Serializer::serialize_one(&value.into(), None).unwrap();
+        assert_eq!(serialized, format!("^1{expected}^^").as_bytes());
+
+        let deserialized = Deserializer::from_bytes(&serialized)
+            .deserialize_first()
+            .unwrap();
+        assert!(
+            matches!(deserialized, Some(LuaValue::Number(n)) if n.to_bits() == value.to_bits())
+        );
+    }
+
+    // Subnormals are normalized, just like `frexp` does. Lua reads them back as zeros,
+    // because `2^-1075` underflows, and so does the deserializer.
+    let serialized = Serializer::serialize_one(&(f64::MIN_POSITIVE / 2.0).into(), None).unwrap();
+    assert_eq!(serialized, b"^1^F4503599627370496^f-1075^^");
+    assert_eq!(
+        Deserializer::from_bytes(&serialized).deserialize_first(),
+        Ok(Some(0.0.into()))
+    );
+}
+
+#[test]
+fn numbers_are_read_like_tonumber() {
+    let parse = |input: &[u8]| {
+        let input = [&b"^1"[..], input, b"^^"].concat();
+        match Deserializer::from_bytes(&input).deserialize_first() {
+            Ok(Some(LuaValue::Number(n))) => Ok(n),
+            Ok(v) => panic!("expected a number, got {v:?}"),
+            Err(e) => Err(e),
+        }
+    };
+
+    for (input, expected) in [
+        (&b"^N 0x10\t"[..], 16.0),
+        (b"^N1e2", 100.0),
+        (b"^N.5", 0.5),
+        (b"^N5.", 5.0),
+        (b"^N0x1p-2", 0.25),
+        (b"^N0X.8", 0.5),
+        (b"^N-0x1.8P1", -3.0),
+        (b"^NInfinity", f64::INFINITY),
+        (b"^N-inf", f64::NEG_INFINITY),
+        (b"^N1e400", f64::INFINITY),
+        // Hexadecimal numbers are rounded to nearest, ties to even.
+        (b"^N0x1.00000000000008p0", 1.0),
+        (b"^N0x1.000000000000081p0", 1.0 + f64::EPSILON),
+        (b"^N0x1p-1075", 0.0),
+        (b"^N0x3p-1076", f64::from_bits(1)),
+        // Strings end at the first NUL for `tonumber`.
+        (b"^N1\0x", 1.0),
+        (b"^F 3 ^f0x2", 12.0),
+    ] {
+        assert_eq!(parse(input), Ok(expected), "{}", input.escape_ascii());
+    }
+
+    for input in [&b"^Nnan"[..], b"^N-NaN", b"^Nnan(ind)", b"^F-nan^f-53"] {
+        assert!(parse(input).unwrap().is_nan(), "{}", input.escape_ascii());
+    }
+
+    for input in [
+        &b"^N"[..],
+        b"^N ",
+        b"^N0x",
+        b"^N1e",
+        b"^N--1",
+        b"^N1 2",
+        b"^Nx1",
+        b"^N0x1p",
+        b"^Nnan(",
+        b"^N1.#IND",
+        b"^F1^fa",
+    ] {
+        assert_eq!(
+            parse(input).map_err(|e| e.kind()),
+            Err(DeserializationErrorKind::InvalidFloatNumber),
+            "{}",
+            input.escape_ascii()
+        );
+    }
+}
+
+#[test]
+fn non_finite_policies_are_applied() {
+    let serialize = |value: &LuaValue, policy| {
+        let options = SerializeOptions::new()
+            .with_nan_policy(policy)
+            .with_infinity_policy(policy);
+        Serializer::serialize_one_with(value, options)
+    };
+
+    let array = LuaValue::Array(vec![
+        f64::NAN.into(),
+        (-f64::NAN).into(),
+        f64::INFINITY.into(),
+    ]);
+    let map = LuaValue::Map(
+        [(f64::NEG_INFINITY, "a"), (1.0, "b")]
+            .into_iter()
+            .map(|(k, v)| (LuaMapKey::try_from(LuaValue::from(k)).unwrap(), v.into()))
+            .collect(),
+    );
+
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::Error),
+        Err(SerializationError::NanEncountered)
+    );
+    assert_eq!(
+        serialize(&map, NonFinitePolicy::Error),
+        Err(SerializationError::InfinityEncountered)
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::ReplaceWithNil).unwrap(),
+        b"^1^T^t^^"
+    );
+    assert_eq!(
+        serialize(&map, NonFinitePolicy::ReplaceWithNil).unwrap(),
+        b"^1^T^N1^Sb^t^^"
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::ReplaceWithZero).unwrap(),
+        b"^1^T^N1^N0^N2^N0^N3^N0^t^^"
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::Write).unwrap(),
+        b"^1^T^N1^Fnan^f-53^N2^F-nan^f-53^N3^N1.#INF^t^^"
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::PreserveBits),
+        serialize(&array, NonFinitePolicy::Write)
+    );
+
+    // Only the default of the NaN policy rejects anything.
+    assert_eq!(
+        Serializer::serialize_one(&f64::INFINITY.into(), None).unwrap(),
+        b"^1^N1.#INF^^"
+    );
+    assert_eq!(
+        Serializer::serialize_one(&f64::NAN.into(), None),
+        Err(SerializationError::NanEncountered)
+    );
+
+    let mut emitter = Emitter::new(Vec::new()).with_nan_policy(NonFinitePolicy::ReplaceWithNil);
+    emitter.begin_table(2, 0).unwrap();
+    emitter.number(f64::NAN).unwrap().number(1.0).unwrap();
+    emitter.end_table().unwrap();
+    assert_eq!(emitter.finish().unwrap(), b"^1^T^N2^N1^t^^");
+}
+
+#[test]
+fn non_finite_numbers_are_reported() {
+    let input = b"^1^T^N1^Fnan^f-53^N2^F-nan^f-53^N3^N1.#INF^N4^N-1.#INF^N5^N-inf^t^^";
+    let mut deserializer = Deserializer::from_bytes(input);
+    assert!(deserializer.non_finite_report().is_empty());
+
+    deserializer.deserialize_next().unwrap();
+    let report = deserializer.non_finite_report();
+    assert_eq!(report.nan(), 2);
+    assert_eq!(report.positive_infinity(), 1);
+    assert_eq!(report.negative_infinity(), 2);
+
+    let mut deserializer = Deserializer::from_bytes(input).into_borrowed();
+    deserializer.deserialize_next().unwrap();
+    assert_eq!(deserializer.non_finite_report(), report);
+}
+
+#[test]
+fn wide_tables_are_rejected() {
+    let limits = ResourceLimits::default().with_max_collection_len(2);
+
+    assert!(
+        Deserializer::from_str("^1^T^N1^Sa^N2^Sb^t^^")
+            .with_limits(limits)
+            .deserialize_first()
+            .is_ok()
+    );
+    assert_eq!(
+        Deserializer::from_str("^1^T^N1^Sa^N2^Sb^N3^Sc^t^^")
+            .with_limits(limits)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::CollectionLength
+        ))
+    );
+}
+
+#[test]
+fn nodes_are_counted_across_values() {
+    let limits = ResourceLimits::default().with_max_nodes(3);
+
+    assert_eq!(
+        Deserializer::from_str("^1^B^B^B^B^^")
+            .with_limits(limits)
+            .deserialize_all()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::Nodes
+        ))
+    );
+}
+
+#[test]
+fn streamed_output_matches_buffered() {
+    let value = LuaValue::Array(
+        (0..2000)
+            .map(|i| LuaValue::Array(vec![format!("string ~{i}").into(), (i as f64 / 8.0).into()]))
+            .collect(),
+    );
+    let expected = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut output = Vec::new();
+    Serializer::serialize_one_into(&value, &mut output).unwrap();
+    assert_eq!(output, expected);
+
+    let mut output = String::new();
+    Serializer::serialize_one_into_fmt(&value, &mut output).unwrap();
+    assert_eq!(output.as_bytes(), expected);
+}
+
+#[test]
+fn formatter_output_must_be_utf8() {
+    let mut output = String::new();
+    Serializer::serialize_one_into_fmt(&"Żółw".into(), &mut output).unwrap();
+    assert_eq!(output, "^1^SŻółw^^");
+
+    let bytes: &[u8] = b"\xff\xfe";
+    assert!(matches!(
+        Serializer::serialize_one_into_fmt(&bytes.into(), &mut String::new()),
+        Err(WriteError::IoError(e)) if e.kind() == io::ErrorKind::InvalidData
+    ));
+
+    assert_eq!(
+        Serializer::serialize_one_to_string(&"Żółw".into(), None).unwrap(),
+        "^1^SŻółw^^"
+    );
+    assert!(matches!(
+        Serializer::serialize_one_to_string(&bytes.into(), None),
+        Err(WriteError::IoError(e)) if e.kind() == io::ErrorKind::InvalidData
+    ));
+}
+
+#[test]
+fn parser_describes_tables() {
+    let input = b"^1^T^N1^T^t^Sa~`b^F5^f-1^t^B^^";
+    let events: Vec<_> = Parser::new(input).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(
+        events,
+        [
+            (2, Event::TableStart),
+            (4, Event::Key),
+            (4, Event::Number(1.0)),
+            (7, Event::TableStart),
+            (9, Event::TableEnd),
+            (11, Event::Key),
+            (11, Event::String(b"a b".into())),
+            (17, Event::Number(2.5)),
+            (24, Event::TableEnd),
+            (26, Event::Bool(true)),
+        ]
+    );
+}
+
+#[test]
+fn parser_reports_errors_once() {
+    let mut parser = Parser::new(b"^1^T^N1^t^^");
+    assert_eq!(
+        parser.nth(3).map(|r| r.map_err(|e| e.kind())),
+        Some(Err(DeserializationErrorKind::MapMissingValue))
+    );
+    assert_eq!(parser.next(), None);
+
+    assert_eq!(
+        Parser::new(b"^1^T^^")
+            .nth(2)
+            .map(|r| r.map_err(|e| e.kind())),
+        Some(Err(DeserializationErrorKind::UnclosedMap))
+    );
+    assert_eq!(
+        Parser::new(b"^2^^").next().map(|r| r.map_err(|e| e.kind())),
+        Some(Err(DeserializationErrorKind::InvalidPrefix))
+    );
+}
+
+#[test]
+fn emitted_output_matches_serializer() {
+    let value = LuaValue::Array(
+        (0..2000)
+            .map(|i| {
+                LuaValue::Array(vec![
+                    format!("string ~{i}").into(),
+                    LuaValue::Null,
+                    (i as f64 / 8.0).into(),
+                ])
+            })
+            .collect(),
+    );
+    let expected = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(2000, 0).unwrap();
+    for i in 0..2000 {
+        emitter
+            .begin_table(3, 0)
+            .unwrap()
+            .string(format!("string ~{i}"))
+            .unwrap()
+            .nil()
+            .unwrap()
+            .number(i as f64 / 8.0)
+            .unwrap()
+            .end_table()
+            .unwrap();
+    }
+    emitter.end_table().unwrap();
+    assert_eq!(emitter.finish().unwrap(), expected);
+}
+
+#[test]
+fn emitter_checks_structure() {
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(1, 0).unwrap();
+    assert!(matches!(emitter.key(), Err(EmitError::CountMismatch)));
+    assert!(matches!(emitter.end_table(), Err(EmitError::CountMismatch)));
+    emitter.boolean(false).unwrap();
+    assert!(matches!(emitter.nil(), Err(EmitError::CountMismatch)));
+    emitter.end_table().unwrap();
+    assert!(matches!(
+        emitter.end_table(),
+        Err(EmitError::UnexpectedTableEnd)
+    ));
+    assert!(matches!(emitter.key(), Err(EmitError::UnexpectedKey)));
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(0, 1).unwrap();
+    assert!(matches!(emitter.string("a"), Err(EmitError::MissingKey)));
+    emitter.key().unwrap();
+    assert!(matches!(
+        emitter.nil(),
+        Err(EmitError::SerializationError(
+            SerializationError::UnserializableKey
+        ))
+    ));
+    assert!(matches!(
+        emitter.number(f64::NAN),
+        Err(EmitError::SerializationError(
+            SerializationError::NanEncountered
+        ))
+    ));
+    emitter.string("a").unwrap().nil().unwrap();
+    assert!(matches!(emitter.finish(), Err(EmitError::UnclosedTable)));
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(0, 1).unwrap().key().unwrap();
+    emitter
+        .string("a")
+        .unwrap()
+        .nil()
+        .unwrap()
+        .end_table()
+        .unwrap();
+    assert_eq!(emitter.finish().unwrap(), b"^1^T^Sa^Z^t^^");
+}
+
+/// `{d = {id = "Aura", ...}, c = {{id = "a", ...}, {id = "b", ...}}}` with escaped strings
+/// and nested tables in the skipped parts.
+const EXPORT: &str = "^1^T^Sd^T^Sid^SAura^SregionType^Sicon^Sload^T^Suse~`class^B^t^t\
+                      ^Sc^T^N1^T^Sid^Sa^Sx^T^N1^F5^f-1^t^t^N2^T^Sid^S~}b^t^t^t^^";
+
+#[test]
+fn selecting_everything_matches_deserialization() {
+    let full = Deserializer::from_str(EXPORT).deserialize_first().unwrap();
+    let selected = Deserializer::from_str(EXPORT)
+        .deserialize_selected(&Selector::new().with_path(["d"]).with_path(["c"]))
+        .unwrap();
+
+    assert_eq!(format!("{selected:?}"), format!("{full:?}"));
+}
+
+#[test]
+fn only_selected_values_are_built() {
+    let selector = Selector::new().with_path(["d", "id"]).with_path([
+        PathSegment::from("c"),
+        PathSegment::Any,
+        PathSegment::from("id"),
+    ]);
+    let value = Deserializer::from_str(EXPORT)
+        .deserialize_selected(&selector)
+        .unwrap();
+    let expected =
+        Deserializer::from_str("^1^T^Sd^T^Sid^SAura^t^Sc^T^N1^T^Sid^Sa^t^N2^T^Sid^S~}b^t^t^t^^")
+            .deserialize_first()
+            .unwrap();
+
+    assert_eq!(format!("{value:?}"), format!("{expected:?}"));
+}
+
+#[test]
+fn skipped_values_are_validated() {
+    for (input, error) in [
+        (
+            "^1^T^Sa^S~~^t^^",
+            DeserializationErrorKind::InvalidEscapeCharacter,
+        ),
+        (
+            "^1^T^Sa^T^Z^B^t^t^^",
+            DeserializationErrorKind::InvalidMapKeyType,
+        ),
+        (
+            "^1^T^Sa^T^Sb^t^t^^",
+            DeserializationErrorKind::MapMissingValue,
+        ),
+        ("^1^T^Sa^T^t^^", DeserializationErrorKind::UnclosedMap),
+    ] {
+        assert_eq!(
+            Deserializer::from_str(input)
+                .deserialize_selected(&Selector::new())
+                .map_err(|e| e.kind()),
+            Err(error)
+        );
+    }
+}
+
+#[test]
+fn borrowed_values_match_owned_ones() {
+    let full = Deserializer::from_str(EXPORT).deserialize_first().unwrap();
+    let borrowed = Deserializer::from_str(EXPORT)
+        .into_borrowed()
+        .deserialize_first()
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", borrowed.as_ref().map(LuaValueRef::to_owned)),
+        format!("{full:?}")
+    );
+
+    // {id = "Aura", name = "a^b"}
+    let Some(LuaValueRef::Map(entries)) = Deserializer::from_str("^1^T^Sid^SAura^Sname^Sa~}b^t^^")
+        .into_borrowed()
+        .deserialize_first()
+        .unwrap()
+    else {
+        panic!("expected a map");
+    };
+
+    // Strings with escape sequences have to be decoded.
+    assert!(matches!(
+        entries[..],
+        [
+            (LuaValueRef::String(Cow::Borrowed(b"id")), LuaValueRef::String(Cow::Borrowed(b"Aura"))),
+            (LuaValueRef::String(Cow::Borrowed(b"name")), LuaValueRef::String(Cow::Owned(ref s))),
+        ] if s == b"a^b"
+    ));
+}
+
+#[test]
+fn errors_report_offsets_and_paths() {
+    let input = "^1^T^Sc^T^N1^X^t^t^^";
+    let path = [PathSegment::from("c"), PathSegment::Index(1.0)];
+
+    let errors = [
+        Deserializer::from_str(input)
+            .deserialize_first()
+            .unwrap_err(),
+        Deserializer::from_str(input)
+            .into_borrowed()
+            .deserialize_first()
+            .unwrap_err(),
+        Deserializer::from_str(input)
+            .deserialize_selected(&Selector::new().with_path(["c", "1"]))
+            .unwrap_err(),
+        Deserializer::from_str(input)
+            .deserialize_selected(&Selector::new().with_path([PathSegment::from("c"), 1.0.into()]))
+            .unwrap_err(),
+    ];
+    for error in errors {
+        assert_eq!(error.kind(), DeserializationErrorKind::InvalidIdentifier);
+        let location = error.location().unwrap();
+        assert_eq!((location.offset(), location.path()), (12, &path[..]));
+    }
+
+    let error = Parser::new(input.as_bytes()).find_map(Result::err).unwrap();
+    let location = error.location().unwrap();
+    assert_eq!((location.offset(), location.path()), (12, &[][..]));
+
+    // {[nil] = true} is reported at the key.
+    let error = Deserializer::from_str("^1^T^Z^B^t^^")
+        .deserialize_first()
+        .unwrap_err();
+    assert_eq!(error.kind(), DeserializationErrorKind::InvalidMapKeyType);
+    assert_eq!(error.location().unwrap().offset(), 4);
+
+    assert_eq!(
+        Deserializer::from_str(input)
+            .deserialize_first()
+            .unwrap_err()
+            .to_string(),
+        "Invalid identifier at offset 12 in c[1]\n    ^1^T^Sc^T^N1^X^t^t^^\n                ^"
+    );
+}
+
+#[test]
+fn recovery_closes_tables_cut_short() {
+    let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    // {d = {a = "Aura", z = "print(1)"}}
+    let input = "^1^T^Sd^T^Sa^SAura^Sz^Sprint(1)^t^t^^";
+
+    let mut deserializer = Deserializer::from_str(&input[..input.len() - 8]).with_recovery();
+    let LuaValue::Map(root) = deserializer.deserialize_next().unwrap().unwrap() else {
+        panic!("expected a map");
+    };
+    let Some(LuaValue::Map(d)) = root.get(&key("d")) else {
+        panic!("expected a map");
+    };
+    assert_eq!(d.len(), 1);
+    assert_eq!(d.get(&key("a")), Some(&"Aura".into()));
+
+    let error = deserializer.recovery_error().unwrap();
+    assert_eq!(error.kind(), DeserializationErrorKind::UnexpectedEof);
+    assert_eq!(
+        error.location().unwrap().path(),
+        [PathSegment::from("d"), PathSegment::from("z")]
+    );
+    assert_eq!(deserializer.deserialize_next(), Ok(None));
+
+    // Every truncation recovers, reporting the error that stops regular deserialization.
+    for len in 0..=input.len() {
+        let mut deserializer = Deserializer::from_str(&input[..len]).with_recovery();
+        assert!(deserializer.deserialize_next().is_ok());
+        assert_eq!(
+            deserializer.recovery_error().cloned(),
+            Deserializer::from_str(&input[..len])
+                .deserialize_first()
+                .err()
+        );
+    }
+}
+
+#[cfg(feature = "lua-value-indexmap")]
+#[test]
+fn maps_use_the_requested_backend() {
+    use crate::{MapBackend, NormalizationPolicy};
+
+    // {b = true, a = {[1] = 1}}
+    let input = "^1^T^Sb^B^Sa^T^N1^N1^t^t^^";
+    let value = Deserializer::from_str(input)
+        .with_map_backend(MapBackend::IndexMap)
+        .with_normalization(NormalizationPolicy::PreferMaps)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+
+    let LuaValue::Map(m) = value else {
+        panic!("expected a map");
+    };
+    assert_eq!(m.backend(), MapBackend::IndexMap);
+    // Entries are kept in the order they were read.
+    let keys: Vec<_> = m.keys().map(|k| k.as_value().clone()).collect();
+    assert_eq!(keys, [LuaValue::from("b"), LuaValue::from("a")]);
+    assert!(matches!(
+        m.get(&LuaMapKey::try_from(LuaValue::from("a")).unwrap()),
+        Some(LuaValue::Map(inner)) if inner.backend() == MapBackend::IndexMap
+    ));
+}
@@ -0,0 +1,42 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    use weakauras_codec_base64::{
+        DecodeError, decode::ignorable_len, decode_to_vec, decode_to_vec_lenient,
+    };
+
+    // Strip ignorable characters, remembering where the rest came from.
+    let mut stripped = Vec::with_capacity(data.len());
+    let mut offsets = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match ignorable_len(&data[i..]) {
+            0 => {
+                stripped.push(data[i]);
+                offsets.push(i);
+                i += 1;
+            }
+            len => i += len,
+        }
+    }
+
+    let strict_result = decode_to_vec(&stripped);
+    let lenient_result = decode_to_vec_lenient(data);
+
+    match (strict_result, lenient_result) {
+        (Ok(strict), Ok(lenient)) => assert!(
+            strict == lenient,
+            "Lenient decoding differs from strict decoding of stripped data"
+        ),
+        // Strict decoding checks the length first.
+        (Err(DecodeError::InvalidLength), Err(_)) => {}
+        (Err(DecodeError::InvalidByte(strict)), Err(DecodeError::InvalidByte(lenient))) => {
+            assert!(
+                offsets[strict] == lenient,
+                "Lenient decoding reported a different invalid byte"
+            )
+        }
+        _ => panic!("Lenient decoding differs from strict decoding of stripped data"),
+    }
+});
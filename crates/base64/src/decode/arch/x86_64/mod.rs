@@ -5,9 +5,11 @@
 pub mod sse41;
 
 #[allow(unused_imports)]
-use crate::{decode::scalar, macros::unsafe_runtime_dispatch};
+use crate::decode::scalar;
 #[allow(unused_imports)]
 use core::mem::MaybeUninit;
+#[allow(unused_imports)]
+use weakauras_codec_runtime_dispatch::unsafe_runtime_dispatch;
 
 #[cfg(target_feature = "avx2")]
 pub use avx2::decode_into_unchecked;
@@ -22,8 +24,7 @@ pub unsafe fn decode_into_unchecked(
     unsafe_runtime_dispatch!(
         decode_into_unchecked,
         Result<usize, usize>,
-        input,
-        output,
+        (input: &[u8], output: &mut [MaybeUninit<u8>]),
         is_x86_feature_detected,
         ("avx2", avx2),
         sse41,
@@ -40,8 +41,7 @@ pub unsafe fn decode_into_unchecked(
     unsafe_runtime_dispatch!(
         decode_into_unchecked,
         Result<usize, usize>,
-        input,
-        output,
+        (input: &[u8], output: &mut [MaybeUninit<u8>]),
         is_x86_feature_detected,
         ("avx2", avx2),
         ("sse4.1", sse41),
@@ -143,5 +143,239 @@ pub fn decode_into(
     Ok(unsafe { decode_into_unchecked(input, output).map_err(DecodeError::InvalidByte)? })
 }
 
+/// The number of characters [find_invalid_byte] validates at once.
+const VALIDATION_CHUNK: usize = 1024;
+
+/// Find the offset of the first byte of `input` outside the base64 alphabet.
+///
+/// This uses the same SIMD routines as decoding. `input`'s length is not validated,
+/// thus this can be used to find where base64-encoded data embedded in text ends.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_base64::decode::find_invalid_byte;
+///
+/// assert_eq!(find_invalid_byte(b"ivgBS9glGC3BYXgzHa, and more"), Some(18));
+/// assert_eq!(find_invalid_byte(b"ivgBS9glGC3BYXgzHa"), None);
+/// ```
+pub fn find_invalid_byte(input: &[u8]) -> Option<usize> {
+    let mut buffer = [MaybeUninit::uninit(); VALIDATION_CHUNK / 4 * 3];
+
+    for (i, chunk) in input.chunks(VALIDATION_CHUNK).enumerate() {
+        // SAFETY: buffer's len is enough to store a decoded chunk.
+        if let Err(invalid_byte_at) = unsafe { decode_into_unchecked(chunk, &mut buffer) } {
+            return Some(i * VALIDATION_CHUNK + invalid_byte_at);
+        }
+    }
+
+    // A lone trailing character is neither decoded nor validated.
+    match input.last() {
+        Some(&byte) if input.len() % 4 == 1 && !scalar::is_valid_byte(byte) => {
+            Some(input.len() - 1)
+        }
+        _ => None,
+    }
+}
+
+/// The number of characters [decode_into_lenient] decodes at once
+/// before looking for the bytes it skips.
+const LENIENT_WINDOW: usize = 4096;
+
+/// Get the length of the ignorable character `input` starts with, or 0 if there's none.
+///
+/// Ignorable characters are ASCII whitespace, and the UTF-8 encoded no-break space,
+/// soft hyphen, zero-width space, zero-width non-joiner, zero-width joiner,
+/// line separator, paragraph separator, word joiner and byte order mark.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_base64::decode::ignorable_len;
+///
+/// assert_eq!(ignorable_len(b"\r\nivgB"), 1);
+/// assert_eq!(ignorable_len("\u{feff}ivgB".as_bytes()), 3);
+/// assert_eq!(ignorable_len(b"ivgB"), 0);
+/// ```
+#[inline]
+pub fn ignorable_len(input: &[u8]) -> usize {
+    match input {
+        [b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c, ..] => 1,
+        // U+00A0, U+00AD
+        [0xc2, 0xa0 | 0xad, ..] => 2,
+        // U+200B..=U+200D, U+2028, U+2029
+        [0xe2, 0x80, 0x8b..=0x8d | 0xa8 | 0xa9, ..] => 3,
+        // U+2060
+        [0xe2, 0x81, 0xa0, ..] => 3,
+        // U+FEFF
+        [0xef, 0xbb, 0xbf, ..] => 3,
+        _ => 0,
+    }
+}
+
+/// Calculate the amount of bytes that is enough to store base64-encoded `input`
+/// after decoding it with [decode_into_lenient].
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_base64::decode;
+///
+/// assert_eq!(
+///     decode::calculate_max_lenient_decoded_len(b"ivgBS9glG\r\nC3BYXgzHa"),
+///     15
+/// );
+/// ```
+#[inline]
+pub fn calculate_max_lenient_decoded_len(input: &[u8]) -> usize {
+    // Equivalent to input.len() * 3 / 4 but does not overflow
+    let len = input.len();
+    len / 4 * 3 + len % 4 * 3 / 4
+}
+
+/// Decode base64-encoded `input` into a new `Vec<u8>`, skipping ignorable characters,
+/// see [ignorable_len].
+///
+/// Unlike [decode_to_vec], this accepts base64-strings split into lines or
+/// containing invisible characters, e.g. copied from a chat.
+/// Runs of valid characters are decoded as fast as by [decode_to_vec].
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_base64::{decode::decode_to_vec_lenient, error::DecodeError};
+///
+/// fn main() -> Result<(), DecodeError> {
+///     assert_eq!(
+///         decode_to_vec_lenient("\u{feff}ivgBS9gl\r\nGC3BY\u{200b}XgzHa\n".as_bytes())?,
+///         b"Hello, world!"
+///     );
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_to_vec_lenient(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut buffer = Vec::with_capacity(calculate_max_lenient_decoded_len(input));
+
+    // SAFETY:
+    // - buffer's capacity is enough for storing decoded base64-input;
+    // - decode_lenient_unchecked returns the amount of bytes written,
+    //   thus it is safe to call set_len using its return value.
+    unsafe {
+        let written = decode_lenient_unchecked(input, buffer.spare_capacity_mut())?;
+        buffer.set_len(written)
+    }
+
+    Ok(buffer)
+}
+
+/// Decode base64-encoded `input` into the provided slice, skipping ignorable characters,
+/// see [ignorable_len].
+///
+/// Returns the amount of bytes written.
+/// The offset of an invalid byte is its offset in `input`, ignorable characters included.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_base64::{decode, error::DecodeIntoSliceError};
+///
+/// fn main() -> Result<(), DecodeIntoSliceError> {
+///     let input = b"  ivgBS9glGC3B\r\nYXgzHa\r\n";
+///     let required_capacity = decode::calculate_max_lenient_decoded_len(input);
+///     let mut output = Vec::with_capacity(required_capacity);
+///
+///     let bytes_written = decode::decode_into_lenient(input, output.spare_capacity_mut())?;
+///     unsafe {
+///         output.set_len(bytes_written);
+///     }
+///     assert_eq!(output, b"Hello, world!");
+///     Ok(())
+/// }
+/// ```
+pub fn decode_into_lenient(
+    input: &[u8],
+    output: &mut [MaybeUninit<u8>],
+) -> Result<usize, DecodeIntoSliceError> {
+    if output.len() < calculate_max_lenient_decoded_len(input) {
+        return Err(DecodeIntoSliceError::OutputSliceIsTooSmall);
+    }
+
+    // SAFETY: output's len is enough to store decoded base64-input.
+    Ok(unsafe { decode_lenient_unchecked(input, output)? })
+}
+
+/// # Safety
+///
+/// * `output`'s length must be AT LEAST `calculate_max_lenient_decoded_len(input)`
+unsafe fn decode_lenient_unchecked(
+    input: &[u8],
+    output: &mut [MaybeUninit<u8>],
+) -> Result<usize, DecodeError> {
+    let mut read = 0;
+    let mut written = 0;
+
+    // Valid characters that do not make up a whole chunk yet.
+    let mut carry = [0u8; 4];
+    let mut carried = 0;
+
+    // Every slice of output starting at `written` is at least 3/4 as long
+    // as the input following `read`, since at least 4 characters are read
+    // for every 3 bytes written.
+    while read < input.len() {
+        if carried == 0 && input.len() - read >= 4 {
+            let chunk = (input.len() - read).min(LENIENT_WINDOW) / 4 * 4;
+            let chunk = &input[read..read + chunk];
+
+            // SAFETY: See above.
+            match unsafe { decode_into_unchecked(chunk, &mut output[written..]) } {
+                Ok(chunk_written) => {
+                    read += chunk.len();
+                    written += chunk_written;
+                    continue;
+                }
+                Err(invalid_byte_at) => {
+                    // Decode the whole chunks preceding the invalid byte once again
+                    // since the amount of bytes written before it is unknown.
+                    let valid = &chunk[..invalid_byte_at / 4 * 4];
+
+                    // SAFETY: See above.
+                    written +=
+                        unsafe { decode_into_unchecked(valid, &mut output[written..]) }.unwrap();
+                    read += valid.len();
+                }
+            }
+        }
+
+        if scalar::is_valid_byte(input[read]) {
+            carry[carried] = input[read];
+            carried += 1;
+            read += 1;
+
+            if carried == 4 {
+                // SAFETY: See above.
+                written += unsafe { scalar::decode_into_unchecked(&carry, &mut output[written..]) }
+                    .unwrap();
+                carried = 0;
+            }
+        } else {
+            match ignorable_len(&input[read..]) {
+                0 => return Err(DecodeError::InvalidByte(read)),
+                len => read += len,
+            }
+        }
+    }
+
+    if carried == 1 {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    // SAFETY: See above.
+    written += unsafe { scalar::decode_into_unchecked(&carry[..carried], &mut output[written..]) }
+        .unwrap();
+
+    Ok(written)
+}
+
 #[cfg(test)]
 pub(crate) mod tests;
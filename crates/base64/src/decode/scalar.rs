@@ -89,7 +89,7 @@ pub(crate) fn find_invalid_byte(bytes: &[u8]) -> Option<usize> {
     bytes.iter().copied().position(|b| !is_valid_byte(b))
 }
 
-fn is_valid_byte(byte: u8) -> bool {
+pub(crate) fn is_valid_byte(byte: u8) -> bool {
     matches!(byte, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'(' | b')')
 }
 
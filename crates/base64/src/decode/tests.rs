@@ -8,3 +8,88 @@ pub(crate) fn base64_iter() -> impl Iterator<Item = u8> {
         .chain(b'('..=b')')
         .cycle()
 }
+
+#[test]
+fn find_invalid_byte_checks_every_byte() {
+    use crate::decode::find_invalid_byte;
+    use alloc::vec::Vec;
+
+    for len in [0, 1, 2, 3, 4, 5, 1023, 1024, 1025, 2049] {
+        let mut data: Vec<u8> = base64_iter().take(len).collect();
+        assert_eq!(find_invalid_byte(&data), None);
+
+        for invalid_byte_at in [0, len / 2, len.saturating_sub(1)] {
+            if invalid_byte_at < len {
+                let byte = core::mem::replace(&mut data[invalid_byte_at], b'=');
+                assert_eq!(find_invalid_byte(&data), Some(invalid_byte_at));
+                data[invalid_byte_at] = byte;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod lenient {
+    use super::base64_iter;
+    use crate::decode::{decode_into_lenient, decode_to_vec, decode_to_vec_lenient};
+    use crate::error::{DecodeError, DecodeIntoSliceError};
+
+    use alloc::vec::Vec;
+
+    const IGNORABLE: [&str; 6] = [" ", "\r\n", "\t", "\u{a0}", "\u{200b}", "\u{feff}"];
+
+    #[test]
+    fn lenient_skips_ignorable_characters() {
+        let data: Vec<u8> = base64_iter().take(3 * 4096 + 3).collect();
+        let expected = decode_to_vec(&data).unwrap();
+
+        // Line lengths not divisible by 4 leave characters carried over between lines.
+        for line_len in [1, 2, 3, 5, 76, 4095, 4096, 4097] {
+            let mut input = Vec::new();
+            for (i, line) in data.chunks(line_len).enumerate() {
+                input.extend_from_slice(IGNORABLE[i % IGNORABLE.len()].as_bytes());
+                input.extend_from_slice(line);
+            }
+            input.extend_from_slice(b"\n");
+
+            assert_eq!(decode_to_vec_lenient(&input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn lenient_returns_offset_in_input() {
+        let mut input: Vec<u8> = base64_iter().take(4103).collect();
+        input[2] = b'\n';
+        input[4100] = b'=';
+
+        assert_eq!(
+            decode_to_vec_lenient(&input),
+            Err(DecodeError::InvalidByte(4100))
+        );
+        // U+2019 RIGHT SINGLE QUOTATION MARK is not ignorable.
+        assert_eq!(
+            decode_to_vec_lenient("ivgB\u{200b}S9\u{2019}gl".as_bytes()),
+            Err(DecodeError::InvalidByte(9))
+        );
+    }
+
+    #[test]
+    fn lenient_checks_length_without_ignorable_characters() {
+        assert_eq!(
+            decode_to_vec_lenient(b"ivgBS \r\n"),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(decode_to_vec_lenient(b"ivgBS9 \r\n").unwrap(), b"Hell");
+        assert_eq!(decode_to_vec_lenient(b" \r\n").unwrap(), b"");
+    }
+
+    #[test]
+    fn lenient_checks_output_len() {
+        let mut output = Vec::with_capacity(3);
+
+        assert_eq!(
+            decode_into_lenient(b"ivgB\r\n", output.spare_capacity_mut()),
+            Err(DecodeIntoSliceError::OutputSliceIsTooSmall)
+        );
+    }
+}
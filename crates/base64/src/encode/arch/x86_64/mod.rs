@@ -5,9 +5,11 @@
 pub mod ssse3;
 
 #[allow(unused_imports)]
-use crate::{encode::scalar, macros::unsafe_runtime_dispatch};
+use crate::encode::scalar;
 #[allow(unused_imports)]
 use core::mem::MaybeUninit;
+#[allow(unused_imports)]
+use weakauras_codec_runtime_dispatch::unsafe_runtime_dispatch;
 
 #[cfg(target_feature = "avx2")]
 pub use avx2::encode_into_unchecked;
@@ -19,8 +21,7 @@ pub unsafe fn encode_into_unchecked(input: &[u8], output: &mut [MaybeUninit<u8>]
     unsafe_runtime_dispatch!(
         encode_into_unchecked,
         usize,
-        input,
-        output,
+        (input: &[u8], output: &mut [MaybeUninit<u8>]),
         is_x86_feature_detected,
         ("avx2", avx2),
         ssse3,
@@ -34,8 +35,7 @@ pub unsafe fn encode_into_unchecked(input: &[u8], output: &mut [MaybeUninit<u8>]
     unsafe_runtime_dispatch!(
         encode_into_unchecked,
         usize,
-        input,
-        output,
+        (input: &[u8], output: &mut [MaybeUninit<u8>]),
         is_x86_feature_detected,
         ("avx2", avx2),
         ("ssse3", ssse3),
@@ -7,6 +7,8 @@
 pub mod arch;
 #[doc(hidden)]
 pub mod scalar;
+#[cfg(feature = "std")]
+mod writer;
 
 use crate::error::{EncodeError, EncodeIntoSliceError};
 /// Encode `input` as base64 into the provided slice without validating its length.
@@ -42,6 +44,8 @@
 
 #[cfg(feature = "alloc")]
 use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+pub use writer::EncoderWriter;
 
 /// Calculate the amount of bytes required to store `input`
 /// after encoding it as base64.
@@ -0,0 +1,168 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{calculate_encoded_len, encode_into_unchecked};
+use alloc::vec::Vec;
+use std::io;
+
+/// The maximum amount of input encoded by a single call to `write`.
+const CHUNK_LEN: usize = 3 * 1024;
+
+/// A writer that encodes everything written into it as base64
+/// and passes the result to the underlying writer.
+///
+/// Input is encoded in groups of 3 bytes, so up to 2 bytes are held back
+/// until more input arrives or [EncoderWriter::finish] is called.
+/// Dropping the writer without calling `finish` loses them.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+/// use weakauras_codec_base64::EncoderWriter;
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut writer = EncoderWriter::new(Vec::new());
+///     writer.write_all(b"Hello, ")?;
+///     writer.write_all(b"world!")?;
+///
+///     assert_eq!(writer.finish()?, b"ivgBS9glGC3BYXgzHa");
+///     Ok(())
+/// }
+/// ```
+pub struct EncoderWriter<W: io::Write> {
+    writer: W,
+    leftover: [u8; 3],
+    leftover_len: usize,
+    /// Encoded data that has not been passed to the writer yet.
+    output: Vec<u8>,
+}
+
+impl<W: io::Write> EncoderWriter<W> {
+    /// Create a writer that passes encoded data to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            leftover: [0; 3],
+            leftover_len: 0,
+            output: Vec::new(),
+        }
+    }
+
+    /// Encode the held back input, flush the underlying writer and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_output()?;
+
+        let leftover = self.leftover;
+        self.encode(&leftover[..self.leftover_len]);
+        self.leftover_len = 0;
+
+        self.write_output()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn encode(&mut self, input: &[u8]) {
+        // Cannot overflow, since input is at most CHUNK_LEN bytes long.
+        let len = calculate_encoded_len(input).unwrap();
+        self.output.reserve(len);
+
+        // SAFETY:
+        // - output's spare capacity is enough for storing base64-encoded input;
+        // - encode_into_unchecked returns the amount of bytes written,
+        //   thus it is safe to call set_len adding its return value.
+        unsafe {
+            let written = encode_into_unchecked(input, self.output.spare_capacity_mut());
+            self.output.set_len(self.output.len() + written);
+        }
+    }
+
+    fn write_output(&mut self) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.writer.write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: io::Write> io::Write for EncoderWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // Encoded data is passed on lazily, so that an error never occurs
+        // after a part of the input has been consumed.
+        self.write_output()?;
+
+        let consumed = buf.len().min(CHUNK_LEN);
+        let mut input = &buf[..consumed];
+
+        if self.leftover_len > 0 {
+            let needed = (3 - self.leftover_len).min(input.len());
+            self.leftover[self.leftover_len..self.leftover_len + needed]
+                .copy_from_slice(&input[..needed]);
+            self.leftover_len += needed;
+            input = &input[needed..];
+
+            if self.leftover_len < 3 {
+                return Ok(consumed);
+            }
+
+            let leftover = self.leftover;
+            self.encode(&leftover);
+            self.leftover_len = 0;
+        }
+
+        let (groups, rest) = input.split_at(input.len() - input.len() % 3);
+        self.encode(groups);
+        self.leftover[..rest.len()].copy_from_slice(rest);
+        self.leftover_len = rest.len();
+
+        Ok(consumed)
+    }
+
+    /// Pass all encoded data to the underlying writer and flush it.
+    ///
+    /// Held back input is not encoded, since it depends on the input that follows.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_output()?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::encode_to_string;
+    use std::io::Write;
+
+    #[test]
+    fn chunked_output_matches_one_shot() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let expected = encode_to_string(&input).unwrap();
+
+        for chunk_len in [1, 2, 3, 4, 5, 7, 1000, 4096, 10_000] {
+            let mut writer = EncoderWriter::new(Vec::new());
+            for chunk in input.chunks(chunk_len) {
+                writer.write_all(chunk).unwrap();
+            }
+
+            assert_eq!(writer.finish().unwrap(), expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        let writer = EncoderWriter::new(Vec::new());
+        assert!(writer.finish().unwrap().is_empty());
+    }
+}
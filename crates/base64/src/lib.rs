@@ -39,7 +39,7 @@
 //!
 //! # Crate features
 //!
-//! * **std** - Enable features that require the standard library. As of now, it's used only for runtime SIMD feature detection on x86_64 and x86 CPUs. **Enabled** by default.
+//! * **std** - Enable features that require the standard library. It's used for runtime SIMD feature detection on x86_64 and x86 CPUs and for `EncoderWriter`, which implements `std::io::Write`. **Enabled** by default.
 //! * **alloc** - Enable APIs that allocate, like `decode_to_vec` and `encode_to_string`. **Enabled** by default.
 //!
 //! [WeakAuras]: https://weakauras.wtf
@@ -60,12 +60,16 @@
 pub mod encode;
 /// Error types.
 pub mod error;
-pub(crate) mod macros;
-pub use decode::{decode_into, decode_into_unchecked};
+pub use decode::{decode_into, decode_into_lenient, decode_into_unchecked};
 pub use encode::{encode_into, encode_into_unchecked};
 pub use error::*;
 
 #[cfg(feature = "alloc")]
-pub use decode::decode_to_vec;
+pub use decode::{decode_to_vec, decode_to_vec_lenient};
+#[cfg(feature = "std")]
+pub use encode::EncoderWriter;
 #[cfg(feature = "alloc")]
 pub use encode::{encode_to_string, encode_to_string_with_prefix};
+
+/// The characters of encoded data, in the order of the 6-bit values they encode.
+pub const ALPHABET: &[u8; 64] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789()";
@@ -0,0 +1,324 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use weakauras_codec_lib_serialize::{Deserializer, Serializer};
+use weakauras_codec_lua_value::{LuaMapKey, LuaValue};
+
+/// Keeps track of the number of bytes currently allocated and its peak.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            let now = ALLOCATED.fetch_add(new_size, Ordering::Relaxed) + new_size;
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the result of `f`, the number of bytes it still holds
+/// and the peak number of bytes allocated while running it.
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+
+    let result = f();
+
+    let retained = ALLOCATED.load(Ordering::Relaxed) - base;
+    let peak = PEAK.load(Ordering::Relaxed) - base;
+    (result, retained, peak)
+}
+
+fn map<const N: usize>(entries: [(&str, LuaValue); N]) -> LuaValue {
+    LuaValue::Map(
+        entries
+            .into_iter()
+            .map(|(k, v)| (LuaMapKey::try_from(LuaValue::from(k)).unwrap(), v))
+            .collect(),
+    )
+}
+
+fn array<const N: usize>(elements: [LuaValue; N]) -> LuaValue {
+    LuaValue::Array(elements.into())
+}
+
+fn color(r: f64, g: f64, b: f64, a: f64) -> LuaValue {
+    array([r.into(), g.into(), b.into(), a.into()])
+}
+
+/// Builds something resembling a child of a WeakAuras group.
+fn child(i: usize, parent: &str) -> LuaValue {
+    let fraction = (i % 100) as f64 / 100.0;
+
+    map([
+        ("id", format!("{parent} - Bar {i}").into()),
+        ("uid", format!("{:011x}", i * 0x9e37_79b9).into()),
+        ("parent", parent.into()),
+        ("regionType", "aurabar".into()),
+        ("internalVersion", 78.0.into()),
+        ("tocversion", 110100.0.into()),
+        ("xOffset", (i as f64 * 1.5).into()),
+        ("yOffset", (-(i as f64) * 22.25).into()),
+        ("width", 200.0.into()),
+        ("height", 20.0.into()),
+        ("anchorPoint", "CENTER".into()),
+        ("selfPoint", "CENTER".into()),
+        ("anchorFrameType", "SCREEN".into()),
+        ("frameStrata", 1.0.into()),
+        ("orientation", "HORIZONTAL".into()),
+        ("inverse", false.into()),
+        ("smoothProgress", true.into()),
+        ("useAdjustededMax", false.into()),
+        ("barColor", color(fraction, 0.5, 1.0 - fraction, 1.0)),
+        ("backgroundColor", color(0.0, 0.0, 0.0, 0.5)),
+        (
+            "texture",
+            "Interface\\AddOns\\WeakAuras\\Media\\Textures\\Statusbar_Clean".into(),
+        ),
+        ("icon", true.into()),
+        ("icon_side", "LEFT".into()),
+        ("iconSource", (-1.0).into()),
+        ("zoom", 0.3.into()),
+        (
+            "triggers",
+            map([
+                ("activeTriggerMode", (-10.0).into()),
+                ("disjunctive", "any".into()),
+                (
+                    "1",
+                    map([
+                        (
+                            "trigger",
+                            map([
+                                ("type", "aura2".into()),
+                                ("unit", "player".into()),
+                                ("debuffType", "HELPFUL".into()),
+                                ("auranames", array([format!("Spell {i}").into()])),
+                                ("useName", true.into()),
+                                ("matchesShowOn", "showOnActive".into()),
+                                ("event", "Health".into()),
+                            ]),
+                        ),
+                        ("untrigger", map([])),
+                    ]),
+                ),
+            ]),
+        ),
+        (
+            "subRegions",
+            array([
+                map([("type", "subbackground".into())]),
+                map([
+                    ("type", "subtext".into()),
+                    ("text_text", "%p".into()),
+                    ("text_color", color(1.0, 1.0, 1.0, 1.0)),
+                    ("text_font", "Friz Quadrata TT".into()),
+                    ("text_fontSize", 12.0.into()),
+                    ("text_fontType", "None".into()),
+                    ("text_justify", "CENTER".into()),
+                    ("text_selfPoint", "AUTO".into()),
+                    ("anchor_point", "INNER_RIGHT".into()),
+                    ("text_visible", true.into()),
+                    ("text_shadowXOffset", 1.0.into()),
+                    ("text_shadowYOffset", (-1.0).into()),
+                ]),
+                map([
+                    ("type", "subtext".into()),
+                    ("text_text", "%n".into()),
+                    ("text_color", color(1.0, 1.0, 1.0, 1.0)),
+                    ("text_font", "Friz Quadrata TT".into()),
+                    ("text_fontSize", 12.0.into()),
+                    ("anchor_point", "INNER_LEFT".into()),
+                    ("text_visible", true.into()),
+                ]),
+                map([
+                    ("type", "subborder".into()),
+                    ("border_visible", false.into()),
+                    ("border_edge", "Square Full White".into()),
+                    ("border_color", color(0.0, 0.0, 0.0, 1.0)),
+                    ("border_size", 1.0.into()),
+                ]),
+            ]),
+        ),
+        (
+            "conditions",
+            array([map([
+                (
+                    "check",
+                    map([
+                        ("trigger", 1.0.into()),
+                        ("variable", "expirationTime".into()),
+                        ("op", "<".into()),
+                        ("value", "3".into()),
+                    ]),
+                ),
+                (
+                    "changes",
+                    array([map([
+                        ("property", "barColor".into()),
+                        ("value", color(1.0, 0.0, 0.0, 1.0)),
+                    ])]),
+                ),
+            ])]),
+        ),
+        (
+            "load",
+            map([
+                ("use_class", false.into()),
+                ("class", map([("multi", map([]))])),
+                ("spec", map([("multi", map([]))])),
+                ("size", map([("multi", map([]))])),
+                ("talent", map([("multi", map([]))])),
+            ]),
+        ),
+        (
+            "animation",
+            map([
+                (
+                    "start",
+                    map([
+                        ("type", "none".into()),
+                        ("easeType", "none".into()),
+                        ("easeStrength", 3.0.into()),
+                        ("duration_type", "seconds".into()),
+                    ]),
+                ),
+                (
+                    "main",
+                    map([
+                        ("type", "none".into()),
+                        ("easeType", "none".into()),
+                        ("easeStrength", 3.0.into()),
+                        ("duration_type", "seconds".into()),
+                    ]),
+                ),
+                (
+                    "finish",
+                    map([
+                        ("type", "none".into()),
+                        ("easeType", "none".into()),
+                        ("easeStrength", 3.0.into()),
+                        ("duration_type", "seconds".into()),
+                    ]),
+                ),
+            ]),
+        ),
+        (
+            "actions",
+            map([("init", map([])), ("start", map([])), ("finish", map([]))]),
+        ),
+        ("config", map([])),
+        ("authorOptions", array([])),
+        ("information", map([])),
+    ])
+}
+
+/// Builds a table resembling a WeakAuras export of a group with `children` children.
+fn group_export(children: usize) -> LuaValue {
+    let id = "Raid Cooldowns";
+
+    map([
+        ("m", "d".into()),
+        ("v", 2000.0.into()),
+        ("s", "5.19.0".into()),
+        (
+            "d",
+            map([
+                ("id", id.into()),
+                ("uid", "(eXN3kdTmVd".into()),
+                ("regionType", "dynamicgroup".into()),
+                ("grow", "DOWN".into()),
+                ("space", 2.0.into()),
+                ("internalVersion", 78.0.into()),
+            ]),
+        ),
+        (
+            "c",
+            LuaValue::Array((0..children).map(|i| child(i, id)).collect()),
+        ),
+    ])
+}
+
+pub fn deserialization_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deserialize");
+    for children in [50, 500] {
+        let data = Serializer::serialize_one(&group_export(children), None).unwrap();
+        group.throughput(Throughput::Bytes(data.len() as u64));
+
+        let (value, retained, peak) = measure(|| {
+            Deserializer::from_slice(&data)
+                .deserialize_first()
+                .unwrap()
+                .unwrap()
+        });
+        drop(value);
+        println!(
+            "LuaValue, {children} children: {} bytes serialized, {retained} bytes retained, {peak} bytes at peak",
+            data.len()
+        );
+
+        let (value, retained, peak) = measure(|| {
+            Deserializer::from_slice(&data)
+                .into_compact()
+                .deserialize_first()
+                .unwrap()
+                .unwrap()
+        });
+        drop(value);
+        println!(
+            "CompactLuaValue, {children} children: {} bytes serialized, {retained} bytes retained, {peak} bytes at peak",
+            data.len()
+        );
+
+        group.bench_with_input(BenchmarkId::new("LuaValue", children), &data, |b, data| {
+            b.iter(|| {
+                Deserializer::from_slice(black_box(data))
+                    .deserialize_first()
+                    .unwrap()
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("CompactLuaValue", children),
+            &data,
+            |b, data| {
+                b.iter(|| {
+                    Deserializer::from_slice(black_box(data))
+                        .into_compact()
+                        .deserialize_first()
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, deserialization_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use weakauras_codec_lib_serialize::{
+    Deserializer, IncrementalDeserializer, LuaValue, Serializer, deserialization::Step,
+};
+
+fn serialize_all(values: &[LuaValue]) -> Vec<Vec<u8>> {
+    values
+        .iter()
+        .map(|v| Serializer::serialize_one(v, None).unwrap())
+        .collect()
+}
+
+fuzz_target!(|input: (u8, u8, &[u8])| {
+    let (chunk_len, max_items, data) = input;
+    let (chunk_len, max_items) = (usize::from(chunk_len).max(1), usize::from(max_items).max(1));
+
+    let expected = Deserializer::from_slice(data).deserialize_all();
+
+    let mut deserializer = IncrementalDeserializer::new();
+    let mut chunks = data.chunks(chunk_len);
+    let mut values = Vec::new();
+    let actual = loop {
+        match deserializer.step(max_items) {
+            Ok(Step::Value(value)) => values.push(value),
+            Ok(Step::NeedMoreInput) => match chunks.next() {
+                Some(chunk) => deserializer.feed(chunk),
+                None => deserializer.finish(),
+            },
+            Ok(Step::Suspended) => {}
+            Ok(Step::End) => break Ok(values),
+            Err(e) => break Err(e),
+        }
+    };
+
+    match (expected, actual) {
+        (Ok(expected), Ok(actual)) => assert_eq!(serialize_all(&expected), serialize_all(&actual)),
+        (Err(expected), Err(actual)) => assert_eq!(expected, actual),
+        (expected, actual) => panic!("{expected:?} != {actual:?}"),
+    }
+});
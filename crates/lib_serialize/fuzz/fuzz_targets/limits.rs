@@ -0,0 +1,60 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use weakauras_codec_lib_serialize::{Deserializer, LuaValue, NormalizationPolicy, ResourceLimits};
+
+#[derive(Default)]
+struct Usage {
+    nodes: usize,
+    string_bytes: usize,
+    max_collection_len: usize,
+}
+
+impl Usage {
+    fn add(&mut self, value: &LuaValue) {
+        self.nodes += 1;
+        match value {
+            LuaValue::Map(m) => {
+                self.max_collection_len = self.max_collection_len.max(m.len());
+                for (k, v) in m.iter() {
+                    self.add(k.as_value());
+                    self.add(v);
+                }
+            }
+            LuaValue::Array(v) => {
+                self.max_collection_len = self.max_collection_len.max(v.len());
+                v.iter().for_each(|v| self.add(v));
+            }
+            LuaValue::String(s) => self.string_bytes += s.len(),
+            _ => {}
+        }
+    }
+}
+
+fuzz_target!(|input: (u8, u8, u8, u8, &[u8])| {
+    let (nodes, string_bytes, collection_len, cloned_nodes, data) = input;
+    let limits = ResourceLimits::default()
+        .with_max_nodes(nodes.into())
+        .with_max_string_bytes(usize::from(string_bytes) * 4)
+        .with_max_collection_len(collection_len.into())
+        .with_max_cloned_nodes(cloned_nodes.into());
+
+    // Normalization could turn a map with holes into a longer array.
+    if let Ok(values) = Deserializer::from_slice(data)
+        .with_normalization(NormalizationPolicy::Preserve)
+        .with_limits(limits)
+        .deserialize_all()
+    {
+        let mut usage = Usage::default();
+        values.iter().for_each(|v| usage.add(v));
+
+        assert!(usage.nodes <= limits.max_nodes(), "Too many nodes");
+        assert!(
+            usage.string_bytes <= limits.max_string_bytes(),
+            "Too many string bytes"
+        );
+        assert!(
+            usage.max_collection_len <= limits.max_collection_len(),
+            "Too long collection"
+        );
+    }
+});
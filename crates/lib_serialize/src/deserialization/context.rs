@@ -0,0 +1,137 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{
+    Value,
+    token::{Table, Token},
+};
+use crate::error::{DeserializationError, DeserializationErrorKind};
+use weakauras_codec_lua_value::{
+    MapBackend, NonFiniteReport, NormalizationPolicy, ResourceBudget, ResourceLimits,
+};
+
+/// A value or the header of a table whose contents follow.
+pub(super) enum Item<V> {
+    Value(V),
+    Table(Table),
+}
+
+/// The state shared by all values deserialized from the same input.
+pub(super) struct Context<'s, V: Value<'s>> {
+    pub(super) normalization: NormalizationPolicy,
+    pub(super) map_backend: MapBackend,
+    pub(super) budget: ResourceBudget,
+    pub(super) non_finite: NonFiniteReport,
+
+    table_refs: Vec<V>,
+    string_refs: Vec<V::String>,
+}
+
+impl<'s, V: Value<'s>> Context<'s, V> {
+    pub(super) fn new() -> Self {
+        Self {
+            normalization: NormalizationPolicy::PreferArrays,
+            map_backend: MapBackend::BTreeMap,
+            budget: ResourceBudget::new(ResourceLimits::default()),
+            non_finite: NonFiniteReport::default(),
+
+            table_refs: Vec::new(),
+            string_refs: Vec::new(),
+        }
+    }
+
+    /// Keeps the settings, but not the references.
+    pub(super) fn cast<W: Value<'s>>(self) -> Context<'s, W> {
+        Context {
+            normalization: self.normalization,
+            map_backend: self.map_backend,
+            budget: self.budget,
+            non_finite: self.non_finite,
+
+            table_refs: Vec::new(),
+            string_refs: Vec::new(),
+        }
+    }
+
+    /// Accounts for a token and resolves it into a value, unless it starts a table.
+    pub(super) fn resolve(
+        &mut self,
+        token: Token<V::String>,
+    ) -> Result<Item<V>, DeserializationError> {
+        self.budget.spend_nodes(1)?;
+
+        Ok(Item::Value(match token {
+            Token::Null => V::null(),
+            Token::Boolean(b) => V::boolean(b),
+            Token::Number(n) => {
+                self.non_finite.record(n);
+                V::number(n)
+            }
+            Token::String(s) => {
+                let len = V::string_len(&s);
+                self.budget.spend_string_bytes(len)?;
+                if len > 2 {
+                    self.string_refs.push(s.clone());
+                }
+
+                V::string(s)
+            }
+            Token::StringRef(index) => match self.string_refs.get(index) {
+                None => return Err(DeserializationErrorKind::InvalidStringReference.into()),
+                Some(s) => {
+                    self.budget.spend_string_bytes(V::string_len(s))?;
+                    V::string(s.clone())
+                }
+            },
+            Token::TableRef(index) => match self.table_refs.get(index) {
+                None => return Err(DeserializationErrorKind::InvalidMapReference.into()),
+                Some(v) => {
+                    self.budget.spend_cloned_nodes(v.clone_cost())?;
+                    v.clone()
+                }
+            },
+            Token::Table(table) => {
+                let len = match table {
+                    Table::Map(len) | Table::Array(len) => len,
+                    Table::Mixed { array_len, map_len } => array_len.saturating_add(map_len),
+                };
+                self.budget.check_collection_len(len)?;
+
+                return Ok(Item::Table(table));
+            }
+        }))
+    }
+
+    #[inline(always)]
+    pub(super) fn new_map(&self) -> V::Map {
+        V::new_map(self.map_backend)
+    }
+
+    pub(super) fn finish_map(&mut self, map: V::Map) -> V {
+        let m = V::map(map);
+        self.table_refs.push(m.clone());
+        m
+    }
+
+    pub(super) fn finish_array(&mut self, elements: Vec<V>) -> V {
+        let v = self.build_array(elements);
+        self.table_refs.push(v.clone());
+        v
+    }
+
+    /// Builds an array without making it referenceable.
+    pub(super) fn build_array(&self, mut elements: Vec<V>) -> V {
+        // Trailing `nil`s are not part of the array.
+        while elements.last().is_some_and(V::is_null) {
+            elements.pop();
+        }
+
+        V::array(elements, self.normalization, self.map_backend)
+    }
+
+    /// Prepares a top-level value to be returned.
+    #[inline(always)]
+    pub(super) fn finish_value(&self, value: &mut V) {
+        value.normalize(self.normalization);
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{
+    Context, Item, SliceReader, Value,
+    token::{Table, read_token},
+};
+use crate::{
+    FORMAT_VERSION,
+    error::{DeserializationError, DeserializationErrorKind},
+};
+use weakauras_codec_lua_value::{
+    CompactLuaValue, LuaValue, MapBackend, NonFiniteReport, NormalizationPolicy, PathSegment,
+    ResourceBudget, ResourceLimits,
+};
+
+const MAX_DEPTH: usize = 128;
+
+/// The outcome of [IncrementalDeserializer::step].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step<V = LuaValue> {
+    /// A top-level value has been deserialized.
+    Value(V),
+    /// All input fed so far has been consumed, more is needed to make progress.
+    NeedMoreInput,
+    /// The step has processed as many items as it was allowed to.
+    Suspended,
+    /// All values have been deserialized and [IncrementalDeserializer::finish] has been called.
+    End,
+}
+
+/// A table whose contents are being deserialized.
+enum Frame<V: Value<'static>> {
+    Array {
+        elements: Vec<V>,
+        remaining: usize,
+    },
+    Map {
+        map: V::Map,
+        /// The 1-based index of the next element of the array part.
+        index: usize,
+        array_len: usize,
+        key: Option<V>,
+        remaining: usize,
+    },
+}
+
+impl<V: Value<'static>> Frame<V> {
+    fn is_complete(&self) -> bool {
+        match self {
+            Frame::Array { remaining, .. } => *remaining == 0,
+            Frame::Map {
+                index,
+                array_len,
+                key,
+                remaining,
+                ..
+            } => *index > *array_len && key.is_none() && *remaining == 0,
+        }
+    }
+}
+
+/// A push-based deserializer for data produced by LibSerialize,
+/// similar to LibSerialize's `DeserializeAsync`.
+///
+/// Input is [fed](IncrementalDeserializer::feed) in chunks of any size as it becomes available,
+/// and values are produced by [steps](IncrementalDeserializer::step) that do a bounded
+/// amount of work each, so deserialization can be interleaved with other tasks.
+///
+/// Produces [LuaValue]s by default, see [IncrementalDeserializer::into_compact]
+/// for producing [CompactLuaValue]s. Once a step fails, the deserializer must not be used further.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lib_serialize::{
+///     DeserializationError, IncrementalDeserializer, LuaValue, deserialization::Step,
+/// };
+///
+/// fn main() -> Result<(), DeserializationError> {
+///     let mut deserializer = IncrementalDeserializer::new();
+///
+///     deserializer.feed(b"\x01\xd2Hello");
+///     assert_eq!(deserializer.step(100)?, Step::NeedMoreInput);
+///
+///     deserializer.feed(b", world!");
+///     assert_eq!(deserializer.step(100)?, Step::Value("Hello, world!".into()));
+///
+///     deserializer.finish();
+///     assert_eq!(deserializer.step(100)?, Step::End);
+///     Ok(())
+/// }
+/// ```
+pub struct IncrementalDeserializer<V: Value<'static> = LuaValue> {
+    buffer: Vec<u8>,
+    position: usize,
+    /// The position of the last item, errors are reported there.
+    item_position: usize,
+    /// The amount of consumed input dropped from the buffer.
+    drained: usize,
+    prefix_checked: bool,
+    finished: bool,
+
+    stack: Vec<Frame<V>>,
+    context: Context<'static, V>,
+}
+
+impl IncrementalDeserializer {
+    /// Create a deserializer without any input.
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            position: 0,
+            item_position: 0,
+            drained: 0,
+            prefix_checked: false,
+            finished: false,
+
+            stack: Vec::new(),
+            context: Context::new(),
+        }
+    }
+
+    /// Make the deserializer produce [CompactLuaValue]s,
+    /// see [Deserializer::into_compact](super::Deserializer::into_compact).
+    pub fn into_compact(self) -> IncrementalDeserializer<CompactLuaValue> {
+        IncrementalDeserializer {
+            buffer: self.buffer,
+            position: self.position,
+            item_position: self.item_position,
+            drained: self.drained,
+            prefix_checked: self.prefix_checked,
+            finished: self.finished,
+
+            stack: Vec::new(),
+            context: self.context.cast(),
+        }
+    }
+}
+
+impl Default for IncrementalDeserializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Value<'static>> IncrementalDeserializer<V> {
+    /// Set the policy for choosing between [LuaValue::Array] and [LuaValue::Map],
+    /// see [Deserializer::with_normalization](super::Deserializer::with_normalization).
+    pub fn with_normalization(mut self, policy: NormalizationPolicy) -> Self {
+        self.context.normalization = policy;
+        self
+    }
+
+    /// Set the implementation of produced [LuaValue::Map]s.
+    ///
+    /// The default is [MapBackend::BTreeMap].
+    pub fn with_map_backend(mut self, backend: MapBackend) -> Self {
+        self.context.map_backend = backend;
+        self
+    }
+
+    /// Set the limits on resources consumed by deserialization.
+    ///
+    /// The default is [ResourceLimits::default].
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.context.budget = ResourceBudget::new(limits);
+        self
+    }
+
+    /// Get the non-finite numbers among the values deserialized so far.
+    pub fn non_finite_report(&self) -> NonFiniteReport {
+        self.context.non_finite
+    }
+
+    /// Append a chunk of input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // Drop consumed input once it makes up the larger part of the buffer.
+        if self.position > 0 && self.position >= self.buffer.len() / 2 {
+            self.buffer.drain(..self.position);
+            self.drained += self.position;
+            self.position = 0;
+        }
+
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Signal that there is no more input.
+    ///
+    /// Afterwards, running out of input in the middle of a value
+    /// is an error rather than [Step::NeedMoreInput].
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Deserialize until the next top-level value is complete or input runs out.
+    pub fn next_value(&mut self) -> Result<Step<V>, DeserializationError> {
+        self.step(usize::MAX)
+    }
+
+    /// Deserialize at most `max_items` items, i.e. scalars, references and table headers.
+    ///
+    /// Returns [Step::Suspended] if the limit was reached before a top-level value
+    /// was completed. Calling `step` again continues where the previous call stopped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{
+    ///     DeserializationError, IncrementalDeserializer, LuaValue, deserialization::Step,
+    /// };
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let mut deserializer = IncrementalDeserializer::new();
+    ///     // {1, 2, 3}
+    ///     deserializer.feed(b"\x01\x3a\x03\x05\x07");
+    ///
+    ///     let mut steps = 1;
+    ///     let value = loop {
+    ///         match deserializer.step(1)? {
+    ///             Step::Value(value) => break value,
+    ///             Step::Suspended => steps += 1,
+    ///             step => unreachable!("{step:?}"),
+    ///         }
+    ///     };
+    ///
+    ///     assert!(matches!(value, LuaValue::Array(v) if v.len() == 3));
+    ///     assert_eq!(steps, 4);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn step(&mut self, max_items: usize) -> Result<Step<V>, DeserializationError> {
+        self.step_helper(max_items).map_err(|e| self.locate(e))
+    }
+
+    /// Reports `error` at the last item, in the table entries that are being deserialized.
+    #[cold]
+    fn locate(&self, mut error: DeserializationError) -> DeserializationError {
+        for frame in self.stack.iter().rev() {
+            let segment = match frame {
+                Frame::Array { elements, .. } => PathSegment::Index((elements.len() + 1) as f64),
+                Frame::Map {
+                    index,
+                    array_len,
+                    key,
+                    ..
+                } => match key {
+                    _ if index <= array_len => PathSegment::Index(*index as f64),
+                    Some(key) => key.path_segment(),
+                    // The key itself is being deserialized.
+                    None => PathSegment::Any,
+                },
+            };
+            error = error.with_parent(segment);
+        }
+
+        error
+            .located_at(&self.buffer, self.item_position)
+            .with_base_offset(self.drained)
+    }
+
+    fn step_helper(&mut self, max_items: usize) -> Result<Step<V>, DeserializationError> {
+        if !self.prefix_checked {
+            match self.buffer.get(self.position) {
+                Some(&val) if val == FORMAT_VERSION || val == FORMAT_VERSION + 1 => {
+                    self.position += 1;
+                    self.prefix_checked = true;
+                }
+                None if !self.finished => return Ok(Step::NeedMoreInput),
+                _ => return Err(DeserializationErrorKind::InvalidPrefix.into()),
+            }
+        }
+
+        for _ in 0..max_items {
+            if self.stack.len() >= MAX_DEPTH {
+                return Err(DeserializationErrorKind::RecursionLimitExceeded.into());
+            }
+
+            self.item_position = self.position;
+            let mut reader = SliceReader::new(&self.buffer[self.position..]);
+            let token = match read_token(&mut reader) {
+                Ok(Some(token)) => token.map_string(V::new_string),
+                Ok(None) if self.finished && self.stack.is_empty() => return Ok(Step::End),
+                // The item is incomplete, it will be read again once there is more input.
+                Ok(None) if !self.finished => return Ok(Step::NeedMoreInput),
+                Err(e) if !self.finished && e.kind() == DeserializationErrorKind::UnexpectedEof => {
+                    return Ok(Step::NeedMoreInput);
+                }
+                Ok(None) => return Err(DeserializationErrorKind::UnexpectedEof.into()),
+                Err(e) => return Err(e),
+            };
+            self.position += reader.position();
+
+            let value = match self.context.resolve(token)? {
+                Item::Value(v) => v,
+                Item::Table(table) => {
+                    let frame = match table {
+                        Table::Array(len) => Frame::Array {
+                            elements: Vec::new(),
+                            remaining: len,
+                        },
+                        Table::Map(len) => Frame::Map {
+                            map: self.context.new_map(),
+                            index: 1,
+                            array_len: 0,
+                            key: None,
+                            remaining: len,
+                        },
+                        Table::Mixed { array_len, map_len } => Frame::Map {
+                            map: self.context.new_map(),
+                            index: 1,
+                            array_len,
+                            key: None,
+                            remaining: map_len,
+                        },
+                    };
+
+                    if frame.is_complete() {
+                        self.finish_frame(frame)
+                    } else {
+                        self.stack.push(frame);
+                        continue;
+                    }
+                }
+            };
+
+            if let Some(mut value) = self.add_value(value)? {
+                self.context.finish_value(&mut value);
+                return Ok(Step::Value(value));
+            }
+        }
+
+        Ok(Step::Suspended)
+    }
+
+    /// Adds a value to the innermost table, completing tables as needed.
+    /// Returns the value if it is a complete top-level value.
+    fn add_value(&mut self, mut value: V) -> Result<Option<V>, DeserializationError> {
+        loop {
+            match self.stack.last_mut() {
+                None => return Ok(Some(value)),
+                Some(Frame::Array {
+                    elements,
+                    remaining,
+                }) => {
+                    elements.push(value);
+                    *remaining -= 1;
+                }
+                Some(Frame::Map {
+                    map,
+                    index,
+                    array_len,
+                    key,
+                    remaining,
+                }) => {
+                    if *index <= *array_len {
+                        V::insert(map, V::number(*index as f64), value)?;
+                        *index += 1;
+                    } else if let Some(key) = key.take() {
+                        V::insert(map, key, value)?;
+                        *remaining -= 1;
+                    } else {
+                        *key = Some(value);
+                    }
+                }
+            }
+
+            if !self.stack.last().is_some_and(Frame::is_complete) {
+                return Ok(None);
+            }
+
+            let frame = self.stack.pop().unwrap();
+            value = self.finish_frame(frame);
+        }
+    }
+
+    fn finish_frame(&mut self, frame: Frame<V>) -> V {
+        match frame {
+            Frame::Array { elements, .. } => self.context.finish_array(elements),
+            Frame::Map { map, .. } => self.context.finish_map(map),
+        }
+    }
+}
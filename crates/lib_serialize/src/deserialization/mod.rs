@@ -4,15 +4,37 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT
 
+mod context;
+mod incremental;
+mod parser;
 mod reader;
+mod select;
+mod token;
+mod value;
 
 use crate::{
-    EmbeddedTypeTag, FORMAT_VERSION, TypeTag, error::DeserializationError, macros::check_recursion,
+    error::{DeserializationError, DeserializationErrorKind},
+    macros::check_recursion,
 };
+use context::{Context, Item};
+pub use incremental::{IncrementalDeserializer, Step};
+use parser::RawEvent;
+pub use parser::{Event, Parser};
 use reader::SliceReader;
-use weakauras_codec_lua_value::{LuaMapKey, LuaValue, Map};
+use select::Selection;
+use token::Table;
+pub use value::Value;
+use weakauras_codec_lua_value::{
+    CompactLuaValue, LuaValue, LuaValueRef, MapBackend, NonFiniteReport, NormalizationPolicy,
+    PathSegment, ResourceBudget, ResourceLimits, Selector,
+};
 
-/// A structure for deserializing data produced by LibSerialize.
+/// A structure for deserializing data produced by LibSerialize,
+/// built on top of [Parser].
+///
+/// Produces [LuaValue]s by default, see [Deserializer::into_compact]
+/// for producing [CompactLuaValue]s and [Deserializer::into_borrowed]
+/// for producing [LuaValueRef]s.
 ///
 /// # Example
 ///
@@ -29,12 +51,12 @@
 ///     Ok(())
 /// }
 /// ```
-pub struct Deserializer<'s> {
+pub struct Deserializer<'s, V: Value<'s> = LuaValue> {
     remaining_depth: usize,
-    reader: SliceReader<'s>,
-
-    table_refs: Vec<LuaValue>,
-    string_refs: Vec<String>,
+    parser: Parser<'s>,
+    context: Context<'s, V>,
+    recovery: bool,
+    stopped: Option<DeserializationError>,
 }
 
 impl<'s> Deserializer<'s> {
@@ -42,23 +64,197 @@ impl<'s> Deserializer<'s> {
     pub fn from_slice(slice: &'s [u8]) -> Self {
         Self {
             remaining_depth: 128,
-            reader: SliceReader::new(slice),
+            parser: Parser::without_refs(slice),
+            context: Context::new(),
+            recovery: false,
+            stopped: None,
+        }
+    }
 
-            table_refs: Vec::new(),
-            string_refs: Vec::new(),
+    /// Make the deserializer produce [CompactLuaValue]s,
+    /// which take considerably less memory than [LuaValue]s.
+    ///
+    /// References to previously deserialized tables resolve to the same table
+    /// instead of a copy of it, see [CompactLuaValue::ptr_eq].
+    ///
+    /// Normalization does not apply to [CompactLuaValue]s, tables keep the shape
+    /// they were serialized in. Convert the result into a [LuaValue] and call
+    /// [LuaValue::normalize] if that is needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{CompactLuaValue, DeserializationError, Deserializer};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let value = Deserializer::from_slice(b"\x01\xd2Hello, world!")
+    ///         .into_compact()
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///
+    ///     assert!(matches!(value, CompactLuaValue::String(ref s) if s == "Hello, world!"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_compact(self) -> Deserializer<'s, CompactLuaValue> {
+        Deserializer {
+            remaining_depth: self.remaining_depth,
+            parser: self.parser,
+            context: self.context.cast(),
+            recovery: self.recovery,
+            stopped: self.stopped,
         }
     }
 
-    /// Deserialize all values.
-    pub fn deserialize_all(mut self) -> Result<Vec<LuaValue>, DeserializationError> {
-        match self.reader.read_u8() {
-            Some(val) if val == FORMAT_VERSION || val == FORMAT_VERSION + 1 => {}
-            _ => return Err(DeserializationError::InvalidPrefix),
+    /// Make the deserializer produce [LuaValueRef]s, whose strings borrow from the input.
+    ///
+    /// LibSerialize stores strings as they are, so no string is copied.
+    /// Normalization does not apply to [LuaValueRef]s, tables keep the shape
+    /// they were serialized in.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use weakauras_codec_lib_serialize::{DeserializationError, Deserializer, LuaValueRef};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let input = b"\x01\xd2Hello, world!";
+    ///     let value = Deserializer::from_slice(input)
+    ///         .into_borrowed()
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///
+    ///     assert!(matches!(
+    ///         value,
+    ///         LuaValueRef::String(Cow::Borrowed(s)) if s == b"Hello, world!"
+    ///     ));
+    ///     assert_eq!(value.to_owned(), "Hello, world!".into());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_borrowed(self) -> Deserializer<'s, LuaValueRef<'s>> {
+        Deserializer {
+            remaining_depth: self.remaining_depth,
+            parser: self.parser,
+            context: self.context.cast(),
+            recovery: self.recovery,
+            stopped: self.stopped,
         }
+    }
+}
+
+impl<'s, V: Value<'s>> Deserializer<'s, V> {
+    /// Set the policy for choosing between [LuaValue::Array] and [LuaValue::Map].
+    ///
+    /// The default is [NormalizationPolicy::PreferArrays], like in the AceSerialize
+    /// deserializer, so that the same table has the same shape in both formats.
+    /// [NormalizationPolicy::Preserve] produces arrays for tables serialized as arrays
+    /// and maps for tables serialized as maps or as mixed tables.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{Deserializer, DeserializationError, LuaValue, NormalizationPolicy};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     // A map {[1] = "a", [2] = "b"}
+    ///     let input = b"\x01\x26\x03\x12a\x05\x12b";
+    ///
+    ///     let value = Deserializer::from_slice(input).deserialize_first()?.unwrap();
+    ///     assert!(matches!(value, LuaValue::Array(_)));
+    ///
+    ///     let value = Deserializer::from_slice(input)
+    ///         .with_normalization(NormalizationPolicy::Preserve)
+    ///         .deserialize_first()?
+    ///         .unwrap();
+    ///     assert!(matches!(value, LuaValue::Map(_)));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_normalization(mut self, policy: NormalizationPolicy) -> Self {
+        self.context.normalization = policy;
+        self
+    }
+
+    /// Set the implementation of produced [LuaValue::Map]s.
+    ///
+    /// The default is [MapBackend::BTreeMap].
+    pub fn with_map_backend(mut self, backend: MapBackend) -> Self {
+        self.context.map_backend = backend;
+        self
+    }
 
+    /// Set the limits on resources consumed by deserialization.
+    ///
+    /// The default is [ResourceLimits::default].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{DeserializationErrorKind, Deserializer};
+    /// use weakauras_codec_lua_value::{ResourceLimits, error::ResourceLimitExceeded};
+    ///
+    /// // {"a", "b", "c"}
+    /// let input = b"\x01\x3a\x12a\x12b\x12c";
+    /// let limits = ResourceLimits::default().with_max_collection_len(2);
+    ///
+    /// assert_eq!(
+    ///     Deserializer::from_slice(input)
+    ///         .with_limits(limits)
+    ///         .deserialize_first()
+    ///         .map_err(|e| e.kind()),
+    ///     Err(DeserializationErrorKind::ResourceLimitExceeded(
+    ///         ResourceLimitExceeded::CollectionLength
+    ///     ))
+    /// );
+    /// ```
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.context.budget = ResourceBudget::new(limits);
+        self
+    }
+
+    /// Make the deserializer recover from errors instead of failing.
+    ///
+    /// Once an error occurs, deserialization stops and the tables being deserialized
+    /// are closed with the entries read so far, so a truncated or damaged input
+    /// still produces the part of the value that precedes the damage.
+    /// The error is available from [Deserializer::recovery_error] afterwards.
+    ///
+    /// Exceeding the recursion limit and invalid table keys are still errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{
+    ///     DeserializationError, DeserializationErrorKind, Deserializer, LuaValue,
+    /// };
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     // {1, 2, 3} without its last element
+    ///     let mut deserializer = Deserializer::from_slice(b"\x01\x3a\x03\x05").with_recovery();
+    ///
+    ///     let Some(LuaValue::Array(elements)) = deserializer.deserialize_next()? else {
+    ///         unreachable!()
+    ///     };
+    ///     assert_eq!(elements, [1.0.into(), 2.0.into()]);
+    ///
+    ///     let error = deserializer.recovery_error().unwrap();
+    ///     assert_eq!(error.kind(), DeserializationErrorKind::UnexpectedEof);
+    ///     assert_eq!(error.location().unwrap().to_string().lines().next(), Some("at offset 4 in [3]"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_recovery(mut self) -> Self {
+        self.recovery = true;
+        self
+    }
+
+    /// Deserialize all values.
+    pub fn deserialize_all(mut self) -> Result<Vec<V>, DeserializationError> {
         let mut result = Vec::new();
 
-        while let Some(v) = self.deserialize_helper()? {
+        while let Some(v) = self.deserialize_next()? {
             result.push(v);
         }
 
@@ -66,350 +262,253 @@ pub fn deserialize_all(mut self) -> Result<Vec<LuaValue>, DeserializationError>
     }
 
     /// Deserialize the first value.
-    pub fn deserialize_first(mut self) -> Result<Option<LuaValue>, DeserializationError> {
-        match self.reader.read_u8() {
-            Some(val) if val == FORMAT_VERSION || val == FORMAT_VERSION + 1 => {}
-            _ => return Err(DeserializationError::InvalidPrefix),
-        }
-
-        self.deserialize_helper()
+    pub fn deserialize_first(mut self) -> Result<Option<V>, DeserializationError> {
+        self.deserialize_next()
     }
 
-    fn deserialize_helper(&mut self) -> Result<Option<LuaValue>, DeserializationError> {
-        match self.reader.read_u8() {
-            None => Ok(None),
-            Some(value) => {
-                if value & 1 == 1 {
-                    // `NNNN NNN1`: a 7 bit non-negative int
-                    Ok(Some(LuaValue::Number((value >> 1) as f64)))
-                } else if value & 3 == 2 {
-                    // * `CCCC TT10`: a 2 bit type index and 4 bit count (strlen, #tab, etc.)
-                    //     * Followed by the type-dependent payload
-                    let tag = EmbeddedTypeTag::from_u8((value & 0x0F) >> 2)
-                        .ok_or(DeserializationError::InvalidEmbeddedTag)?;
-                    let len = value >> 4;
-
-                    self.deserialize_embedded(tag, len).map(Option::Some)
-                } else if value & 7 == 4 {
-                    // * `NNNN S100`: the lower four bits of a 12 bit int and 1 bit for its sign
-                    //     * Followed by a byte for the upper bits
-                    let next_byte = self
-                        .reader
-                        .read_u8()
-                        .ok_or(DeserializationError::UnexpectedEof)?
-                        as u16;
-                    let packed = (next_byte << 8) + value as u16;
-
-                    Ok(Some(LuaValue::Number(if value & 15 == 12 {
-                        -((packed >> 4) as f64)
-                    } else {
-                        (packed >> 4) as f64
-                    })))
-                } else {
-                    // * `TTTT T000`: a 5 bit type index
-                    //     * Followed by the type-dependent payload, including count(s) if needed
-                    let tag =
-                        TypeTag::from_u8(value >> 3).ok_or(DeserializationError::InvalidTag)?;
-
-                    self.deserialize_one(tag).map(Option::Some)
-                }
-            }
+    /// Deserialize the next value, returns `None` if there are no values left.
+    ///
+    /// Unlike [Deserializer::deserialize_first], this keeps the deserializer,
+    /// e.g. to inspect [Deserializer::non_finite_report] afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{DeserializationError, Deserializer, LuaValue, Serializer};
+    ///
+    /// fn main() -> Result<(), DeserializationError> {
+    ///     let value = LuaValue::Array(vec![1.0.into(), f64::INFINITY.into()]);
+    ///     let input = Serializer::serialize_one(&value, None).unwrap();
+    ///     let mut deserializer = Deserializer::from_slice(&input);
+    ///
+    ///     assert!(matches!(deserializer.deserialize_next()?, Some(LuaValue::Array(_))));
+    ///     assert_eq!(deserializer.non_finite_report().positive_infinity(), 1);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_next(&mut self) -> Result<Option<V>, DeserializationError> {
+        if self.stopped.is_some() {
+            return Ok(None);
         }
-    }
 
-    #[inline(always)]
-    fn extract_value(&mut self) -> Result<LuaValue, DeserializationError> {
-        match self.deserialize_helper() {
-            Ok(Some(value)) => Ok(value),
-            Ok(None) => Err(DeserializationError::UnexpectedEof),
-            Err(e) => Err(e),
+        let result = self.deserialize_helper();
+        let mut result = match self.recover(result) {
+            Ok(result) => result.flatten(),
+            Err(e) => return Err(self.locate(e)),
+        };
+        if let Some(e) = self.stopped.take() {
+            self.stopped = Some(self.locate(e));
         }
-    }
-
-    fn deserialize_embedded(
-        &mut self,
-        tag: EmbeddedTypeTag,
-        len: u8,
-    ) -> Result<LuaValue, DeserializationError> {
-        match tag {
-            EmbeddedTypeTag::Str => self.deserialize_string(len as usize),
-            EmbeddedTypeTag::Map => self.deserialize_map(len as usize),
-            EmbeddedTypeTag::Array => self.deserialize_array(len as usize),
-            // For MIXED, the 4-bit count contains two 2-bit counts that are one less than the true count.
-            EmbeddedTypeTag::Mixed => {
-                self.deserialize_mixed(((len & 3) + 1) as usize, ((len >> 2) + 1) as usize)
-            }
+        if let Some(ref mut v) = result {
+            self.context.finish_value(v);
         }
-    }
-
-    fn deserialize_one(&mut self, tag: TypeTag) -> Result<LuaValue, DeserializationError> {
-        match tag {
-            TypeTag::Null => Ok(LuaValue::Null),
-
-            TypeTag::Int16Pos => self.deserialize_int(2).map(|v| LuaValue::Number(v as f64)),
-            TypeTag::Int16Neg => self
-                .deserialize_int(2)
-                .map(|v| LuaValue::Number(-(v as f64))),
-            TypeTag::Int24Pos => self.deserialize_int(3).map(|v| LuaValue::Number(v as f64)),
-            TypeTag::Int24Neg => self
-                .deserialize_int(3)
-                .map(|v| LuaValue::Number(-(v as f64))),
-            TypeTag::Int32Pos => self.deserialize_int(4).map(|v| LuaValue::Number(v as f64)),
-            TypeTag::Int32Neg => self
-                .deserialize_int(4)
-                .map(|v| LuaValue::Number(-(v as f64))),
-            TypeTag::Int64Pos => self.deserialize_int(7).map(|v| LuaValue::Number(v as f64)),
-            TypeTag::Int64Neg => self
-                .deserialize_int(7)
-                .map(|v| LuaValue::Number(-(v as f64))),
-
-            TypeTag::Float => self.deserialize_f64().map(LuaValue::Number),
-            TypeTag::FloatStrPos => self.deserialize_f64_from_str().map(LuaValue::Number),
-            TypeTag::FloatStrNeg => self
-                .deserialize_f64_from_str()
-                .map(|v| LuaValue::Number(-v)),
-
-            TypeTag::True => Ok(LuaValue::Boolean(true)),
-            TypeTag::False => Ok(LuaValue::Boolean(false)),
-
-            TypeTag::Str8 => {
-                let len = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?;
-                self.deserialize_string(len as usize)
-            }
-            TypeTag::Str16 => {
-                let len = self.deserialize_int(2)?;
-                self.deserialize_string(len as usize)
-            }
-            TypeTag::Str24 => {
-                let len = self.deserialize_int(3)?;
-                self.deserialize_string(len as usize)
-            }
 
-            TypeTag::Map8 => {
-                let len = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?;
-                self.deserialize_map(len as usize)
-            }
-            TypeTag::Map16 => {
-                let len = self.deserialize_int(2)?;
-                self.deserialize_map(len as usize)
-            }
-            TypeTag::Map24 => {
-                let len = self.deserialize_int(3)?;
-                self.deserialize_map(len as usize)
-            }
+        Ok(result)
+    }
 
-            TypeTag::Array8 => {
-                let len = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?;
-                self.deserialize_array(len as usize)
-            }
-            TypeTag::Array16 => {
-                let len = self.deserialize_int(2)?;
-                self.deserialize_array(len as usize)
-            }
-            TypeTag::Array24 => {
-                let len = self.deserialize_int(3)?;
-                self.deserialize_array(len as usize)
-            }
+    /// Get the non-finite numbers among the values deserialized so far.
+    ///
+    /// Numbers in the parts of values that a [Selector] leaves out are not counted.
+    pub fn non_finite_report(&self) -> NonFiniteReport {
+        self.context.non_finite
+    }
 
-            TypeTag::Mixed8 => {
-                let array_len = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?;
-                let map_len = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?;
-
-                self.deserialize_mixed(array_len as usize, map_len as usize)
-            }
-            TypeTag::Mixed16 => {
-                let array_len = self.deserialize_int(2)?;
-                let map_len = self.deserialize_int(2)?;
+    /// Get the error that stopped deserialization in recovery mode, see [Deserializer::with_recovery].
+    pub fn recovery_error(&self) -> Option<&DeserializationError> {
+        self.stopped.as_ref()
+    }
 
-                self.deserialize_mixed(array_len as usize, map_len as usize)
-            }
-            TypeTag::Mixed24 => {
-                let array_len = self.deserialize_int(3)?;
-                let map_len = self.deserialize_int(3)?;
+    /// Deserialize the selected parts of the first value, see [Selector].
+    ///
+    /// Everything else is skipped without being built, which is considerably faster
+    /// when only a small part of a large value is needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{Deserializer, Emitter, LuaValue, Selector};
+    /// use weakauras_codec_lua_value::LuaMapKey;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     // {d = {id = "Aura", regionType = "icon"}, v = 2000}
+    ///     let mut emitter = Emitter::new(Vec::new());
+    ///     emitter.begin_table(0, 2)?.key()?.string("d")?;
+    ///     emitter.begin_table(0, 2)?.key()?.string("id")?.string("Aura")?;
+    ///     emitter.key()?.string("regionType")?.string("icon")?.end_table()?;
+    ///     emitter.key()?.string("v")?.number(2000.0)?.end_table()?;
+    ///     let input = emitter.finish()?;
+    ///
+    ///     let selector = Selector::new().with_path(["d", "id"]);
+    ///     let value = Deserializer::from_slice(&input)
+    ///         .deserialize_selected(&selector)?
+    ///         .unwrap();
+    ///
+    ///     // {d = {id = "Aura"}}
+    ///     let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    ///     let LuaValue::Map(root) = value else { unreachable!() };
+    ///     let Some(LuaValue::Map(d)) = root.get(&key("d")) else { unreachable!() };
+    ///     assert_eq!((root.len(), d.len()), (1, 1));
+    ///     assert_eq!(d.get(&key("id")), Some(&"Aura".into()));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_selected(
+        mut self,
+        selector: &Selector,
+    ) -> Result<Option<V>, DeserializationError> {
+        let result = Selection::new(&mut self).next(selector);
+        let mut result = result.map_err(|e| self.locate(e))?;
+        if let Some(ref mut v) = result {
+            self.context.finish_value(v);
+        }
 
-                self.deserialize_mixed(array_len as usize, map_len as usize)
-            }
+        Ok(result)
+    }
 
-            TypeTag::StrRef8 => {
-                let index = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidStringReference)?;
-                match self.string_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidStringReference),
-                    Some(s) => Ok(LuaValue::String(s.clone())),
-                }
-            }
-            TypeTag::StrRef16 => {
-                let index = self
-                    .deserialize_int(2)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidStringReference)?;
-                match self.string_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidStringReference),
-                    Some(s) => Ok(LuaValue::String(s.clone())),
-                }
-            }
-            TypeTag::StrRef24 => {
-                let index = self
-                    .deserialize_int(3)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidStringReference)?;
-                match self.string_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidStringReference),
-                    Some(s) => Ok(LuaValue::String(s.clone())),
-                }
-            }
+    /// Reports `error` at the last event unless it already has an offset.
+    #[cold]
+    fn locate(&self, error: DeserializationError) -> DeserializationError {
+        error.located_at(self.parser.input(), self.parser.offset())
+    }
 
-            TypeTag::MapRef8 => {
-                let index = self
-                    .reader
-                    .read_u8()
-                    .ok_or(DeserializationError::UnexpectedEof)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidMapReference)?;
-                match self.table_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidMapReference),
-                    Some(v) => Ok(v.clone()),
-                }
-            }
-            TypeTag::MapRef16 => {
-                let index = self
-                    .deserialize_int(2)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidMapReference)?;
-                match self.table_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidMapReference),
-                    Some(v) => Ok(v.clone()),
-                }
-            }
-            TypeTag::MapRef24 => {
-                let index = self
-                    .deserialize_int(3)?
-                    .checked_sub(1)
-                    .ok_or(DeserializationError::InvalidMapReference)?;
-                match self.table_refs.get(index as usize) {
-                    None => Err(DeserializationError::InvalidMapReference),
-                    Some(v) => Ok(v.clone()),
-                }
+    /// In recovery mode, stores `result`'s error and returns `None` instead of failing.
+    fn recover<T>(
+        &mut self,
+        result: Result<T, DeserializationError>,
+    ) -> Result<Option<T>, DeserializationError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.recovery => {
+                self.stopped = Some(e);
+                Ok(None)
             }
+            Err(e) => Err(e),
         }
     }
 
-    fn deserialize_string(&mut self, len: usize) -> Result<LuaValue, DeserializationError> {
-        match self.reader.read_string(len) {
-            None => Err(DeserializationError::UnexpectedEof),
-            Some(s) => {
-                let s = s.into_owned();
-                if len > 2 {
-                    self.string_refs.push(s.clone());
+    /// Like [Deserializer::recover], but for a table entry at `segment`.
+    #[inline(always)]
+    fn entry<T>(
+        &mut self,
+        result: Result<T, DeserializationError>,
+        segment: impl FnOnce() -> PathSegment,
+    ) -> Result<Option<T>, DeserializationError> {
+        match result {
+            Ok(value) => {
+                // The value is a table that was cut short by an error within it.
+                if let Some(e) = self.stopped.take() {
+                    self.stopped = Some(e.with_parent(segment()));
                 }
-
-                Ok(LuaValue::String(s))
+                Ok(Some(value))
             }
+            Err(e) => self.recover(Err(e.with_parent(segment()))),
         }
     }
 
-    fn deserialize_f64(&mut self) -> Result<f64, DeserializationError> {
-        match self.reader.read_f64() {
-            None => Err(DeserializationError::UnexpectedEof),
-            Some(v) => Ok(v),
-        }
-    }
-
-    fn deserialize_f64_from_str(&mut self) -> Result<f64, DeserializationError> {
-        let len = self
-            .reader
-            .read_u8()
-            .ok_or(DeserializationError::UnexpectedEof)?;
-
-        match self.reader.read_bytes(len as usize) {
-            None => Err(DeserializationError::UnexpectedEof),
-            Some(bytes) => core::str::from_utf8(bytes)
-                .ok()
-                .and_then(|s| s.parse::<f64>().ok())
-                .ok_or(DeserializationError::InvalidFloatNumber),
+    fn deserialize_helper(&mut self) -> Result<Option<V>, DeserializationError> {
+        let Some((_, event)) = self.parser.next_raw()? else {
+            return Ok(None);
+        };
+        let RawEvent::Token(token) = event else {
+            unreachable!("tables are consumed as a whole");
+        };
+
+        match self.context.resolve(token.map_string(V::borrow_string))? {
+            Item::Value(v) => Ok(Some(v)),
+            Item::Table(Table::Array(len)) => self.deserialize_array(len).map(Some),
+            Item::Table(Table::Map(len)) => self.deserialize_map(0, len).map(Some),
+            Item::Table(Table::Mixed { array_len, map_len }) => {
+                self.deserialize_map(array_len, map_len).map(Some)
+            }
         }
     }
 
-    fn deserialize_int(&mut self, bytes: usize) -> Result<u64, DeserializationError> {
-        match self.reader.read_int(bytes) {
-            None => Err(DeserializationError::UnexpectedEof),
-            Some(v) => Ok(v),
+    #[inline(always)]
+    fn extract_value(&mut self) -> Result<V, DeserializationError> {
+        match self.deserialize_helper() {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err(DeserializationErrorKind::UnexpectedEof.into()),
+            Err(e) => Err(e),
         }
     }
 
-    fn deserialize_map(&mut self, len: usize) -> Result<LuaValue, DeserializationError> {
-        let mut m = Map::new();
-
-        for _ in 0..len {
-            check_recursion!(self, DeserializationError, {
-                let (key, value) = (self.extract_value()?, self.extract_value()?);
-
-                m.insert(LuaMapKey::try_from(key)?, value);
-            });
-        }
-
-        let m = LuaValue::Map(m);
-        self.table_refs.push(m.clone());
-        Ok(m)
+    /// Consumes a [RawEvent::Key] or a [RawEvent::TableEnd], which are always next when expected.
+    #[inline(always)]
+    fn skip_marker(&mut self) -> Result<(), DeserializationError> {
+        let event = self.parser.next_raw()?;
+        debug_assert!(matches!(
+            event,
+            Some((_, RawEvent::Key | RawEvent::TableEnd))
+        ));
+        Ok(())
     }
 
-    fn deserialize_array(&mut self, len: usize) -> Result<LuaValue, DeserializationError> {
+    fn deserialize_array(&mut self, len: usize) -> Result<V, DeserializationError> {
         let mut v = Vec::new();
 
-        for _ in 0..len {
-            check_recursion!(self, DeserializationError, {
-                v.push(self.extract_value()?);
+        for i in 1..=len {
+            check_recursion!(self, DeserializationErrorKind, {
+                let el = self.extract_value();
+                if let Some(el) = self.entry(el, || PathSegment::Index(i as f64))? {
+                    v.push(el);
+                }
             });
+
+            if self.stopped.is_some() {
+                break;
+            }
+        }
+        if self.stopped.is_none() {
+            self.skip_marker()?;
         }
 
-        let v = LuaValue::Array(v);
-        self.table_refs.push(v.clone());
-        Ok(v)
+        Ok(self.context.finish_array(v))
     }
 
-    fn deserialize_mixed(
+    fn deserialize_map(
         &mut self,
         array_len: usize,
         map_len: usize,
-    ) -> Result<LuaValue, DeserializationError> {
-        let mut m = Map::new();
+    ) -> Result<V, DeserializationError> {
+        let mut m = self.context.new_map();
 
         for i in 1..=array_len {
-            check_recursion!(self, DeserializationError, {
-                let el = self.extract_value()?;
-                m.insert(LuaMapKey::try_from(LuaValue::Number(i as f64)).unwrap(), el);
+            check_recursion!(self, DeserializationErrorKind, {
+                let el = self.extract_value();
+                if let Some(el) = self.entry(el, || PathSegment::Index(i as f64))? {
+                    V::insert(&mut m, V::number(i as f64), el)?;
+                }
             });
+
+            if self.stopped.is_some() {
+                break;
+            }
         }
 
         for _ in 0..map_len {
-            check_recursion!(self, DeserializationError, {
-                let (key, value) = (self.extract_value()?, self.extract_value()?);
+            if self.stopped.is_some() {
+                break;
+            }
 
-                m.insert(LuaMapKey::try_from(key)?, value);
+            check_recursion!(self, DeserializationErrorKind, {
+                self.skip_marker()?;
+                let key_offset = self.parser.offset();
+                let key = self.extract_value();
+                // A key that was cut short is dropped along with its entry.
+                if let Some(key) = self.recover(key)?.filter(|_| self.stopped.is_none()) {
+                    let value = self.extract_value();
+                    if let Some(value) = self.entry(value, || key.path_segment())? {
+                        // Invalid keys are reported at the key rather than after its value.
+                        V::insert(&mut m, key, value)
+                            .map_err(|e| e.located_at(self.parser.input(), key_offset))?;
+                    }
+                }
             });
         }
+        if self.stopped.is_none() {
+            self.skip_marker()?;
+        }
 
-        let m = LuaValue::Map(m);
-        self.table_refs.push(m.clone());
-        Ok(m)
+        Ok(self.context.finish_map(m))
     }
 }
@@ -0,0 +1,303 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{
+    SliceReader,
+    token::{Token, read_token},
+};
+use crate::{
+    FORMAT_VERSION,
+    error::{DeserializationError, DeserializationErrorKind},
+};
+
+/// An event produced by [Parser].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'s> {
+    /// The start of a table with `array_len` elements in its array part
+    /// and `hash_len` key-value pairs in its hash part.
+    ///
+    /// It is followed by the events of the array elements, then by a [Event::Key]
+    /// and the events of the key and the value for each pair, then by [Event::TableEnd].
+    TableStart {
+        /// The amount of elements in the array part.
+        array_len: usize,
+        /// The amount of key-value pairs in the hash part.
+        hash_len: usize,
+    },
+    /// The start of a key-value pair, the next value is the key.
+    Key,
+    /// The end of the innermost table.
+    TableEnd,
+    /// `nil`.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(&'s [u8]),
+    /// A reference to a previous string.
+    StringRef {
+        /// A 0-based index among the referenceable strings, i.e. those longer than 2 bytes.
+        index: usize,
+        /// The referenced string.
+        value: &'s [u8],
+    },
+    /// A reference to a previous table.
+    TableRef {
+        /// A 0-based index among the tables in the order they were completed.
+        index: usize,
+        /// The offset of the referenced table's [Event::TableStart].
+        offset: usize,
+    },
+}
+
+/// An event, with references left unresolved.
+pub(super) enum RawEvent<'s> {
+    Token(Token<&'s [u8]>),
+    Key,
+    TableEnd,
+}
+
+/// The position within the hash part of a table.
+#[derive(Clone, Copy)]
+enum Entry {
+    Key,
+    KeyValue,
+    Value,
+}
+
+/// A table whose contents are being parsed.
+struct Frame {
+    offset: usize,
+    array_remaining: usize,
+    hash_remaining: usize,
+    entry: Entry,
+}
+
+/// A pull parser for data produced by LibSerialize.
+///
+/// Yields [Event]s along with the offsets in the input they start at,
+/// without building any values. Once an error has been returned, the parser yields nothing.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lib_serialize::{DeserializationError, Parser, deserialization::Event};
+///
+/// fn main() -> Result<(), DeserializationError> {
+///     // {"abc", "abc"}
+///     let events = Parser::new(b"\x01\x2a\x32abc\xd0\x01").collect::<Result<Vec<_>, _>>()?;
+///
+///     assert_eq!(
+///         events,
+///         [
+///             (1, Event::TableStart { array_len: 2, hash_len: 0 }),
+///             (2, Event::String(b"abc")),
+///             (6, Event::StringRef { index: 0, value: b"abc" }),
+///             (8, Event::TableEnd),
+///         ]
+///     );
+///     Ok(())
+/// }
+/// ```
+pub struct Parser<'s> {
+    reader: SliceReader<'s>,
+    prefix_checked: bool,
+    done: bool,
+    /// Whether references are resolved, see [Parser::next_raw].
+    resolve_refs: bool,
+    /// The offset of the last event, errors are reported there.
+    offset: usize,
+
+    stack: Vec<Frame>,
+    strings: Vec<&'s [u8]>,
+    table_offsets: Vec<usize>,
+}
+
+impl<'s> Parser<'s> {
+    /// Create a parser from a slice of bytes.
+    pub fn new(slice: &'s [u8]) -> Self {
+        Self {
+            reader: SliceReader::new(slice),
+            prefix_checked: false,
+            done: false,
+            resolve_refs: true,
+            offset: 0,
+
+            stack: Vec::new(),
+            strings: Vec::new(),
+            table_offsets: Vec::new(),
+        }
+    }
+
+    /// Parse the next event, returns `None` once all values have been parsed.
+    pub fn next_event(&mut self) -> Result<Option<(usize, Event<'s>)>, DeserializationError> {
+        let result = self.next_raw().and_then(|event| {
+            let Some((offset, event)) = event else {
+                return Ok(None);
+            };
+
+            let event = match event {
+                RawEvent::Key => Event::Key,
+                RawEvent::TableEnd => Event::TableEnd,
+                RawEvent::Token(token) => match token {
+                    Token::Null => Event::Nil,
+                    Token::Boolean(b) => Event::Bool(b),
+                    Token::Number(n) => Event::Number(n),
+                    Token::String(s) => Event::String(s),
+                    Token::StringRef(index) => Event::StringRef {
+                        index,
+                        value: self
+                            .strings
+                            .get(index)
+                            .copied()
+                            .ok_or(DeserializationErrorKind::InvalidStringReference)?,
+                    },
+                    Token::TableRef(index) => Event::TableRef {
+                        index,
+                        offset: self
+                            .table_offsets
+                            .get(index)
+                            .copied()
+                            .ok_or(DeserializationErrorKind::InvalidMapReference)?,
+                    },
+                    Token::Table(table) => {
+                        let (array_len, hash_len) = table.lens();
+                        Event::TableStart {
+                            array_len,
+                            hash_len,
+                        }
+                    }
+                },
+            };
+
+            Ok(Some((offset, event)))
+        });
+
+        result.map_err(|e| {
+            self.done = true;
+            e.located_at(self.input(), self.offset)
+        })
+    }
+
+    /// Create a parser that is only used through [Parser::next_raw].
+    pub(super) fn without_refs(slice: &'s [u8]) -> Self {
+        Self {
+            resolve_refs: false,
+            ..Self::new(slice)
+        }
+    }
+
+    /// Create a parser for the value at `offset`, which is only used through [Parser::next_raw].
+    pub(super) fn without_refs_at(slice: &'s [u8], offset: usize) -> Self {
+        Self {
+            reader: SliceReader::new_at(slice, offset),
+            prefix_checked: true,
+            offset,
+            ..Self::without_refs(slice)
+        }
+    }
+
+    /// Get the whole input.
+    pub(super) fn input(&self) -> &'s [u8] {
+        self.reader.input()
+    }
+
+    /// Get the offset of the last event.
+    pub(super) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Parse the next event without resolving references.
+    ///
+    /// [Deserializer](super::Deserializer) keeps track of references itself
+    /// and reports errors in its own order.
+    pub(super) fn next_raw(
+        &mut self,
+    ) -> Result<Option<(usize, RawEvent<'s>)>, DeserializationError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        if !self.prefix_checked {
+            match self.reader.read_u8() {
+                Some(val) if val == FORMAT_VERSION || val == FORMAT_VERSION + 1 => {
+                    self.prefix_checked = true;
+                }
+                _ => {
+                    self.done = true;
+                    return Err(DeserializationErrorKind::InvalidPrefix.into());
+                }
+            }
+        }
+
+        let offset = self.reader.position();
+        self.offset = offset;
+
+        if let Some(frame) = self.stack.last_mut() {
+            if frame.array_remaining > 0 {
+                frame.array_remaining -= 1;
+            } else {
+                match frame.entry {
+                    Entry::Key if frame.hash_remaining == 0 => {
+                        if self.resolve_refs {
+                            self.table_offsets.push(frame.offset);
+                        }
+                        self.stack.pop();
+                        return Ok(Some((offset, RawEvent::TableEnd)));
+                    }
+                    Entry::Key => {
+                        frame.entry = Entry::KeyValue;
+                        return Ok(Some((offset, RawEvent::Key)));
+                    }
+                    Entry::KeyValue => frame.entry = Entry::Value,
+                    Entry::Value => {
+                        frame.entry = Entry::Key;
+                        frame.hash_remaining -= 1;
+                    }
+                }
+            }
+        }
+
+        let token = match read_token(&mut self.reader) {
+            Ok(Some(token)) => token,
+            Ok(None) if self.stack.is_empty() => {
+                self.done = true;
+                return Ok(None);
+            }
+            Ok(None) => {
+                self.done = true;
+                return Err(DeserializationErrorKind::UnexpectedEof.into());
+            }
+            Err(e) => {
+                self.done = true;
+                return Err(e);
+            }
+        };
+
+        match token {
+            Token::String(s) if s.len() > 2 && self.resolve_refs => self.strings.push(s),
+            Token::Table(table) => {
+                let (array_remaining, hash_remaining) = table.lens();
+                self.stack.push(Frame {
+                    offset,
+                    array_remaining,
+                    hash_remaining,
+                    entry: Entry::Key,
+                });
+            }
+            _ => {}
+        }
+
+        Ok(Some((offset, RawEvent::Token(token))))
+    }
+}
+
+impl<'s> Iterator for Parser<'s> {
+    type Item = Result<(usize, Event<'s>), DeserializationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
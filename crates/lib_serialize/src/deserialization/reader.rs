@@ -1,8 +1,6 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT
 
-use std::borrow::Cow;
-
 pub(crate) struct SliceReader<'s> {
     slice: &'s [u8],
     index: usize,
@@ -13,6 +11,21 @@ pub(crate) fn new(slice: &'s [u8]) -> Self {
         Self { slice, index: 0 }
     }
 
+    /// Create a reader that starts at `index`.
+    pub(crate) fn new_at(slice: &'s [u8], index: usize) -> Self {
+        Self { slice, index }
+    }
+
+    #[inline]
+    pub(crate) fn input(&self) -> &'s [u8] {
+        self.slice
+    }
+
+    #[inline]
+    pub(crate) fn position(&self) -> usize {
+        self.index
+    }
+
     #[inline]
     pub(crate) fn read_u8(&mut self) -> Option<u8> {
         if self.index < self.slice.len() {
@@ -49,7 +62,7 @@ pub(crate) fn read_int(&mut self, bytes: usize) -> Option<u64> {
     }
 
     pub(crate) fn read_bytes(&mut self, len: usize) -> Option<&'s [u8]> {
-        if self.index + len - 1 < self.slice.len() {
+        if len <= self.slice.len() - self.index {
             let bytes = &self.slice[self.index..self.index + len];
             self.index += len;
 
@@ -58,15 +71,4 @@ pub(crate) fn read_bytes(&mut self, len: usize) -> Option<&'s [u8]> {
             None
         }
     }
-
-    pub(crate) fn read_string(&mut self, len: usize) -> Option<Cow<'s, str>> {
-        if self.index + len - 1 < self.slice.len() {
-            let s = String::from_utf8_lossy(&self.slice[self.index..self.index + len]);
-            self.index += len;
-
-            Some(s)
-        } else {
-            None
-        }
-    }
 }
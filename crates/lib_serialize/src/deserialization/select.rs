@@ -0,0 +1,373 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{
+    Deserializer, Parser, Value,
+    parser::RawEvent,
+    token::{Table, Token},
+};
+use crate::{
+    error::{DeserializationError, DeserializationErrorKind},
+    macros::check_recursion,
+};
+use core::mem;
+use weakauras_codec_lua_value::{PathSegment, Selector};
+
+const ALL: &Selector = &Selector::all();
+
+/// A token along with its offset.
+type OffsetToken<'s> = (usize, Token<&'s [u8]>);
+
+/// A table that has been either built or skipped.
+struct Slot<V> {
+    /// The offset of the table's header.
+    offset: usize,
+    /// The amounts of referenceable strings and of tables that precede the table.
+    strings_before: usize,
+    tables_before: usize,
+    /// The amount of tokens the table consists of, including its header.
+    tokens: usize,
+    /// The whole table, if it has been built.
+    value: Option<V>,
+}
+
+/// Deserializes the selected parts of values.
+///
+/// Skipped values are not built, but strings and tables are still tracked,
+/// so that references resolve correctly. A reference to a table that
+/// has not been built is resolved by parsing the table again, which is
+/// accounted for like copying the table, so that references cannot make
+/// the amount of parsing grow quadratically.
+pub(super) struct Selection<'d, 's, V: Value<'s>> {
+    remaining_depth: usize,
+    deserializer: &'d mut Deserializer<'s, V>,
+    input: &'s [u8],
+
+    strings: Vec<&'s [u8]>,
+    /// The amount of referenceable strings parsed so far,
+    /// less than the length of `strings` while a table is parsed again.
+    string_count: usize,
+    tables: Vec<Slot<V>>,
+    /// The amount of tables parsed so far, see `string_count`.
+    table_count: usize,
+    /// The amount of tokens parsed so far, including the ones parsed again.
+    token_count: usize,
+}
+
+impl<'d, 's, V: Value<'s>> Selection<'d, 's, V> {
+    pub(super) fn new(deserializer: &'d mut Deserializer<'s, V>) -> Self {
+        Self {
+            remaining_depth: deserializer.remaining_depth,
+            input: deserializer.parser.input(),
+            deserializer,
+
+            strings: Vec::new(),
+            string_count: 0,
+            tables: Vec::new(),
+            table_count: 0,
+            token_count: 0,
+        }
+    }
+
+    /// Deserializes the selected parts of the next value, returns `None` if there are no values left.
+    pub(super) fn next(&mut self, selector: &Selector) -> Result<Option<V>, DeserializationError> {
+        match self.next_token()? {
+            Some((offset, token)) => self.token_value(offset, token, Some(selector)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<OffsetToken<'s>>, DeserializationError> {
+        match self.deserializer.parser.next_raw()? {
+            Some((offset, RawEvent::Token(token))) => {
+                self.token_count += 1;
+                Ok(Some((offset, token)))
+            }
+            Some(_) => unreachable!("tables are consumed as a whole"),
+            None => Ok(None),
+        }
+    }
+
+    /// Deserializes the selected parts of the next value, or skips it if `selector` is `None`.
+    fn value(&mut self, selector: Option<&Selector>) -> Result<V, DeserializationError> {
+        match self.next_token()? {
+            Some((offset, token)) => self.token_value(offset, token, selector),
+            None => Err(DeserializationErrorKind::UnexpectedEof.into()),
+        }
+    }
+
+    fn token_value(
+        &mut self,
+        offset: usize,
+        token: Token<&'s [u8]>,
+        selector: Option<&Selector>,
+    ) -> Result<V, DeserializationError> {
+        let all = selector.is_some_and(Selector::selects_all);
+        let budget = &mut self.deserializer.context.budget;
+
+        match token {
+            Token::Table(table) => self.table(offset, table, selector),
+            Token::TableRef(index) => self.table_ref(index, selector),
+            Token::String(s) => {
+                if s.len() > 2 {
+                    if self.string_count == self.strings.len() {
+                        self.strings.push(s);
+                    }
+                    self.string_count += 1;
+                }
+
+                if !all {
+                    return Ok(V::null());
+                }
+                budget.spend_nodes(1)?;
+                budget.spend_string_bytes(s.len())?;
+                Ok(V::string(V::borrow_string(s)))
+            }
+            Token::StringRef(index) => {
+                let s = self.string(index)?;
+
+                let budget = &mut self.deserializer.context.budget;
+                if !all {
+                    return Ok(V::null());
+                }
+                budget.spend_nodes(1)?;
+                budget.spend_string_bytes(s.len())?;
+                Ok(V::string(V::borrow_string(s)))
+            }
+            _ if !all => Ok(V::null()),
+            Token::Boolean(b) => {
+                budget.spend_nodes(1)?;
+                Ok(V::boolean(b))
+            }
+            Token::Number(n) => {
+                budget.spend_nodes(1)?;
+                self.deserializer.context.non_finite.record(n);
+                Ok(V::number(n))
+            }
+            Token::Null => {
+                budget.spend_nodes(1)?;
+                Ok(V::null())
+            }
+        }
+    }
+
+    fn string(&self, index: usize) -> Result<&'s [u8], DeserializationError> {
+        if index < self.string_count {
+            Ok(self.strings[index])
+        } else {
+            Err(DeserializationErrorKind::InvalidStringReference.into())
+        }
+    }
+
+    fn table(
+        &mut self,
+        offset: usize,
+        table: Table,
+        selector: Option<&Selector>,
+    ) -> Result<V, DeserializationError> {
+        let strings_before = self.string_count;
+        let tables_before = self.table_count;
+        // The header has been parsed already.
+        let tokens_before = self.token_count - 1;
+        let (array_len, hash_len) = table.lens();
+
+        let value = match selector {
+            None => {
+                for i in 1..=array_len {
+                    check_recursion!(self, DeserializationErrorKind, {
+                        self.value(None)
+                            .map_err(|e| e.with_parent(PathSegment::Index(i as f64)))?;
+                    });
+                }
+                for _ in 0..hash_len {
+                    check_recursion!(self, DeserializationErrorKind, {
+                        self.entry(None)?;
+                    });
+                }
+
+                None
+            }
+            Some(selector) => {
+                let budget = &mut self.deserializer.context.budget;
+                budget.spend_nodes(1)?;
+                budget.check_collection_len(array_len.saturating_add(hash_len))?;
+
+                Some(self.build_table(table, selector)?)
+            }
+        };
+        self.deserializer.skip_marker()?;
+
+        // Only whole tables can be referenced, a copy of them is kept for that.
+        let slot = self.tables.get(self.table_count);
+        let copy = match value.as_ref() {
+            Some(v)
+                if selector.is_some_and(Selector::selects_all)
+                    && slot.is_none_or(|slot| slot.value.is_none()) =>
+            {
+                let budget = &mut self.deserializer.context.budget;
+                budget.spend_copied_nodes(v.clone_cost())?;
+                Some(v.clone())
+            }
+            _ => None,
+        };
+
+        match self.tables.get_mut(self.table_count) {
+            None => self.tables.push(Slot {
+                offset,
+                strings_before,
+                tables_before,
+                tokens: self.token_count - tokens_before,
+                value: copy,
+            }),
+            Some(slot) => {
+                if copy.is_some() {
+                    slot.value = copy;
+                }
+            }
+        }
+        self.table_count += 1;
+
+        Ok(value.unwrap_or_else(V::null))
+    }
+
+    fn build_table(
+        &mut self,
+        table: Table,
+        selector: &Selector,
+    ) -> Result<V, DeserializationError> {
+        if let Table::Array(len) = table {
+            let mut v = Vec::new();
+
+            for i in 1..=len {
+                check_recursion!(self, DeserializationErrorKind, {
+                    let el = self
+                        .value(selector.number_child(i as f64))
+                        .map_err(|e| e.with_parent(PathSegment::Index(i as f64)))?;
+                    v.push(el);
+                });
+            }
+
+            return Ok(self.deserializer.context.build_array(v));
+        }
+
+        let (array_len, hash_len) = table.lens();
+        let mut m = self.deserializer.context.new_map();
+
+        for i in 1..=array_len {
+            check_recursion!(self, DeserializationErrorKind, {
+                let el = self
+                    .value(selector.number_child(i as f64))
+                    .map_err(|e| e.with_parent(PathSegment::Index(i as f64)))?;
+                V::insert(&mut m, V::number(i as f64), el)?;
+            });
+        }
+
+        for _ in 0..hash_len {
+            check_recursion!(self, DeserializationErrorKind, {
+                if let Some((key, value)) = self.entry(Some(selector))? {
+                    V::insert(&mut m, key, value)?;
+                }
+            });
+        }
+
+        Ok(V::map(m))
+    }
+
+    /// Deserializes an entry of the hash part if its value is selected.
+    fn entry(
+        &mut self,
+        selector: Option<&Selector>,
+    ) -> Result<Option<(V, V)>, DeserializationError> {
+        self.deserializer.skip_marker()?;
+        let Some((offset, token)) = self.next_token()? else {
+            return Err(DeserializationErrorKind::UnexpectedEof.into());
+        };
+
+        let child = match (selector, &token) {
+            (None, _) | (_, Token::Null) => None,
+            (_, Token::Number(n)) if n.is_nan() => None,
+            (Some(selector), Token::String(s)) => selector.string_child(s),
+            (Some(selector), Token::StringRef(index)) => {
+                selector.string_child(self.string(*index)?)
+            }
+            (Some(selector), Token::Number(n)) => selector.number_child(*n),
+            (Some(selector), _) => selector.any_child(),
+        };
+
+        if let Some(child) = child {
+            let key = self.token_value(offset, token, Some(ALL))?;
+            let value = self
+                .value(Some(child))
+                .map_err(|e| e.with_parent(key.path_segment()))?;
+            return Ok(Some((key, value)));
+        }
+
+        // Invalid keys are reported after their values, like `V::insert` does.
+        let valid = match token {
+            Token::Null => false,
+            Token::Number(n) => !n.is_nan(),
+            _ => true,
+        };
+        self.token_value(offset, token, None)?;
+        self.value(None).map_err(|e| {
+            e.with_parent(match token {
+                Token::String(s) => PathSegment::from(s),
+                Token::Number(n) => PathSegment::Index(n),
+                _ => PathSegment::Any,
+            })
+        })?;
+
+        if valid {
+            Ok(None)
+        } else {
+            Err(
+                DeserializationError::from(DeserializationErrorKind::InvalidMapKeyType)
+                    .located_at(self.input, offset),
+            )
+        }
+    }
+
+    fn table_ref(
+        &mut self,
+        index: usize,
+        selector: Option<&Selector>,
+    ) -> Result<V, DeserializationError> {
+        if index >= self.table_count {
+            return Err(DeserializationErrorKind::InvalidMapReference.into());
+        }
+        let Some(selector) = selector else {
+            return Ok(V::null());
+        };
+
+        let slot = &self.tables[index];
+        if let (true, Some(v)) = (selector.selects_all(), &slot.value) {
+            let budget = &mut self.deserializer.context.budget;
+            budget.spend_nodes(1)?;
+            budget.spend_cloned_nodes(v.clone_cost())?;
+            return Ok(v.clone());
+        }
+
+        // Parse the table again, as if it was at the place of the reference.
+        // References within it are accounted for when they are resolved.
+        let budget = &mut self.deserializer.context.budget;
+        budget.spend_copied_nodes(slot.tokens)?;
+        let parser = Parser::without_refs_at(self.input, slot.offset);
+        let saved = (
+            mem::replace(&mut self.deserializer.parser, parser),
+            mem::replace(&mut self.string_count, slot.strings_before),
+            mem::replace(&mut self.table_count, slot.tables_before),
+        );
+
+        let result;
+        check_recursion!(self, DeserializationErrorKind, {
+            result = self.value(Some(selector));
+        });
+
+        (
+            self.deserializer.parser,
+            self.string_count,
+            self.table_count,
+        ) = saved;
+        result
+    }
+}
@@ -0,0 +1,245 @@
+// Based on code from LibSerialize
+// https://github.com/rossnichols/LibSerialize
+// Copyright 2020-2021 Ross Nichols
+// Copyright 2020-2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::reader::SliceReader;
+use crate::{
+    EmbeddedTypeTag, TypeTag,
+    error::{DeserializationError, DeserializationErrorKind},
+};
+
+/// A single item of serialized data: a scalar, a reference or the header of a table.
+///
+/// Reading a token has no side effects besides advancing the reader,
+/// so reading can be retried once more data is available.
+#[derive(Clone, Copy)]
+pub(super) enum Token<S> {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(S),
+    /// A 0-based index of a previously deserialized string.
+    StringRef(usize),
+    /// A 0-based index of a previously deserialized table.
+    TableRef(usize),
+    Table(Table),
+}
+
+/// The header of a table, followed by its contents.
+#[derive(Clone, Copy)]
+pub(super) enum Table {
+    Map(usize),
+    Array(usize),
+    Mixed { array_len: usize, map_len: usize },
+}
+
+impl Table {
+    /// Returns the lengths of the array part and the hash part.
+    pub(super) fn lens(self) -> (usize, usize) {
+        match self {
+            Table::Map(len) => (0, len),
+            Table::Array(len) => (len, 0),
+            Table::Mixed { array_len, map_len } => (array_len, map_len),
+        }
+    }
+}
+
+impl<S> Token<S> {
+    #[inline(always)]
+    pub(super) fn map_string<T>(self, f: impl FnOnce(S) -> T) -> Token<T> {
+        match self {
+            Token::Null => Token::Null,
+            Token::Boolean(b) => Token::Boolean(b),
+            Token::Number(n) => Token::Number(n),
+            Token::String(s) => Token::String(f(s)),
+            Token::StringRef(index) => Token::StringRef(index),
+            Token::TableRef(index) => Token::TableRef(index),
+            Token::Table(table) => Token::Table(table),
+        }
+    }
+}
+
+/// Reads the next token, returns `None` if there is no data left.
+pub(super) fn read_token<'s>(
+    reader: &mut SliceReader<'s>,
+) -> Result<Option<Token<&'s [u8]>>, DeserializationError> {
+    let Some(value) = reader.read_u8() else {
+        return Ok(None);
+    };
+
+    if value & 1 == 1 {
+        // `NNNN NNN1`: a 7 bit non-negative int
+        Ok(Some(Token::Number((value >> 1) as f64)))
+    } else if value & 3 == 2 {
+        // * `CCCC TT10`: a 2 bit type index and 4 bit count (strlen, #tab, etc.)
+        //     * Followed by the type-dependent payload
+        let tag = EmbeddedTypeTag::from_u8((value & 0x0F) >> 2)
+            .ok_or(DeserializationErrorKind::InvalidEmbeddedTag)?;
+        let len = (value >> 4) as usize;
+
+        Ok(Some(match tag {
+            EmbeddedTypeTag::Str => Token::String(read_bytes(reader, len)?),
+            EmbeddedTypeTag::Map => Token::Table(Table::Map(len)),
+            EmbeddedTypeTag::Array => Token::Table(Table::Array(len)),
+            // For MIXED, the 4-bit count contains two 2-bit counts that are one less than the true count.
+            EmbeddedTypeTag::Mixed => Token::Table(Table::Mixed {
+                array_len: (len & 3) + 1,
+                map_len: (len >> 2) + 1,
+            }),
+        }))
+    } else if value & 7 == 4 {
+        // * `NNNN S100`: the lower four bits of a 12 bit int and 1 bit for its sign
+        //     * Followed by a byte for the upper bits
+        let next_byte = reader
+            .read_u8()
+            .ok_or(DeserializationErrorKind::UnexpectedEof)? as u16;
+        let packed = (next_byte << 8) + value as u16;
+
+        Ok(Some(Token::Number(if value & 15 == 12 {
+            -((packed >> 4) as f64)
+        } else {
+            (packed >> 4) as f64
+        })))
+    } else {
+        // * `TTTT T000`: a 5 bit type index
+        //     * Followed by the type-dependent payload, including count(s) if needed
+        let tag = TypeTag::from_u8(value >> 3).ok_or(DeserializationErrorKind::InvalidTag)?;
+
+        read_tagged(reader, tag).map(Some)
+    }
+}
+
+fn read_tagged<'s>(
+    reader: &mut SliceReader<'s>,
+    tag: TypeTag,
+) -> Result<Token<&'s [u8]>, DeserializationError> {
+    Ok(match tag {
+        TypeTag::Null => Token::Null,
+
+        TypeTag::Int16Pos => Token::Number(read_int(reader, 2)? as f64),
+        TypeTag::Int16Neg => Token::Number(-(read_int(reader, 2)? as f64)),
+        TypeTag::Int24Pos => Token::Number(read_int(reader, 3)? as f64),
+        TypeTag::Int24Neg => Token::Number(-(read_int(reader, 3)? as f64)),
+        TypeTag::Int32Pos => Token::Number(read_int(reader, 4)? as f64),
+        TypeTag::Int32Neg => Token::Number(-(read_int(reader, 4)? as f64)),
+        TypeTag::Int64Pos => Token::Number(read_int(reader, 7)? as f64),
+        TypeTag::Int64Neg => Token::Number(-(read_int(reader, 7)? as f64)),
+
+        TypeTag::Float => Token::Number(
+            reader
+                .read_f64()
+                .ok_or(DeserializationErrorKind::UnexpectedEof)?,
+        ),
+        TypeTag::FloatStrPos => Token::Number(read_f64_from_str(reader)?),
+        TypeTag::FloatStrNeg => Token::Number(-read_f64_from_str(reader)?),
+
+        TypeTag::True => Token::Boolean(true),
+        TypeTag::False => Token::Boolean(false),
+
+        TypeTag::Str8 => {
+            let len = read_int(reader, 1)?;
+            Token::String(read_bytes(reader, len as usize)?)
+        }
+        TypeTag::Str16 => {
+            let len = read_int(reader, 2)?;
+            Token::String(read_bytes(reader, len as usize)?)
+        }
+        TypeTag::Str24 => {
+            let len = read_int(reader, 3)?;
+            Token::String(read_bytes(reader, len as usize)?)
+        }
+
+        TypeTag::Map8 => Token::Table(Table::Map(read_int(reader, 1)? as usize)),
+        TypeTag::Map16 => Token::Table(Table::Map(read_int(reader, 2)? as usize)),
+        TypeTag::Map24 => Token::Table(Table::Map(read_int(reader, 3)? as usize)),
+
+        TypeTag::Array8 => Token::Table(Table::Array(read_int(reader, 1)? as usize)),
+        TypeTag::Array16 => Token::Table(Table::Array(read_int(reader, 2)? as usize)),
+        TypeTag::Array24 => Token::Table(Table::Array(read_int(reader, 3)? as usize)),
+
+        TypeTag::Mixed8 => read_mixed(reader, 1)?,
+        TypeTag::Mixed16 => read_mixed(reader, 2)?,
+        TypeTag::Mixed24 => read_mixed(reader, 3)?,
+
+        TypeTag::StrRef8 => Token::StringRef(read_ref(
+            reader,
+            1,
+            DeserializationErrorKind::InvalidStringReference,
+        )?),
+        TypeTag::StrRef16 => Token::StringRef(read_ref(
+            reader,
+            2,
+            DeserializationErrorKind::InvalidStringReference,
+        )?),
+        TypeTag::StrRef24 => Token::StringRef(read_ref(
+            reader,
+            3,
+            DeserializationErrorKind::InvalidStringReference,
+        )?),
+
+        TypeTag::MapRef8 => Token::TableRef(read_ref(
+            reader,
+            1,
+            DeserializationErrorKind::InvalidMapReference,
+        )?),
+        TypeTag::MapRef16 => Token::TableRef(read_ref(
+            reader,
+            2,
+            DeserializationErrorKind::InvalidMapReference,
+        )?),
+        TypeTag::MapRef24 => Token::TableRef(read_ref(
+            reader,
+            3,
+            DeserializationErrorKind::InvalidMapReference,
+        )?),
+    })
+}
+
+#[inline(always)]
+fn read_int(reader: &mut SliceReader<'_>, bytes: usize) -> Result<u64, DeserializationError> {
+    reader
+        .read_int(bytes)
+        .ok_or(DeserializationErrorKind::UnexpectedEof.into())
+}
+
+#[inline(always)]
+fn read_bytes<'s>(
+    reader: &mut SliceReader<'s>,
+    len: usize,
+) -> Result<&'s [u8], DeserializationError> {
+    reader
+        .read_bytes(len)
+        .ok_or(DeserializationErrorKind::UnexpectedEof.into())
+}
+
+fn read_f64_from_str(reader: &mut SliceReader<'_>) -> Result<f64, DeserializationError> {
+    let len = read_int(reader, 1)?;
+    let bytes = read_bytes(reader, len as usize)?;
+
+    core::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or(DeserializationErrorKind::InvalidFloatNumber.into())
+}
+
+fn read_mixed<S>(
+    reader: &mut SliceReader<'_>,
+    bytes: usize,
+) -> Result<Token<S>, DeserializationError> {
+    let array_len = read_int(reader, bytes)? as usize;
+    let map_len = read_int(reader, bytes)? as usize;
+
+    Ok(Token::Table(Table::Mixed { array_len, map_len }))
+}
+
+/// Reads a 1-based reference and converts it into a 0-based index.
+fn read_ref(
+    reader: &mut SliceReader<'_>,
+    bytes: usize,
+    error: DeserializationErrorKind,
+) -> Result<usize, DeserializationError> {
+    let index = read_int(reader, bytes)?;
+    index.checked_sub(1).map(|i| i as usize).ok_or(error.into())
+}
@@ -0,0 +1,353 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::error::DeserializationError;
+use std::borrow::Cow;
+use std::sync::Arc;
+use weakauras_codec_lua_value::{
+    CompactLuaValue, CompactString, LuaMap, LuaMapKey, LuaString, LuaValue, LuaValueRef,
+    MapBackend, NormalizationPolicy, PathSegment, error::TryFromLuaValueError,
+};
+
+/// A type that [Deserializer](super::Deserializer) can produce
+/// from input that lives for `'s`.
+///
+/// This trait is sealed and implemented for [LuaValue], [CompactLuaValue] and [LuaValueRef].
+pub trait Value<'s>: private::Value<'s> {}
+
+impl Value<'_> for LuaValue {}
+impl Value<'_> for CompactLuaValue {}
+impl<'s> Value<'s> for LuaValueRef<'s> {}
+
+pub(super) mod private {
+    use super::*;
+
+    pub trait Value<'s>: Clone {
+        type String: Clone;
+        type Map;
+
+        fn null() -> Self;
+        fn boolean(value: bool) -> Self;
+        fn number(value: f64) -> Self;
+        fn is_null(&self) -> bool;
+        /// Returns the path segment of the entry that has `self` as its key.
+        fn path_segment(&self) -> PathSegment;
+
+        fn new_string(bytes: &[u8]) -> Self::String;
+        /// Like [Value::new_string], but may borrow `bytes`.
+        #[inline(always)]
+        fn borrow_string(bytes: &'s [u8]) -> Self::String {
+            Self::new_string(bytes)
+        }
+        fn string_len(value: &Self::String) -> usize;
+        fn string(value: Self::String) -> Self;
+
+        fn new_map(backend: MapBackend) -> Self::Map;
+        /// Validates the key and inserts the entry unless the value is `nil`.
+        fn insert(map: &mut Self::Map, key: Self, value: Self) -> Result<(), DeserializationError>;
+        fn map(map: Self::Map) -> Self;
+        /// Expects `elements` not to end with a `nil`.
+        fn array(elements: Vec<Self>, policy: NormalizationPolicy, backend: MapBackend) -> Self;
+
+        fn normalize(&mut self, policy: NormalizationPolicy);
+
+        /// Returns the number of values created by cloning `self`.
+        fn clone_cost(&self) -> usize;
+    }
+}
+
+impl private::Value<'_> for LuaValue {
+    type String = LuaString;
+    type Map = LuaMap;
+
+    #[inline(always)]
+    fn null() -> Self {
+        LuaValue::Null
+    }
+
+    #[inline(always)]
+    fn boolean(value: bool) -> Self {
+        LuaValue::Boolean(value)
+    }
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        LuaValue::Number(value)
+    }
+
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        LuaValue::is_null(self)
+    }
+
+    fn path_segment(&self) -> PathSegment {
+        match self {
+            LuaValue::String(s) => PathSegment::Key(s.clone()),
+            LuaValue::Number(n) => PathSegment::Index(*n),
+            _ => PathSegment::Any,
+        }
+    }
+
+    #[inline(always)]
+    fn new_string(bytes: &[u8]) -> LuaString {
+        LuaString::from(bytes)
+    }
+
+    #[inline(always)]
+    fn string_len(value: &LuaString) -> usize {
+        value.len()
+    }
+
+    #[inline(always)]
+    fn string(value: LuaString) -> Self {
+        LuaValue::String(value)
+    }
+
+    #[inline(always)]
+    fn new_map(backend: MapBackend) -> LuaMap {
+        LuaMap::with_backend(backend)
+    }
+
+    #[inline(always)]
+    fn insert(map: &mut LuaMap, key: Self, value: Self) -> Result<(), DeserializationError> {
+        let key = LuaMapKey::try_from(key)?;
+
+        if !value.is_null() {
+            map.insert(key, value);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn map(map: LuaMap) -> Self {
+        LuaValue::Map(map)
+    }
+
+    fn array(elements: Vec<Self>, policy: NormalizationPolicy, backend: MapBackend) -> Self {
+        if policy == NormalizationPolicy::PreferMaps {
+            let mut m = LuaMap::with_backend(backend);
+            m.extend(
+                elements
+                    .into_iter()
+                    .zip(1..)
+                    .filter(|(v, _)| !v.is_null())
+                    .map(|(v, i)| (LuaMapKey::try_from(LuaValue::Number(i as f64)).unwrap(), v)),
+            );
+            LuaValue::Map(m)
+        } else {
+            LuaValue::Array(elements)
+        }
+    }
+
+    #[inline(always)]
+    fn normalize(&mut self, policy: NormalizationPolicy) {
+        LuaValue::normalize(self, policy)
+    }
+
+    fn clone_cost(&self) -> usize {
+        match self {
+            LuaValue::Map(m) => m.iter().fold(1, |acc, (k, v)| {
+                acc + k.as_value().clone_cost() + v.clone_cost()
+            }),
+            LuaValue::Array(v) => v.iter().fold(1, |acc, v| acc + v.clone_cost()),
+            _ => 1,
+        }
+    }
+}
+
+type CompactEntries = Vec<(CompactLuaValue, CompactLuaValue)>;
+
+impl private::Value<'_> for CompactLuaValue {
+    type String = CompactString;
+    type Map = CompactEntries;
+
+    #[inline(always)]
+    fn null() -> Self {
+        CompactLuaValue::Null
+    }
+
+    #[inline(always)]
+    fn boolean(value: bool) -> Self {
+        CompactLuaValue::Boolean(value)
+    }
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        CompactLuaValue::Number(value)
+    }
+
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        CompactLuaValue::is_null(self)
+    }
+
+    fn path_segment(&self) -> PathSegment {
+        match self {
+            CompactLuaValue::String(s) => PathSegment::from(s.as_bytes()),
+            CompactLuaValue::Number(n) => PathSegment::Index(*n),
+            _ => PathSegment::Any,
+        }
+    }
+
+    #[inline(always)]
+    fn new_string(bytes: &[u8]) -> CompactString {
+        CompactString::new(bytes)
+    }
+
+    #[inline(always)]
+    fn string_len(value: &CompactString) -> usize {
+        value.len()
+    }
+
+    #[inline(always)]
+    fn string(value: CompactString) -> Self {
+        CompactLuaValue::String(value)
+    }
+
+    #[inline(always)]
+    fn new_map(_backend: MapBackend) -> CompactEntries {
+        Vec::new()
+    }
+
+    #[inline(always)]
+    fn insert(
+        map: &mut CompactEntries,
+        key: Self,
+        value: Self,
+    ) -> Result<(), DeserializationError> {
+        match key {
+            CompactLuaValue::Null => return Err(TryFromLuaValueError::KeyCannotBeNull.into()),
+            CompactLuaValue::Number(n) if n.is_nan() => {
+                return Err(TryFromLuaValueError::KeyCannotBeNan.into());
+            }
+            _ => {}
+        }
+
+        if !value.is_null() {
+            map.push((key, value));
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn map(map: CompactEntries) -> Self {
+        CompactLuaValue::Map(Arc::from(map))
+    }
+
+    #[inline(always)]
+    fn array(elements: Vec<Self>, _policy: NormalizationPolicy, _backend: MapBackend) -> Self {
+        CompactLuaValue::Array(Arc::from(elements))
+    }
+
+    #[inline(always)]
+    fn normalize(&mut self, _policy: NormalizationPolicy) {}
+
+    #[inline(always)]
+    fn clone_cost(&self) -> usize {
+        // Tables are shared instead of copied.
+        0
+    }
+}
+
+type RefEntries<'s> = Vec<(LuaValueRef<'s>, LuaValueRef<'s>)>;
+
+impl<'s> private::Value<'s> for LuaValueRef<'s> {
+    type String = Cow<'s, [u8]>;
+    type Map = RefEntries<'s>;
+
+    #[inline(always)]
+    fn null() -> Self {
+        LuaValueRef::Null
+    }
+
+    #[inline(always)]
+    fn boolean(value: bool) -> Self {
+        LuaValueRef::Boolean(value)
+    }
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        LuaValueRef::Number(value)
+    }
+
+    #[inline(always)]
+    fn is_null(&self) -> bool {
+        LuaValueRef::is_null(self)
+    }
+
+    fn path_segment(&self) -> PathSegment {
+        match self {
+            LuaValueRef::String(s) => PathSegment::from(&**s),
+            LuaValueRef::Number(n) => PathSegment::Index(*n),
+            _ => PathSegment::Any,
+        }
+    }
+
+    #[inline(always)]
+    fn new_string(bytes: &[u8]) -> Cow<'s, [u8]> {
+        Cow::Owned(bytes.to_vec())
+    }
+
+    #[inline(always)]
+    fn borrow_string(bytes: &'s [u8]) -> Cow<'s, [u8]> {
+        Cow::Borrowed(bytes)
+    }
+
+    #[inline(always)]
+    fn string_len(value: &Cow<'s, [u8]>) -> usize {
+        value.len()
+    }
+
+    #[inline(always)]
+    fn string(value: Cow<'s, [u8]>) -> Self {
+        LuaValueRef::String(value)
+    }
+
+    #[inline(always)]
+    fn new_map(_backend: MapBackend) -> RefEntries<'s> {
+        Vec::new()
+    }
+
+    #[inline(always)]
+    fn insert(
+        map: &mut RefEntries<'s>,
+        key: Self,
+        value: Self,
+    ) -> Result<(), DeserializationError> {
+        match key {
+            LuaValueRef::Null => return Err(TryFromLuaValueError::KeyCannotBeNull.into()),
+            LuaValueRef::Number(n) if n.is_nan() => {
+                return Err(TryFromLuaValueError::KeyCannotBeNan.into());
+            }
+            _ => {}
+        }
+
+        if !value.is_null() {
+            map.push((key, value));
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn map(map: RefEntries<'s>) -> Self {
+        LuaValueRef::Map(map)
+    }
+
+    #[inline(always)]
+    fn array(elements: Vec<Self>, _policy: NormalizationPolicy, _backend: MapBackend) -> Self {
+        LuaValueRef::Array(elements)
+    }
+
+    #[inline(always)]
+    fn normalize(&mut self, _policy: NormalizationPolicy) {}
+
+    fn clone_cost(&self) -> usize {
+        match self {
+            LuaValueRef::Map(m) => m
+                .iter()
+                .fold(1, |acc, (k, v)| acc + k.clone_cost() + v.clone_cost()),
+            LuaValueRef::Array(v) => v.iter().fold(1, |acc, v| acc + v.clone_cost()),
+            _ => 1,
+        }
+    }
+}
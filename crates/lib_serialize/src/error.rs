@@ -2,15 +2,18 @@
 // SPDX-License-Identifier: MIT
 
 use core::fmt;
-use std::error;
+use std::{error, io};
 
 use core::num::ParseFloatError;
-use weakauras_codec_lua_value::error::TryFromLuaValueError;
+use weakauras_codec_lua_value::{
+    ErrorLocation, PathSegment,
+    error::{ResourceLimitExceeded, TryFromLuaValueError},
+};
 
-/// Errors than can occur while deserializing.
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Kinds of errors than can occur while deserializing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[non_exhaustive]
-pub enum DeserializationError {
+pub enum DeserializationErrorKind {
     /// The input does not start with a valid prefix.
     InvalidPrefix,
     /// Invalid tag.
@@ -30,32 +33,127 @@ pub enum DeserializationError {
     UnexpectedEof,
     /// Exceeded recursion limit while deserializing nested data.
     RecursionLimitExceeded,
+    /// Exceeded one of the [ResourceLimits](weakauras_codec_lua_value::ResourceLimits).
+    ResourceLimitExceeded(ResourceLimitExceeded),
+}
+
+impl fmt::Display for DeserializationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidPrefix => write!(f, "Invalid prefix"),
+            Self::InvalidTag => write!(f, "Invalid tag"),
+            Self::InvalidEmbeddedTag => write!(f, "Invalid embedded tag"),
+            Self::InvalidStringReference => write!(f, "Invalid string reference"),
+            Self::InvalidMapReference => write!(f, "Invalid map reference"),
+            Self::InvalidFloatNumber => write!(f, "Failed to parse a floating-point number"),
+            Self::InvalidMapKeyType => write!(f, "Invalid map key type"),
+            Self::UnexpectedEof => write!(f, "Unexpected EOF"),
+            Self::RecursionLimitExceeded => write!(f, "Recursion limit exceeded"),
+            Self::ResourceLimitExceeded(e) => write!(f, "Resource limit exceeded: {e}"),
+        }
+    }
+}
+
+/// An error that occurred while deserializing.
+///
+/// Besides its [kind](DeserializationError::kind), it knows the offset within the input
+/// and the path to the value being deserialized when it occurred, see [ErrorLocation].
+/// Its `Display` renders both, e.g.
+///
+/// ```text
+/// Unexpected EOF at offset 6 in a[2]
+///     \x01\x16\x12a*\x03\xf8
+///                       ^
+/// ```
+///
+/// This used to be an enum of what is now [DeserializationErrorKind]:
+/// match on [DeserializationError::kind] instead of the error itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeserializationError {
+    kind: DeserializationErrorKind,
+    // Boxed to keep results small. The path is collected while the error propagates,
+    // the offset is known once it reaches the deserializer.
+    location: Option<Box<ErrorLocation>>,
+    located: bool,
+}
+
+impl DeserializationError {
+    /// Get the kind of the error.
+    pub fn kind(&self) -> DeserializationErrorKind {
+        self.kind
+    }
+
+    /// Get where the error occurred.
+    ///
+    /// Errors returned by this crate always have a location.
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        self.location.as_deref().filter(|_| self.located)
+    }
+
+    /// Prepends `segment` to the path.
+    #[cold]
+    pub(crate) fn with_parent(mut self, segment: PathSegment) -> Self {
+        let location = self.location.take().map(|l| *l).unwrap_or_default();
+        self.location = Some(Box::new(location.with_parent(segment)));
+        self
+    }
+
+    /// Sets the offset unless it is already known.
+    #[cold]
+    pub(crate) fn located_at(mut self, input: &[u8], offset: usize) -> Self {
+        if !self.located {
+            let path = self
+                .location
+                .take()
+                .map(|mut l| l.take_path())
+                .unwrap_or_default();
+            self.location = Some(Box::new(ErrorLocation::new(input, offset).with_path(path)));
+            self.located = true;
+        }
+        self
+    }
+
+    /// Shifts the offset by `base`.
+    #[cold]
+    pub(crate) fn with_base_offset(mut self, base: usize) -> Self {
+        self.location = self.location.map(|l| Box::new(l.with_base_offset(base)));
+        self
+    }
+}
+
+impl From<DeserializationErrorKind> for DeserializationError {
+    fn from(kind: DeserializationErrorKind) -> Self {
+        Self {
+            kind,
+            location: None,
+            located: false,
+        }
+    }
 }
 
 impl From<ParseFloatError> for DeserializationError {
     fn from(_value: ParseFloatError) -> Self {
-        Self::InvalidFloatNumber
+        DeserializationErrorKind::InvalidFloatNumber.into()
     }
 }
 
 impl From<TryFromLuaValueError> for DeserializationError {
     fn from(_value: TryFromLuaValueError) -> Self {
-        Self::InvalidMapKeyType
+        DeserializationErrorKind::InvalidMapKeyType.into()
+    }
+}
+
+impl From<ResourceLimitExceeded> for DeserializationError {
+    fn from(value: ResourceLimitExceeded) -> Self {
+        DeserializationErrorKind::ResourceLimitExceeded(value).into()
     }
 }
 
 impl fmt::Display for DeserializationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::InvalidPrefix => write!(f, "Invalid prefix"),
-            Self::InvalidTag => write!(f, "Invalid tag"),
-            Self::InvalidEmbeddedTag => write!(f, "Invalid embedded tag"),
-            Self::InvalidStringReference => write!(f, "Invalid string reference"),
-            Self::InvalidMapReference => write!(f, "Invalid map reference"),
-            Self::InvalidFloatNumber => write!(f, "Failed to parse a floating-point number"),
-            Self::InvalidMapKeyType => write!(f, "Invalid map key type"),
-            Self::UnexpectedEof => write!(f, "Unexpected EOF"),
-            Self::RecursionLimitExceeded => write!(f, "Recursion limit exceeded"),
+        match self.location() {
+            Some(location) => write!(f, "{} {location}", self.kind),
+            None => self.kind.fmt(f),
         }
     }
 }
@@ -75,6 +173,13 @@ pub enum SerializationError {
     ArrayIsTooLarge,
     /// Exceeded recursion limit while serializing nested data.
     RecursionLimitExceeded,
+    /// A map key is `nil` or NaN.
+    UnserializableKey,
+    /// Encountered a NaN while [NonFinitePolicy::Error](crate::NonFinitePolicy::Error) applies to NaNs.
+    NanEncountered,
+    /// Encountered an infinity while [NonFinitePolicy::Error](crate::NonFinitePolicy::Error)
+    /// applies to infinities.
+    InfinityEncountered,
 }
 
 impl fmt::Display for SerializationError {
@@ -85,8 +190,116 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             Self::MapIsTooLarge => write!(f, "Map is too large"),
             Self::ArrayIsTooLarge => write!(f, "Array is too large"),
             Self::RecursionLimitExceeded => write!(f, "Recursion limit exceeded"),
+            Self::UnserializableKey => write!(f, "Map key is nil or NaN"),
+            Self::NanEncountered => write!(f, "Encountered a NaN"),
+            Self::InfinityEncountered => write!(f, "Encountered an infinity"),
         }
     }
 }
 
 impl error::Error for SerializationError {}
+
+/// Errors than can occur while serializing into a writer.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WriteError {
+    /// The value cannot be serialized.
+    SerializationError(SerializationError),
+    /// The writer returned an error.
+    IoError(io::Error),
+}
+
+impl From<SerializationError> for WriteError {
+    fn from(value: SerializationError) -> Self {
+        Self::SerializationError(value)
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(value: io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SerializationError(inner) => inner.fmt(f),
+            Self::IoError(inner) => write!(f, "Failed to write data: {}", inner),
+        }
+    }
+}
+
+impl error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::SerializationError(inner) => Some(inner),
+            Self::IoError(inner) => Some(inner),
+        }
+    }
+}
+
+/// Errors than can occur while emitting values with an [Emitter](crate::Emitter).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EmitError {
+    /// A table received more or fewer entries than declared in `begin_table`.
+    CountMismatch,
+    /// A value was emitted in the hash part of a table without calling `key` first.
+    MissingKey,
+    /// `key` was called outside of the hash part of a table or twice in a row.
+    UnexpectedKey,
+    /// `end_table` was called outside of a table.
+    UnexpectedTableEnd,
+    /// `finish` was called before all tables were ended.
+    UnclosedTable,
+    /// The value cannot be serialized.
+    SerializationError(SerializationError),
+    /// The writer returned an error.
+    IoError(io::Error),
+}
+
+impl From<SerializationError> for EmitError {
+    fn from(value: SerializationError) -> Self {
+        Self::SerializationError(value)
+    }
+}
+
+impl From<io::Error> for EmitError {
+    fn from(value: io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+impl From<WriteError> for EmitError {
+    fn from(value: WriteError) -> Self {
+        match value {
+            WriteError::SerializationError(e) => Self::SerializationError(e),
+            WriteError::IoError(e) => Self::IoError(e),
+        }
+    }
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CountMismatch => write!(f, "Table entries do not match the declared counts"),
+            Self::MissingKey => write!(f, "A value in the hash part is missing a key"),
+            Self::UnexpectedKey => write!(f, "Unexpected key"),
+            Self::UnexpectedTableEnd => write!(f, "Unexpected end of a table"),
+            Self::UnclosedTable => write!(f, "A table was not ended"),
+            Self::SerializationError(inner) => inner.fmt(f),
+            Self::IoError(inner) => write!(f, "Failed to write data: {}", inner),
+        }
+    }
+}
+
+impl error::Error for EmitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::SerializationError(inner) => Some(inner),
+            Self::IoError(inner) => Some(inner),
+            _ => None,
+        }
+    }
+}
@@ -41,8 +41,8 @@
 //! # Crate features
 //!
 //! * **lua-value-arbitrary** - Implement `arbitrary::Arbitrary` for [`LuaValue`]. **Disabled** by default.
-//! * **lua-value-fnv** - Use `fnv` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
-//! * **lua-value-indexmap** - Use `indexmap` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
+//! * **lua-value-fnv** - Make `fnv` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
+//! * **lua-value-indexmap** - Make `indexmap` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
 //! * **serde** - Allow serializing and deserializing [`LuaValue`] using `serde`. **Disabled** by default.
 
 #![deny(missing_docs)]
@@ -56,10 +56,16 @@
 pub mod serialization;
 pub(crate) mod type_tag;
 
-pub use deserialization::Deserializer;
+pub use deserialization::{Deserializer, IncrementalDeserializer, Parser};
 pub use error::*;
-pub use serialization::Serializer;
+pub use serialization::{Emitter, SerializeOptions, Serializer};
 pub(crate) use type_tag::{EmbeddedTypeTag, TypeTag};
-pub use weakauras_codec_lua_value::LuaValue;
+pub use weakauras_codec_lua_value::{
+    CompactLuaValue, LuaValue, LuaValueRef, MapBackend, NonFinitePolicy, NonFiniteReport,
+    NormalizationPolicy, PathSegment, ResourceLimits, Selector,
+};
 
 pub(crate) const FORMAT_VERSION: u8 = 1;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,220 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use super::{FLUSH_THRESHOLD, Serializer, TYPE_TAG_SHIFT};
+use crate::{
+    FORMAT_VERSION, TypeTag,
+    error::{EmitError, SerializationError},
+};
+use std::io;
+use weakauras_codec_lua_value::NonFinitePolicy;
+
+/// The position within the hash part of a table.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Key,
+    KeyValue,
+    Value,
+}
+
+/// A table whose contents are being emitted.
+struct Frame {
+    array_remaining: usize,
+    hash_remaining: usize,
+    entry: Entry,
+}
+
+/// A builder that writes values in LibSerialize's format as they are described,
+/// without building a [LuaValue](crate::LuaValue) first.
+///
+/// A table is described by [Emitter::begin_table] with the lengths of its parts,
+/// followed by the elements of the array part, a [Emitter::key], the key and the value
+/// for each entry of the hash part, and [Emitter::end_table]. The declared lengths
+/// are checked and determine how the table is written, see
+/// [Serializer::serialize_one](super::Serializer::serialize_one).
+///
+/// Repeated strings are written as references, like [Serializer](super::Serializer) does.
+/// Once a method fails, the emitter must not be used further.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lib_serialize::{EmitError, Emitter};
+///
+/// fn main() -> Result<(), EmitError> {
+///     // {"Aura", "Aura", id = 1}
+///     let mut emitter = Emitter::new(Vec::new());
+///     emitter.begin_table(2, 1)?.string("Aura")?.string("Aura")?;
+///     emitter.key()?.string("id")?.number(1.0)?.end_table()?;
+///
+///     assert_eq!(emitter.finish()?, b"\x01\x1e\x42Aura\xd0\x01\x22id\x03");
+///     Ok(())
+/// }
+/// ```
+pub struct Emitter<W: io::Write> {
+    serializer: Serializer<'static>,
+    writer: W,
+    stack: Vec<Frame>,
+}
+
+impl<W: io::Write> Emitter<W> {
+    /// Create an emitter that writes into `writer`.
+    pub fn new(writer: W) -> Self {
+        let mut serializer = Serializer::new(FLUSH_THRESHOLD * 2, None);
+        serializer.result.push(FORMAT_VERSION);
+
+        Self {
+            serializer,
+            writer,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Set the policy for emitting NaNs, see
+    /// [SerializeOptions::with_nan_policy](crate::SerializeOptions::with_nan_policy).
+    pub fn with_nan_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.serializer.nan_policy = policy;
+        self
+    }
+
+    /// Set the policy for emitting infinities, see
+    /// [SerializeOptions::with_infinity_policy](crate::SerializeOptions::with_infinity_policy).
+    pub fn with_infinity_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.serializer.infinity_policy = policy;
+        self
+    }
+
+    /// Begin a table with `array_len` elements in its array part
+    /// and `hash_len` entries in its hash part.
+    pub fn begin_table(
+        &mut self,
+        array_len: usize,
+        hash_len: usize,
+    ) -> Result<&mut Self, EmitError> {
+        self.start_value(true)?;
+        self.serializer.serialize_table_start(array_len, hash_len)?;
+        self.stack.push(Frame {
+            array_remaining: array_len,
+            hash_remaining: hash_len,
+            entry: Entry::Key,
+        });
+
+        Ok(self)
+    }
+
+    /// Begin an entry of the hash part, the next value is its key.
+    pub fn key(&mut self) -> Result<&mut Self, EmitError> {
+        match self.stack.last_mut() {
+            Some(frame) if frame.entry == Entry::Key => {
+                if frame.array_remaining > 0 || frame.hash_remaining == 0 {
+                    return Err(EmitError::CountMismatch);
+                }
+                frame.entry = Entry::KeyValue;
+            }
+            _ => return Err(EmitError::UnexpectedKey),
+        }
+
+        Ok(self)
+    }
+
+    /// End the innermost table.
+    pub fn end_table(&mut self) -> Result<&mut Self, EmitError> {
+        match self.stack.last() {
+            None => return Err(EmitError::UnexpectedTableEnd),
+            Some(frame)
+                if frame.array_remaining > 0
+                    || frame.hash_remaining > 0
+                    || frame.entry != Entry::Key =>
+            {
+                return Err(EmitError::CountMismatch);
+            }
+            Some(_) => {
+                self.stack.pop();
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Emit `nil`.
+    pub fn nil(&mut self) -> Result<&mut Self, EmitError> {
+        self.start_value(false)?;
+        self.serializer
+            .result
+            .push(TypeTag::Null.to_u8() << TYPE_TAG_SHIFT);
+
+        Ok(self)
+    }
+
+    /// Emit a boolean.
+    pub fn boolean(&mut self, value: bool) -> Result<&mut Self, EmitError> {
+        self.start_value(true)?;
+        let tag = if value { TypeTag::True } else { TypeTag::False };
+        self.serializer.result.push(tag.to_u8() << TYPE_TAG_SHIFT);
+
+        Ok(self)
+    }
+
+    /// Emit a number.
+    pub fn number(&mut self, value: f64) -> Result<&mut Self, EmitError> {
+        let Some(value) = self.serializer.apply_non_finite_policy(value)? else {
+            return self.nil();
+        };
+
+        self.start_value(!value.is_nan())?;
+        self.serializer.serialize_number(value);
+
+        Ok(self)
+    }
+
+    /// Emit a string.
+    pub fn string(&mut self, value: impl AsRef<[u8]>) -> Result<&mut Self, EmitError> {
+        self.start_value(true)?;
+        self.serializer.serialize_string(value.as_ref())?;
+
+        Ok(self)
+    }
+
+    /// Write the remaining output, flush the writer and return it.
+    pub fn finish(mut self) -> Result<W, EmitError> {
+        if !self.stack.is_empty() {
+            return Err(EmitError::UnclosedTable);
+        }
+
+        self.writer.write_all(&self.serializer.result)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Advances the innermost table to the next value, which can be used as a key if `valid_key`.
+    fn start_value(&mut self, valid_key: bool) -> Result<(), EmitError> {
+        if self.serializer.result.len() >= FLUSH_THRESHOLD {
+            self.writer.write_all(&self.serializer.result)?;
+            self.serializer.result.clear();
+        }
+
+        let Some(frame) = self.stack.last_mut() else {
+            return Ok(());
+        };
+
+        if frame.array_remaining > 0 {
+            frame.array_remaining -= 1;
+            return Ok(());
+        }
+
+        match frame.entry {
+            Entry::Key if frame.hash_remaining == 0 => return Err(EmitError::CountMismatch),
+            Entry::Key => return Err(EmitError::MissingKey),
+            Entry::KeyValue if !valid_key => {
+                return Err(SerializationError::UnserializableKey.into());
+            }
+            Entry::KeyValue => frame.entry = Entry::Value,
+            Entry::Value => {
+                frame.entry = Entry::Key;
+                frame.hash_remaining -= 1;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -4,15 +4,30 @@
 // Copyright 2020-2025 Velithris
 // SPDX-License-Identifier: MIT
 
+mod emitter;
+mod options;
+mod value;
+
 use crate::{
-    EmbeddedTypeTag, FORMAT_VERSION, TypeTag, error::SerializationError, macros::check_recursion,
+    EmbeddedTypeTag, FORMAT_VERSION, TypeTag,
+    error::{SerializationError, WriteError},
+    macros::check_recursion,
 };
-use weakauras_codec_lua_value::{LuaMapKey, LuaValue, Map};
+use core::cmp::Ordering;
+pub use emitter::Emitter;
+use options::FilterFn;
+pub use options::SerializeOptions;
+use std::{collections::HashMap, io};
+use value::{Kind, Value};
+use weakauras_codec_lua_value::{CompactLuaValue, LuaValue, NonFinitePolicy, format_g14};
 
 const TYPE_TAG_SHIFT: u8 = 3;
 const EMBEDDED_TYPE_TAG_SHIFT: u8 = 2;
 const EMBEDDED_LEN_SHIFT: u8 = 4;
 
+/// The amount of buffered output after which it is passed to the writer.
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+
 fn required_bytes(v: u64) -> u8 {
     match v {
         0..=255 => 1,
@@ -23,7 +38,7 @@ fn required_bytes(v: u64) -> u8 {
     }
 }
 
-/// A structure for serializing [LuaValues](LuaValue).
+/// A structure for serializing [LuaValues](LuaValue) and [CompactLuaValues](CompactLuaValue).
 ///
 /// # Example
 ///
@@ -38,68 +53,331 @@ fn required_bytes(v: u64) -> u8 {
 ///     Ok(())
 /// }
 /// ```
-pub struct Serializer {
+pub struct Serializer<'w> {
     remaining_depth: usize,
     result: Vec<u8>,
+    writer: Option<&'w mut dyn io::Write>,
+
+    string_refs: HashMap<Vec<u8>, usize>,
+    table_refs: HashMap<usize, usize>,
+
+    float_buffer: String,
+    stable: bool,
+    error_on_unserializable_type: bool,
+    nan_policy: NonFinitePolicy,
+    infinity_policy: NonFinitePolicy,
+}
 
-    string_refs: Map<String, usize>,
+/// The state of a filter from [SerializeOptions].
+struct Filter<'f, V> {
+    callback: FilterFn<'f, V>,
+    path: Vec<V>,
 }
 
-impl Serializer {
+impl<'w> Serializer<'w> {
     /// Serialize a single value.
+    ///
+    /// The encoding follows the choices LibSerialize makes for the same Lua table.
+    /// Map entries are written in iteration order, so a value deserialized
+    /// with `MapBackend::IndexMap` and [NormalizationPolicy::Preserve](crate::NormalizationPolicy::Preserve)
+    /// is serialized back into the same bytes.
     pub fn serialize_one(
         value: &LuaValue,
         approximate_len: Option<usize>,
     ) -> Result<Vec<u8>, SerializationError> {
-        let mut serializer = Self {
-            remaining_depth: 128,
-            result: Vec::with_capacity(approximate_len.unwrap_or(1024)),
+        Self::serialize_to_vec(value, with_approximate_len(approximate_len))
+    }
 
-            string_refs: Map::new(),
+    /// Serialize a single value using `options`.
+    pub fn serialize_one_with(
+        value: &LuaValue,
+        options: SerializeOptions<'_>,
+    ) -> Result<Vec<u8>, SerializationError> {
+        Self::serialize_to_vec(value, options)
+    }
+
+    /// Serialize a single value into `writer` using `options`.
+    ///
+    /// The output is passed to the writer in chunks as it is produced,
+    /// instead of being held in memory as a whole.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{SerializeOptions, Serializer, WriteError};
+    ///
+    /// fn main() -> Result<(), WriteError> {
+    ///     let mut output = Vec::new();
+    ///     let options = SerializeOptions::new();
+    ///     Serializer::serialize_one_into(&"Hello, world!".into(), &mut output, options)?;
+    ///
+    ///     assert_eq!(output, b"\x01\xd2Hello, world!");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_one_into<W: io::Write>(
+        value: &LuaValue,
+        mut writer: W,
+        options: SerializeOptions<'_>,
+    ) -> Result<(), WriteError> {
+        Serializer::serialize(value, options, Some(&mut writer)).map(drop)
+    }
+
+    /// Serialize a single [CompactLuaValue].
+    ///
+    /// A table that occurs several times in `value` is serialized once,
+    /// subsequent occurrences are written as references to it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{CompactLuaValue, Deserializer, LuaValue, Serializer};
+    ///
+    /// let color = CompactLuaValue::from(LuaValue::Array(vec![1.0.into(), 0.5.into()]));
+    /// let value = CompactLuaValue::Array([color.clone(), color].into());
+    ///
+    /// let serialized = Serializer::serialize_compact(&value, None).unwrap();
+    /// // {{1, 0.5}, <reference to the first table>}
+    /// assert_eq!(serialized, b"\x01\x2a\x2a\x03\x50\x030.5\xe8\x01");
+    ///
+    /// let CompactLuaValue::Array(v) = Deserializer::from_slice(&serialized)
+    ///     .into_compact()
+    ///     .deserialize_first()
+    ///     .unwrap()
+    ///     .unwrap()
+    /// else {
+    ///     unreachable!()
+    /// };
+    /// assert!(v[0].ptr_eq(&v[1]));
+    /// ```
+    pub fn serialize_compact(
+        value: &CompactLuaValue,
+        approximate_len: Option<usize>,
+    ) -> Result<Vec<u8>, SerializationError> {
+        Self::serialize_to_vec(value, with_approximate_len(approximate_len))
+    }
+
+    /// Serialize a single [CompactLuaValue] using `options`.
+    pub fn serialize_compact_with(
+        value: &CompactLuaValue,
+        options: SerializeOptions<'_, CompactLuaValue>,
+    ) -> Result<Vec<u8>, SerializationError> {
+        Self::serialize_to_vec(value, options)
+    }
+
+    /// Serialize a single [CompactLuaValue] into `writer` using `options`,
+    /// see [Serializer::serialize_one_into].
+    pub fn serialize_compact_into<W: io::Write>(
+        value: &CompactLuaValue,
+        mut writer: W,
+        options: SerializeOptions<'_, CompactLuaValue>,
+    ) -> Result<(), WriteError> {
+        Serializer::serialize(value, options, Some(&mut writer)).map(drop)
+    }
+
+    fn serialize_to_vec<V: Value>(
+        value: &V,
+        options: SerializeOptions<'_, V>,
+    ) -> Result<Vec<u8>, SerializationError> {
+        Self::serialize(value, options, None).map_err(|e| match e {
+            WriteError::SerializationError(e) => e,
+            WriteError::IoError(_) => unreachable!("there is no writer to fail"),
+        })
+    }
+
+    /// Serializes `value` into `writer` if there is one, or into the returned buffer otherwise.
+    fn serialize<V: Value>(
+        value: &V,
+        options: SerializeOptions<'_, V>,
+        writer: Option<&'w mut dyn io::Write>,
+    ) -> Result<Vec<u8>, WriteError> {
+        let capacity = match writer {
+            Some(_) => FLUSH_THRESHOLD * 2,
+            None => options.approximate_len.unwrap_or(1024),
         };
+        let mut serializer = Self::new(capacity, writer);
+        serializer.stable = options.stable;
+        serializer.error_on_unserializable_type = options.error_on_unserializable_type;
+        serializer.nan_policy = options.nan_policy;
+        serializer.infinity_policy = options.infinity_policy;
+        let mut filter = options.filter.map(|callback| Filter {
+            callback,
+            path: Vec::new(),
+        });
 
         serializer.result.push(FORMAT_VERSION);
-        serializer.serialize_helper(value)?;
+        serializer.serialize_helper(value, &mut filter)?;
+
+        if let Some(writer) = serializer.writer {
+            writer.write_all(&serializer.result)?;
+            serializer.result.clear();
+        }
 
         Ok(serializer.result)
     }
 
-    fn serialize_helper(&mut self, value: &LuaValue) -> Result<(), SerializationError> {
-        match *value {
-            LuaValue::Null => self.result.push(TypeTag::Null.to_u8() << TYPE_TAG_SHIFT),
-            LuaValue::Boolean(b) => {
+    fn new(capacity: usize, writer: Option<&'w mut dyn io::Write>) -> Self {
+        Self {
+            remaining_depth: 128,
+            result: Vec::with_capacity(capacity),
+            writer,
+
+            string_refs: HashMap::new(),
+            table_refs: HashMap::new(),
+
+            float_buffer: String::new(),
+            stable: false,
+            error_on_unserializable_type: true,
+            nan_policy: NonFinitePolicy::Write,
+            infinity_policy: NonFinitePolicy::Write,
+        }
+    }
+
+    fn flush_if_needed(&mut self) -> Result<(), WriteError> {
+        if self.result.len() >= FLUSH_THRESHOLD {
+            if let Some(writer) = self.writer.as_mut() {
+                writer.write_all(&self.result)?;
+                self.result.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn serialize_helper<V: Value>(
+        &mut self,
+        value: &V,
+        filter: &mut Option<Filter<'_, V>>,
+    ) -> Result<(), WriteError> {
+        self.flush_if_needed()?;
+
+        match value.kind() {
+            Kind::Null => self.result.push(TypeTag::Null.to_u8() << TYPE_TAG_SHIFT),
+            Kind::Boolean(b) => {
                 if b {
                     self.result.push(TypeTag::True.to_u8() << TYPE_TAG_SHIFT);
                 } else {
                     self.result.push(TypeTag::False.to_u8() << TYPE_TAG_SHIFT);
                 }
             }
-            LuaValue::String(ref s) => self.serialize_string(s)?,
-            LuaValue::Number(n) => self.serialize_number(n),
-            LuaValue::Array(ref v) => self.serialize_slice(v)?,
-            LuaValue::Map(ref m) => self.serialize_map(m)?,
+            Kind::String(s) => self.serialize_string(s)?,
+            Kind::Number(n) => match self.apply_non_finite_policy(n)? {
+                Some(n) => self.serialize_number(n),
+                None => self.result.push(TypeTag::Null.to_u8() << TYPE_TAG_SHIFT),
+            },
+            Kind::Array(v) => {
+                if !self.serialize_table_ref(value) {
+                    if filter.is_some() {
+                        // Elements are passed to the filter along with their indices.
+                        let keys: Vec<V> = (1..=v.len()).map(|i| V::number(i as f64)).collect();
+                        self.serialize_entries(keys.iter().zip(v), filter)?;
+                    } else {
+                        self.serialize_slice(v, filter)?;
+                    }
+                    self.add_table_ref(value);
+                }
+            }
+            Kind::Map(m) => {
+                if !self.serialize_table_ref(value) {
+                    self.serialize_entries(V::entries(m), filter)?;
+                    self.add_table_ref(value);
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Writes a reference if the table has already been serialized.
+    fn serialize_table_ref<V: Value>(&mut self, value: &V) -> bool {
+        let Some(index) = value
+            .table_id()
+            .and_then(|id| self.table_refs.get(&id).copied())
+        else {
+            return false;
+        };
+
+        let index = index as u64;
+        match required_bytes(index) {
+            1 => {
+                self.result.push(TypeTag::MapRef8.to_u8() << TYPE_TAG_SHIFT);
+                self.serialize_int(index, 1);
+            }
+            2 => {
+                self.result
+                    .push(TypeTag::MapRef16.to_u8() << TYPE_TAG_SHIFT);
+                self.serialize_int(index, 2);
+            }
+            _ => {
+                self.result
+                    .push(TypeTag::MapRef24.to_u8() << TYPE_TAG_SHIFT);
+                self.serialize_int(index, 3);
+            }
+        }
+
+        true
+    }
+
+    fn add_table_ref<V: Value>(&mut self, value: &V) {
+        // Like LibSerialize, tables are numbered in the order they are completed.
+        if let Some(id) = value.table_id() {
+            let index = self.table_refs.len() + 1;
+            // Tables beyond the range of `MapRef24` are written in full every time.
+            if index < 1 << 24 {
+                self.table_refs.insert(id, index);
+            }
+        }
+    }
+
+    /// Returns the number to write in place of `value`, or `None` if `nil` should be written.
+    fn apply_non_finite_policy(&self, value: f64) -> Result<Option<f64>, SerializationError> {
+        let policy = if value.is_nan() {
+            self.nan_policy
+        } else if value.is_infinite() {
+            self.infinity_policy
+        } else {
+            return Ok(Some(value));
+        };
+
+        match policy {
+            NonFinitePolicy::Error if value.is_nan() => Err(SerializationError::NanEncountered),
+            NonFinitePolicy::Error => Err(SerializationError::InfinityEncountered),
+            NonFinitePolicy::ReplaceWithNil => Ok(None),
+            NonFinitePolicy::ReplaceWithZero => Ok(Some(0.0)),
+            NonFinitePolicy::Write | NonFinitePolicy::PreserveBits => Ok(Some(value)),
+        }
+    }
+
+    /// Whether `value` is written as `nil`.
+    fn is_nil<V: Value>(&self, value: &V) -> bool {
+        match value.kind() {
+            Kind::Null => true,
+            Kind::Number(n) if n.is_nan() => self.nan_policy == NonFinitePolicy::ReplaceWithNil,
+            Kind::Number(n) if n.is_infinite() => {
+                self.infinity_policy == NonFinitePolicy::ReplaceWithNil
+            }
+            _ => false,
+        }
+    }
+
     #[allow(clippy::manual_range_contains)]
     fn serialize_number(&mut self, value: f64) {
         const MAX_7_BIT: f64 = (2i64.pow(56) - 1) as f64;
 
-        if value.is_nan() {
+        if value.is_nan() && self.nan_policy != NonFinitePolicy::PreserveBits {
             // Serialize any NaN as a positive qNaN:
             self.result.push(TypeTag::Float.to_u8() << TYPE_TAG_SHIFT);
             self.result
                 .extend_from_slice(&[0x7f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
         } else if value.fract() != 0.0 || (value < -MAX_7_BIT || value > MAX_7_BIT) {
-            self.result.push(TypeTag::Float.to_u8() << TYPE_TAG_SHIFT);
-            self.result.extend_from_slice(&value.to_be_bytes());
+            if !self.serialize_float_str(value) {
+                self.result.push(TypeTag::Float.to_u8() << TYPE_TAG_SHIFT);
+                self.result.extend_from_slice(&value.to_be_bytes());
+            }
         } else {
             // SAFETY:
-            // 1) NaNs are handled explicitly;
-            // 2) `f64::fract()` returns NaN for infinite values, NaN != 0.0;
-            // 3) `value` is within i64::MIN..=i64::MAX range.
+            // 1) `f64::fract()` returns NaN for NaNs and infinite values, NaN != 0.0;
+            // 2) `value` is within i64::MIN..=i64::MAX range.
             let value = unsafe { value.to_int_unchecked::<i64>() };
 
             if value > -4096 && value < 4096 {
@@ -149,12 +427,37 @@ fn serialize_number(&mut self, value: f64) {
         }
     }
 
+    /// Writes `value` as a string if that is shorter than writing it as a float,
+    /// i.e. if Lua's `tostring` produces less than 7 characters that convert back to `value`.
+    fn serialize_float_str(&mut self, value: f64) -> bool {
+        if !value.is_finite() {
+            return false;
+        }
+
+        let abs = value.abs();
+        format_g14(abs, &mut self.float_buffer);
+        if self.float_buffer.len() >= 7 || self.float_buffer.parse::<f64>() != Ok(abs) {
+            return false;
+        }
+
+        let tag = if value < 0.0 {
+            TypeTag::FloatStrNeg
+        } else {
+            TypeTag::FloatStrPos
+        };
+        self.result.push(tag.to_u8() << TYPE_TAG_SHIFT);
+        self.result.push(self.float_buffer.len() as u8);
+        self.result.extend_from_slice(self.float_buffer.as_bytes());
+
+        true
+    }
+
     fn serialize_int(&mut self, value: u64, len: usize) {
         let bytes = value.to_be_bytes();
         self.result.extend_from_slice(&bytes[bytes.len() - len..]);
     }
 
-    fn serialize_string(&mut self, value: &str) -> Result<(), SerializationError> {
+    fn serialize_string(&mut self, value: &[u8]) -> Result<(), WriteError> {
         match self.string_refs.get(value) {
             Some(index) => {
                 let index = *index as u64;
@@ -174,7 +477,7 @@ fn serialize_string(&mut self, value: &str) -> Result<(), SerializationError> {
                             .push(TypeTag::StrRef24.to_u8() << TYPE_TAG_SHIFT);
                         self.serialize_int(index, 3);
                     }
-                    _ => return Err(SerializationError::TooManyUniqueStrings),
+                    _ => return Err(SerializationError::TooManyUniqueStrings.into()),
                 }
             }
             None => {
@@ -202,7 +505,7 @@ fn serialize_string(&mut self, value: &str) -> Result<(), SerializationError> {
                             self.result.push(TypeTag::Str24.to_u8() << TYPE_TAG_SHIFT);
                             self.serialize_int(len, 3);
                         }
-                        _ => return Err(SerializationError::StringIsTooLarge),
+                        _ => return Err(SerializationError::StringIsTooLarge.into()),
                     }
                 }
 
@@ -211,83 +514,258 @@ fn serialize_string(&mut self, value: &str) -> Result<(), SerializationError> {
                         .insert(value.into(), self.string_refs.len() + 1);
                 }
 
-                self.result.extend_from_slice(value.as_bytes());
+                self.result.extend_from_slice(value);
             }
         }
 
         Ok(())
     }
 
-    fn serialize_map(&mut self, map: &Map<LuaMapKey, LuaValue>) -> Result<(), SerializationError> {
-        let len = map.len();
+    /// Writes the header of a table as an array if there is no map part,
+    /// as a map if there is no array part, or as a mixed table otherwise.
+    fn serialize_table_start(
+        &mut self,
+        array_len: usize,
+        map_len: usize,
+    ) -> Result<(), WriteError> {
+        if map_len == 0 {
+            self.serialize_table_header(
+                EmbeddedTypeTag::Array,
+                [TypeTag::Array8, TypeTag::Array16, TypeTag::Array24],
+                array_len,
+                SerializationError::ArrayIsTooLarge,
+            )
+        } else if array_len == 0 {
+            self.serialize_table_header(
+                EmbeddedTypeTag::Map,
+                [TypeTag::Map8, TypeTag::Map16, TypeTag::Map24],
+                map_len,
+                SerializationError::MapIsTooLarge,
+            )
+        } else {
+            self.serialize_mixed_header(array_len, map_len)
+        }
+    }
+
+    fn serialize_table_header(
+        &mut self,
+        embedded_tag: EmbeddedTypeTag,
+        tags: [TypeTag; 3],
+        len: usize,
+        error: SerializationError,
+    ) -> Result<(), WriteError> {
         if len < 16 {
             self.result.push(
-                (EmbeddedTypeTag::Map.to_u8() << EMBEDDED_TYPE_TAG_SHIFT)
+                (embedded_tag.to_u8() << EMBEDDED_TYPE_TAG_SHIFT)
                     | ((len as u8) << EMBEDDED_LEN_SHIFT)
                     | 2,
             );
         } else {
             let len = len as u64;
-            match required_bytes(len) {
-                1 => {
-                    self.result.push(TypeTag::Map8.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 1);
-                }
-                2 => {
-                    self.result.push(TypeTag::Map16.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 2);
-                }
-                3 => {
-                    self.result.push(TypeTag::Map24.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 3);
-                }
-                _ => return Err(SerializationError::MapIsTooLarge),
+            let required = required_bytes(len);
+            if required > 3 {
+                return Err(error.into());
             }
-        }
 
-        for (key, value) in map {
-            check_recursion!(self, SerializationError, {
-                self.serialize_helper(key.as_value())?;
-                self.serialize_helper(value)?;
-            });
+            self.result
+                .push(tags[required as usize - 1].to_u8() << TYPE_TAG_SHIFT);
+            self.serialize_int(len, required as usize);
         }
 
         Ok(())
     }
 
-    fn serialize_slice(&mut self, slice: &[LuaValue]) -> Result<(), SerializationError> {
-        let len = slice.len();
-        if len < 16 {
+    fn serialize_mixed_header(
+        &mut self,
+        array_len: usize,
+        map_len: usize,
+    ) -> Result<(), WriteError> {
+        if array_len <= 4 && map_len <= 4 {
+            // The 4-bit count contains two 2-bit counts that are one less than the true count.
+            let counts = (((map_len - 1) << 2) | (array_len - 1)) as u8;
             self.result.push(
-                (EmbeddedTypeTag::Array.to_u8() << EMBEDDED_TYPE_TAG_SHIFT)
-                    | ((len as u8) << EMBEDDED_LEN_SHIFT)
+                (EmbeddedTypeTag::Mixed.to_u8() << EMBEDDED_TYPE_TAG_SHIFT)
+                    | (counts << EMBEDDED_LEN_SHIFT)
                     | 2,
             );
         } else {
-            let len = len as u64;
-            match required_bytes(len) {
-                1 => {
-                    self.result.push(TypeTag::Array8.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 1);
+            let required = required_bytes(array_len.max(map_len) as u64);
+            let tag = match required {
+                1 => TypeTag::Mixed8,
+                2 => TypeTag::Mixed16,
+                3 => TypeTag::Mixed24,
+                _ => return Err(SerializationError::MapIsTooLarge.into()),
+            };
+
+            self.result.push(tag.to_u8() << TYPE_TAG_SHIFT);
+            self.serialize_int(array_len as u64, required as usize);
+            self.serialize_int(map_len as u64, required as usize);
+        }
+
+        Ok(())
+    }
+
+    /// Serializes table entries the way LibSerialize serializes a Lua table.
+    ///
+    /// The entries with keys `1`, `2`, `3`... up to the first missing key form
+    /// the array part of the table, like the loop LibSerialize runs over the table.
+    /// This is what Lua's `#` operator returns for tables without holes; with holes,
+    /// `#` may return any border, but LibSerialize still stops at the first one.
+    /// The other entries are written in iteration order unless sorting is requested.
+    fn serialize_entries<'v, V: Value>(
+        &mut self,
+        entries: impl Iterator<Item = (&'v V, &'v V)>,
+        filter: &mut Option<Filter<'_, V>>,
+    ) -> Result<(), WriteError> {
+        let mut map_part = Vec::new();
+        for (key, value) in entries {
+            // Entries with `nil` values do not exist in Lua.
+            if self.is_nil(value) {
+                continue;
+            }
+            if !is_valid_key(key) {
+                if self.error_on_unserializable_type {
+                    return Err(SerializationError::UnserializableKey.into());
                 }
-                2 => {
-                    self.result.push(TypeTag::Array16.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 2);
+                continue;
+            }
+            // Keys replaced with `nil` take their entries with them.
+            if self.is_nil(key) {
+                continue;
+            }
+            if let Some(filter) = filter {
+                if !(filter.callback)(&filter.path, key, value) {
+                    continue;
                 }
-                3 => {
-                    self.result.push(TypeTag::Array24.to_u8() << TYPE_TAG_SHIFT);
-                    self.serialize_int(len, 3);
+            }
+
+            map_part.push((key, value));
+        }
+
+        let len = map_part.len();
+        let mut array_part: Vec<Option<(&V, &V)>> = Vec::new();
+        for &(key, value) in &map_part {
+            if let Some(index) = array_index(key, len) {
+                if array_part.len() < index {
+                    array_part.resize(index, None);
                 }
-                _ => return Err(SerializationError::ArrayIsTooLarge),
+                array_part[index - 1] = Some((key, value));
             }
         }
+        let array_len = array_part
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(array_part.len());
+        array_part.truncate(array_len);
+
+        map_part.retain(|(key, _)| array_index(*key, array_len).is_none());
+        if self.stable {
+            map_part.sort_by(|(a, _), (b, _)| compare_keys(*a, *b));
+        }
+        self.serialize_table_start(array_len, map_part.len())?;
+
+        for (key, value) in array_part.into_iter().flatten() {
+            check_recursion!(self, SerializationError, {
+                self.serialize_entry_value(key, value, filter)?;
+            });
+        }
+
+        for (key, value) in map_part {
+            check_recursion!(self, SerializationError, {
+                self.serialize_helper(key, filter)?;
+                self.serialize_entry_value(key, value, filter)?;
+            });
+        }
+
+        Ok(())
+    }
+
+    fn serialize_entry_value<V: Value>(
+        &mut self,
+        key: &V,
+        value: &V,
+        filter: &mut Option<Filter<'_, V>>,
+    ) -> Result<(), WriteError> {
+        if let Some(filter) = filter {
+            filter.path.push(key.clone());
+        }
+        self.serialize_helper(value, filter)?;
+        if let Some(filter) = filter {
+            filter.path.pop();
+        }
+
+        Ok(())
+    }
+
+    fn serialize_slice<V: Value>(
+        &mut self,
+        slice: &[V],
+        filter: &mut Option<Filter<'_, V>>,
+    ) -> Result<(), WriteError> {
+        // Holes are serialized as `nil`s, trailing ones are not part of the array.
+        let slice = match slice.iter().rposition(|v| !self.is_nil(v)) {
+            Some(last) => &slice[..=last],
+            None => &[],
+        };
+
+        self.serialize_table_header(
+            EmbeddedTypeTag::Array,
+            [TypeTag::Array8, TypeTag::Array16, TypeTag::Array24],
+            slice.len(),
+            SerializationError::ArrayIsTooLarge,
+        )?;
 
         for el in slice {
             check_recursion!(self, SerializationError, {
-                self.serialize_helper(el)?;
+                self.serialize_helper(el, filter)?;
             });
         }
 
         Ok(())
     }
 }
+
+fn with_approximate_len<'f, V>(approximate_len: Option<usize>) -> SerializeOptions<'f, V> {
+    let options = SerializeOptions::new();
+    match approximate_len {
+        Some(len) => options.with_approximate_len(len),
+        None => options,
+    }
+}
+
+fn is_valid_key<V: Value>(key: &V) -> bool {
+    match key.kind() {
+        Kind::Null => false,
+        Kind::Number(n) => !n.is_nan(),
+        _ => true,
+    }
+}
+
+/// Orders keys by type, then by value. Tables are considered equal.
+fn compare_keys<V: Value>(a: &V, b: &V) -> Ordering {
+    fn rank<V: Value>(kind: &Kind<'_, V>) -> u8 {
+        match kind {
+            Kind::Null => 0,
+            Kind::Boolean(_) => 1,
+            Kind::Number(_) => 2,
+            Kind::String(_) => 3,
+            Kind::Map(_) | Kind::Array(_) => 4,
+        }
+    }
+
+    let (a, b) = (a.kind(), b.kind());
+    match (&a, &b) {
+        (Kind::Boolean(a), Kind::Boolean(b)) => a.cmp(b),
+        (Kind::Number(a), Kind::Number(b)) => a.total_cmp(b),
+        (Kind::String(a), Kind::String(b)) => a.cmp(b),
+        _ => rank(&a).cmp(&rank(&b)),
+    }
+}
+
+/// Returns the 1-based index of `key` if it can be a part of an array of `len` elements.
+fn array_index<V: Value>(key: &V, len: usize) -> Option<usize> {
+    match key.kind() {
+        Kind::Number(n) if n >= 1.0 && n <= len as f64 && n.fract() == 0.0 => Some(n as usize),
+        _ => None,
+    }
+}
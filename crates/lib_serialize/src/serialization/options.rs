@@ -0,0 +1,145 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use core::fmt;
+use weakauras_codec_lua_value::{LuaValue, NonFinitePolicy};
+
+pub(super) type FilterFn<'f, V> = Box<dyn FnMut(&[V], &V, &V) -> bool + 'f>;
+
+/// Options for [Serializer](super::Serializer), mirroring the options of LibSerialize's `SerializeEx`.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lib_serialize::{LuaValue, SerializeOptions, Serializer};
+/// use weakauras_codec_lua_value::LuaMapKey;
+///
+/// let value = LuaValue::Map(
+///     [("id", "Aura"), ("information", "Hidden")]
+///         .into_iter()
+///         .map(|(k, v)| (LuaMapKey::try_from(LuaValue::from(k)).unwrap(), v.into()))
+///         .collect(),
+/// );
+///
+/// // Skip `information` at the top level.
+/// let options = SerializeOptions::new().with_filter(|path, key, _| {
+///     !(path.is_empty() && matches!(key, LuaValue::String(s) if s == "information"))
+/// });
+///
+/// assert_eq!(
+///     Serializer::serialize_one_with(&value, options).unwrap(),
+///     b"\x01\x16\x22id\x42Aura"
+/// );
+/// ```
+pub struct SerializeOptions<'f, V = LuaValue> {
+    pub(super) approximate_len: Option<usize>,
+    pub(super) stable: bool,
+    pub(super) error_on_unserializable_type: bool,
+    pub(super) nan_policy: NonFinitePolicy,
+    pub(super) infinity_policy: NonFinitePolicy,
+    pub(super) filter: Option<FilterFn<'f, V>>,
+}
+
+impl<'f, V> SerializeOptions<'f, V> {
+    /// Create options with default values.
+    pub fn new() -> Self {
+        Self {
+            approximate_len: None,
+            stable: false,
+            error_on_unserializable_type: true,
+            nan_policy: NonFinitePolicy::Write,
+            infinity_policy: NonFinitePolicy::Write,
+            filter: None,
+        }
+    }
+
+    /// Set the expected length of the output, used to preallocate the buffer.
+    pub fn with_approximate_len(mut self, value: usize) -> Self {
+        self.approximate_len = Some(value);
+        self
+    }
+
+    /// Sort map entries by their keys to make the output deterministic.
+    ///
+    /// Keys are ordered by type first (booleans, numbers, strings, tables),
+    /// then by value. Tables used as keys keep their relative order.
+    /// The array part of a table is always written in order. Disabled by default.
+    pub fn with_stable(mut self, value: bool) -> Self {
+        self.stable = value;
+        self
+    }
+
+    /// Fail with [SerializationError::UnserializableKey](crate::SerializationError::UnserializableKey)
+    /// upon encountering a map entry whose key is `nil` or NaN, instead of skipping it.
+    ///
+    /// Such keys can only occur in a [CompactLuaValue](crate::CompactLuaValue). Enabled by default.
+    pub fn with_error_on_unserializable_type(mut self, value: bool) -> Self {
+        self.error_on_unserializable_type = value;
+        self
+    }
+
+    /// Set the policy for serializing NaNs. The default is [NonFinitePolicy::Write].
+    ///
+    /// NaN keys are not affected, see [SerializeOptions::with_error_on_unserializable_type].
+    pub fn with_nan_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
+
+    /// Set the policy for serializing infinities. The default is [NonFinitePolicy::Write].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lib_serialize::{
+    ///     LuaValue, NonFinitePolicy, SerializationError, SerializeOptions, Serializer,
+    /// };
+    ///
+    /// let value = LuaValue::Array(vec![1.0.into(), f64::INFINITY.into()]);
+    ///
+    /// let options = SerializeOptions::new().with_infinity_policy(NonFinitePolicy::Error);
+    /// assert_eq!(
+    ///     Serializer::serialize_one_with(&value, options),
+    ///     Err(SerializationError::InfinityEncountered)
+    /// );
+    ///
+    /// // {1}
+    /// let options = SerializeOptions::new().with_infinity_policy(NonFinitePolicy::ReplaceWithNil);
+    /// assert_eq!(Serializer::serialize_one_with(&value, options).unwrap(), b"\x01\x1a\x03");
+    /// ```
+    pub fn with_infinity_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.infinity_policy = policy;
+        self
+    }
+
+    /// Serialize only the table entries for which `filter` returns `true`.
+    ///
+    /// The filter receives the path of keys leading to the table, the key and the value
+    /// of an entry. Array elements are passed with their 1-based indices as keys.
+    pub fn with_filter(mut self, filter: impl FnMut(&[V], &V, &V) -> bool + 'f) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+}
+
+impl<V> Default for SerializeOptions<'_, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> fmt::Debug for SerializeOptions<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerializeOptions")
+            .field("approximate_len", &self.approximate_len)
+            .field("stable", &self.stable)
+            .field(
+                "error_on_unserializable_type",
+                &self.error_on_unserializable_type,
+            )
+            .field("nan_policy", &self.nan_policy)
+            .field("infinity_policy", &self.infinity_policy)
+            .field("filter", &self.filter.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use std::sync::Arc;
+use weakauras_codec_lua_value::{CompactLuaValue, LuaMap, LuaValue};
+
+pub(crate) enum Kind<'a, V: Value> {
+    Map(&'a V::Map),
+    Array(&'a [V]),
+    String(&'a [u8]),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+/// A value that [Serializer](super::Serializer) can serialize.
+pub(crate) trait Value: Clone + 'static {
+    type Map: ?Sized;
+
+    fn number(value: f64) -> Self;
+    fn kind(&self) -> Kind<'_, Self>;
+    fn entries(map: &Self::Map) -> impl Iterator<Item = (&Self, &Self)>;
+    /// Identifies a table that can occur more than once in a value.
+    fn table_id(&self) -> Option<usize>;
+}
+
+impl Value for LuaValue {
+    type Map = LuaMap;
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        LuaValue::Number(value)
+    }
+
+    #[inline(always)]
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            LuaValue::Map(m) => Kind::Map(m),
+            LuaValue::Array(v) => Kind::Array(v),
+            LuaValue::String(s) => Kind::String(s),
+            LuaValue::Number(n) => Kind::Number(*n),
+            LuaValue::Boolean(b) => Kind::Boolean(*b),
+            LuaValue::Null => Kind::Null,
+        }
+    }
+
+    #[inline(always)]
+    fn entries(map: &LuaMap) -> impl Iterator<Item = (&Self, &Self)> {
+        map.iter().map(|(k, v)| (k.as_value(), v))
+    }
+
+    #[inline(always)]
+    fn table_id(&self) -> Option<usize> {
+        // Every table is owned by a single value.
+        None
+    }
+}
+
+impl Value for CompactLuaValue {
+    type Map = [(CompactLuaValue, CompactLuaValue)];
+
+    #[inline(always)]
+    fn number(value: f64) -> Self {
+        CompactLuaValue::Number(value)
+    }
+
+    #[inline(always)]
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            CompactLuaValue::Map(m) => Kind::Map(&m[..]),
+            CompactLuaValue::Array(v) => Kind::Array(&v[..]),
+            CompactLuaValue::String(s) => Kind::String(s),
+            CompactLuaValue::Number(n) => Kind::Number(*n),
+            CompactLuaValue::Boolean(b) => Kind::Boolean(*b),
+            CompactLuaValue::Null => Kind::Null,
+        }
+    }
+
+    #[inline(always)]
+    fn entries(map: &[(CompactLuaValue, CompactLuaValue)]) -> impl Iterator<Item = (&Self, &Self)> {
+        map.iter().map(|(k, v)| (k, v))
+    }
+
+    #[inline(always)]
+    fn table_id(&self) -> Option<usize> {
+        match self {
+            CompactLuaValue::Map(m) => Some(Arc::as_ptr(m).cast::<()>().addr()),
+            CompactLuaValue::Array(v) => Some(Arc::as_ptr(v).cast::<()>().addr()),
+            _ => None,
+        }
+    }
+}
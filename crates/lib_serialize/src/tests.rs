@@ -0,0 +1,1368 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    CompactLuaValue, DeserializationError, DeserializationErrorKind, Deserializer, EmitError,
+    Emitter, FORMAT_VERSION, IncrementalDeserializer, LuaValue, LuaValueRef, NonFinitePolicy,
+    NormalizationPolicy, Parser, PathSegment, Selector, SerializationError, SerializeOptions,
+    Serializer, TypeTag, WriteError,
+    deserialization::{Event, Step},
+};
+use std::{borrow::Cow, io, sync::Arc};
+use weakauras_codec_lua_value::{LuaMap, LuaMapKey, ResourceLimits, error::ResourceLimitExceeded};
+
+fn roundtrip(value: &LuaValue) -> LuaValue {
+    let serialized = Serializer::serialize_one(value, None).unwrap();
+    Deserializer::from_slice(&serialized)
+        .deserialize_first()
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn holes_survive_decode_and_encode() {
+    // {1, nil, 3}
+    let input = b"\x01\x3a\x03\x00\x07";
+
+    let value = Deserializer::from_slice(input)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let LuaValue::Array(ref v) = value else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        *v,
+        [LuaValue::from(1.0), LuaValue::Null, LuaValue::from(3.0)]
+    );
+
+    assert_eq!(Serializer::serialize_one(&value, None).unwrap(), input);
+}
+
+#[test]
+fn trailing_holes_are_not_serialized() {
+    let value = LuaValue::Array(vec![1.0.into(), LuaValue::Null, LuaValue::Null]);
+    assert_eq!(
+        Serializer::serialize_one(&value, None).unwrap(),
+        b"\x01\x1a\x03"
+    );
+}
+
+#[test]
+fn null_map_values_are_not_serialized() {
+    let value = LuaValue::Map(LuaMap::from_iter([
+        (
+            LuaMapKey::try_from(LuaValue::from("a")).unwrap(),
+            1.0.into(),
+        ),
+        (
+            LuaMapKey::try_from(LuaValue::from("b")).unwrap(),
+            LuaValue::Null,
+        ),
+    ]));
+
+    let LuaValue::Map(m) = roundtrip(&value) else {
+        panic!("expected a map");
+    };
+    assert_eq!(m.len(), 1);
+}
+
+#[test]
+fn byte_strings_survive_roundtrip() {
+    let bytes: &[u8] = b"Interface\\Icons\\\xff\xfe";
+    let value = LuaValue::Array(vec![bytes.into(), bytes.into()]);
+
+    let LuaValue::Array(v) = roundtrip(&value) else {
+        panic!("expected an array");
+    };
+    assert!(
+        v.iter()
+            .all(|v| matches!(v, LuaValue::String(s) if s.as_bytes() == bytes))
+    );
+}
+
+#[test]
+fn compact_values_match_regular_ones() {
+    let long = "Interface\\AddOns\\WeakAuras\\Media\\Textures\\Square_White";
+    let mut m = LuaMap::new();
+    m.insert(
+        LuaMapKey::try_from(LuaValue::from("texture")).unwrap(),
+        long.into(),
+    );
+    m.insert(
+        LuaMapKey::try_from(LuaValue::from("subRegions")).unwrap(),
+        LuaValue::Array(vec![long.into(), LuaValue::Null, (-1.5).into()]),
+    );
+    let serialized = Serializer::serialize_one(&LuaValue::Map(m), None).unwrap();
+
+    let value = Deserializer::from_slice(&serialized)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let compact = Deserializer::from_slice(&serialized)
+        .into_compact()
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+
+    let CompactLuaValue::Map(ref entries) = compact else {
+        panic!("expected a map");
+    };
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        Serializer::serialize_one(&LuaValue::from(compact), None).unwrap(),
+        Serializer::serialize_one(&value, None).unwrap()
+    );
+}
+
+#[test]
+fn compact_maps_reject_invalid_keys() {
+    // {[nil] = 1}
+    let input = b"\x01\x16\x00\x03";
+    assert_eq!(
+        Deserializer::from_slice(input)
+            .into_compact()
+            .deserialize_first()
+            .unwrap_err()
+            .kind(),
+        DeserializationErrorKind::InvalidMapKeyType
+    );
+}
+
+#[test]
+fn shared_tables_survive_roundtrip() {
+    let anchor = CompactLuaValue::Map(Arc::from([
+        ("point".into(), "CENTER".into()),
+        ("x".into(), 0.0.into()),
+    ]));
+    let color = CompactLuaValue::from(LuaValue::Array(vec![1.0.into(), 1.0.into()]));
+    let nested = CompactLuaValue::Array(Arc::from([anchor.clone(), color.clone()]));
+    let value = CompactLuaValue::Map(Arc::from([
+        ("anchor".into(), anchor),
+        ("nested".into(), nested.clone()),
+        ("color".into(), color),
+        ("again".into(), nested),
+    ]));
+
+    let serialized = Serializer::serialize_compact(&value, None).unwrap();
+    let CompactLuaValue::Map(entries) = Deserializer::from_slice(&serialized)
+        .into_compact()
+        .deserialize_first()
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("expected a map");
+    };
+
+    let (anchor, nested, color, again) =
+        (&entries[0].1, &entries[1].1, &entries[2].1, &entries[3].1);
+    let CompactLuaValue::Array(ref children) = *nested else {
+        panic!("expected an array");
+    };
+    assert!(children[0].ptr_eq(anchor));
+    assert!(children[1].ptr_eq(color));
+    assert!(again.ptr_eq(nested));
+    assert!(!anchor.ptr_eq(color));
+
+    // Regular values own their tables, so every occurrence is written in full.
+    let expanded = Serializer::serialize_one(&LuaValue::from(&value), None).unwrap();
+    assert!(expanded.len() > serialized.len());
+}
+
+/// `{{1}, {t[1], t[1]}, {t[2], t[2]}, ...}`, where `t[i]` is a reference
+/// to the `i`-th table. Every table is twice as large as the previous one.
+fn reference_bomb(tables: u8) -> Vec<u8> {
+    let mut input = vec![1, TypeTag::Array8.to_u8() << 3, tables, 0x1a, 0x03];
+    for i in 1..tables {
+        let map_ref = TypeTag::MapRef8.to_u8() << 3;
+        input.extend_from_slice(&[0x2a, map_ref, i, map_ref, i]);
+    }
+    input
+}
+
+#[test]
+fn reference_bombs_are_stopped() {
+    let input = reference_bomb(40);
+
+    assert_eq!(
+        Deserializer::from_slice(&input)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::ClonedNodes
+        ))
+    );
+
+    // Compact values share tables instead of copying them.
+    assert!(
+        Deserializer::from_slice(&input)
+            .into_compact()
+            .deserialize_first()
+            .is_ok()
+    );
+}
+
+#[test]
+fn only_resolved_references_count_towards_the_limit() {
+    // {{...{1}...}, t[10]}, 10 tables deep, where `t[10]` is a reference
+    // to the outermost of the nested tables, which consists of 11 values.
+    let mut value = LuaValue::from(1.0);
+    for _ in 0..10 {
+        value = LuaValue::Array(vec![value]);
+    }
+    let nested = Serializer::serialize_one(&value, None).unwrap();
+    let mut input = vec![1, 0x2a];
+    input.extend_from_slice(&nested[1..]);
+    input.extend_from_slice(&[TypeTag::MapRef8.to_u8() << 3, 10]);
+
+    let deserialize = |input: &[u8], limits| {
+        let eager = Deserializer::from_slice(input)
+            .with_limits(limits)
+            .deserialize_first()
+            .map_err(|e| e.kind());
+        let mut incremental = IncrementalDeserializer::new().with_limits(limits);
+        incremental.feed(input);
+        incremental.finish();
+        let incremental = incremental.next_value().map_err(|e| e.kind());
+        assert_eq!(eager.is_ok(), incremental.is_ok());
+        eager.map(|_| ())
+    };
+
+    // Tables that are never referenced are not copied.
+    let limits = ResourceLimits::default().with_max_cloned_nodes(0);
+    assert_eq!(deserialize(&nested, limits), Ok(()));
+
+    let limits = limits.with_max_cloned_nodes(11);
+    assert_eq!(deserialize(&input, limits), Ok(()));
+    assert_eq!(
+        deserialize(&input, limits.with_max_cloned_nodes(10)),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::ClonedNodes
+        ))
+    );
+}
+
+#[test]
+fn repeated_strings_count_towards_the_limit() {
+    let mut input = vec![1, TypeTag::Array8.to_u8() << 3, 100];
+    input.extend_from_slice(&[TypeTag::Str8.to_u8() << 3, 64]);
+    input.extend_from_slice(&[b'a'; 64]);
+    for _ in 1..100 {
+        input.extend_from_slice(&[TypeTag::StrRef8.to_u8() << 3, 1]);
+    }
+
+    let limits = ResourceLimits::default().with_max_string_bytes(64 * 99);
+    assert_eq!(
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::StringBytes
+        ))
+    );
+
+    let limits = limits.with_max_string_bytes(64 * 100);
+    assert!(
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_first()
+            .is_ok()
+    );
+}
+
+#[test]
+fn declared_lengths_are_checked_before_reading() {
+    // A map that claims to have 2^24 - 1 entries.
+    let input = [1, TypeTag::Map24.to_u8() << 3, 0xff, 0xff, 0xff];
+    assert_eq!(
+        Deserializer::from_slice(&input)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::CollectionLength
+        ))
+    );
+}
+
+#[test]
+fn tables_and_their_contents_are_nodes() {
+    // {1, 2}
+    let input = b"\x01\x2a\x03\x05";
+
+    let limits = ResourceLimits::default().with_max_nodes(3);
+    assert!(
+        Deserializer::from_slice(input)
+            .with_limits(limits)
+            .deserialize_first()
+            .is_ok()
+    );
+
+    let limits = limits.with_max_nodes(2);
+    assert_eq!(
+        Deserializer::from_slice(input)
+            .with_limits(limits)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::Nodes
+        ))
+    );
+}
+
+#[cfg(feature = "lua-value-indexmap")]
+#[test]
+fn maps_use_the_requested_backend() {
+    use crate::{MapBackend, NormalizationPolicy};
+
+    let mut m = LuaMap::new();
+    m.insert(
+        LuaMapKey::try_from(LuaValue::from("b")).unwrap(),
+        true.into(),
+    );
+    m.insert(
+        LuaMapKey::try_from(LuaValue::from("a")).unwrap(),
+        LuaValue::Array(vec![1.0.into()]),
+    );
+    let serialized = Serializer::serialize_one(&LuaValue::Map(m), None).unwrap();
+
+    let value = Deserializer::from_slice(&serialized)
+        .with_map_backend(MapBackend::IndexMap)
+        .with_normalization(NormalizationPolicy::PreferMaps)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+
+    let LuaValue::Map(m) = value else {
+        panic!("expected a map");
+    };
+    assert_eq!(m.backend(), MapBackend::IndexMap);
+    assert!(m.values().all(|v| match v {
+        LuaValue::Map(m) => m.backend() == MapBackend::IndexMap,
+        _ => true,
+    }));
+}
+
+#[test]
+fn floats_are_written_as_strings_when_shorter() {
+    let serialize = |n: f64| Serializer::serialize_one(&n.into(), None).unwrap();
+
+    assert_eq!(serialize(0.5), b"\x01\x50\x030.5");
+    assert_eq!(serialize(-1.25), b"\x01\x58\x041.25");
+    assert_eq!(serialize(0.00001), b"\x01\x50\x051e-05");
+    // "0.33333333333333" is longer than the float itself.
+    assert_eq!(
+        serialize(1.0 / 3.0),
+        b"\x01\x48\x3f\xd5\x55\x55\x55\x55\x55\x55"
+    );
+    // "0.1234567" is longer than 6 characters.
+    assert_eq!(serialize(0.1234567)[1], TypeTag::Float.to_u8() << 3);
+}
+
+#[test]
+fn non_finite_policies_are_applied() {
+    let serialize = |value: &LuaValue, policy| {
+        let options = SerializeOptions::new()
+            .with_nan_policy(policy)
+            .with_infinity_policy(policy);
+        Serializer::serialize_one_with(value, options)
+    };
+    let expected = |value: LuaValue| Serializer::serialize_one(&value, None).unwrap();
+
+    let nan = f64::from_bits(0xfff8_0000_0000_0001);
+    let array = LuaValue::Array(vec![1.0.into(), nan.into(), f64::NEG_INFINITY.into()]);
+    let map = LuaValue::Map(
+        [(f64::INFINITY, "a"), (2.0, "b")]
+            .into_iter()
+            .map(|(k, v)| (LuaMapKey::try_from(LuaValue::from(k)).unwrap(), v.into()))
+            .collect(),
+    );
+
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::Error),
+        Err(SerializationError::NanEncountered)
+    );
+    assert_eq!(
+        serialize(&map, NonFinitePolicy::Error),
+        Err(SerializationError::InfinityEncountered)
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::ReplaceWithNil).unwrap(),
+        expected(LuaValue::Array(vec![1.0.into()]))
+    );
+    assert_eq!(
+        serialize(&map, NonFinitePolicy::ReplaceWithNil).unwrap(),
+        expected(LuaValue::Map(
+            [(
+                LuaMapKey::try_from(LuaValue::from(2.0)).unwrap(),
+                "b".into()
+            )]
+            .into_iter()
+            .collect()
+        ))
+    );
+    assert_eq!(
+        serialize(&array, NonFinitePolicy::ReplaceWithZero).unwrap(),
+        expected(LuaValue::Array(vec![1.0.into(), 0.0.into(), 0.0.into()]))
+    );
+
+    let serialize_nan = |policy| serialize(&nan.into(), policy).unwrap();
+    assert_eq!(
+        serialize_nan(NonFinitePolicy::Write),
+        b"\x01\x48\x7f\xf8\x00\x00\x00\x00\x00\x00"
+    );
+    assert_eq!(
+        serialize_nan(NonFinitePolicy::PreserveBits),
+        b"\x01\x48\xff\xf8\x00\x00\x00\x00\x00\x01"
+    );
+
+    let mut emitter =
+        Emitter::new(Vec::new()).with_infinity_policy(NonFinitePolicy::ReplaceWithZero);
+    emitter.number(f64::INFINITY).unwrap();
+    assert_eq!(emitter.finish().unwrap(), expected(0.0.into()));
+}
+
+#[test]
+fn non_finite_numbers_are_reported() {
+    let value = LuaValue::Array(vec![
+        f64::NAN.into(),
+        f64::INFINITY.into(),
+        f64::NEG_INFINITY.into(),
+        f64::NEG_INFINITY.into(),
+        1.0.into(),
+    ]);
+    let serialized = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut deserializer = Deserializer::from_slice(&serialized);
+    assert!(deserializer.non_finite_report().is_empty());
+    deserializer.deserialize_next().unwrap();
+
+    let report = deserializer.non_finite_report();
+    assert_eq!(report.nan(), 1);
+    assert_eq!(report.positive_infinity(), 1);
+    assert_eq!(report.negative_infinity(), 2);
+
+    let mut deserializer = IncrementalDeserializer::new();
+    deserializer.feed(&serialized);
+    deserializer.finish();
+    while deserializer.step(2).unwrap() != Step::End {}
+    assert_eq!(deserializer.non_finite_report(), report);
+}
+
+#[test]
+fn tables_are_split_into_array_and_map_parts() {
+    let key = |k: LuaValue| LuaMapKey::try_from(k).unwrap();
+
+    // {"a", "b", x = true}
+    let mixed = LuaValue::Map(LuaMap::from_iter([
+        (key(1.0.into()), "a".into()),
+        (key("x".into()), true.into()),
+        (key(2.0.into()), "b".into()),
+    ]));
+    assert_eq!(
+        Serializer::serialize_one(&mixed, None).unwrap(),
+        b"\x01\x1e\x12a\x12b\x12x\x60"
+    );
+
+    // {"a", "b"}
+    let sequence = LuaValue::Map(LuaMap::from_iter([
+        (key(2.0.into()), "b".into()),
+        (key(1.0.into()), "a".into()),
+    ]));
+    assert_eq!(
+        Serializer::serialize_one(&sequence, None).unwrap(),
+        b"\x01\x2a\x12a\x12b"
+    );
+
+    // {}
+    assert_eq!(
+        Serializer::serialize_one(&LuaValue::Map(LuaMap::new()), None).unwrap(),
+        b"\x01\x0a"
+    );
+
+    // {1, 2, 3, 4, 5, [7] = 7}
+    let wide = LuaValue::Map(LuaMap::from_iter(
+        [1.0, 2.0, 3.0, 4.0, 5.0, 7.0].map(|n| (key(n.into()), n.into())),
+    ));
+    assert_eq!(
+        Serializer::serialize_one(&wide, None).unwrap(),
+        b"\x01\xb8\x05\x01\x03\x05\x07\x09\x0b\x0f\x0f"
+    );
+}
+
+#[cfg(feature = "lua-value-indexmap")]
+#[test]
+fn libserialize_output_is_reproduced() {
+    use crate::{MapBackend, NormalizationPolicy};
+
+    // {"a", "b", zeta = 0.5, alpha = {x = -1.25}, [10] = "aaa", [11] = "aaa"}
+    let input =
+        b"\x01\xde\x12a\x12b\x42zeta\x50\x030.5\x52alpha\x16\x12x\x58\x041.25\x15\x32aaa\x17\xd0\x03";
+
+    let value = Deserializer::from_slice(input)
+        .with_map_backend(MapBackend::IndexMap)
+        .with_normalization(NormalizationPolicy::Preserve)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(Serializer::serialize_one(&value, None).unwrap(), input);
+}
+
+/// Bytes for the Lua values next to them, traced by hand through LibSerialize's writer
+/// for format version 1 on Lua 5.1, the version embedded in the game client.
+/// They were not captured from the game: recheck them there with
+/// `LibStub("LibSerialize"):Serialize(value)` before relying on them.
+/// Every table has at most one hash key, so the order of `pairs` cannot change them.
+const LIBSERIALIZE_FIXTURES: [(&str, &[u8]); 5] = [
+    // Mixed8: 5 array entries do not fit into the embedded mixed count.
+    (
+        "{1, 2, 3, 4, 5, x = true}",
+        b"\x01\xb8\x05\x01\x03\x05\x07\x09\x0b\x12x\x60",
+    ),
+    // FloatStrNeg: the absolute value as a string, since it is shorter than 7 characters.
+    ("{-1.5, -0.25}", b"\x01\x2a\x58\x031.5\x58\x040.25"),
+    // MapRef8: the inner table is registered once its contents are read,
+    // so it is the first one.
+    ("local t = {1}; return {t, t}", b"\x01\x2a\x1a\x03\xe8\x01"),
+    (
+        "local t = {a = 1}; return {t, t}",
+        b"\x01\x2a\x16\x12a\x03\xe8\x01",
+    ),
+    // A hole: `#` may return 4 here, but the array part ends at the first `nil`,
+    // so 4 is written as a map key.
+    ("{1, 2, nil, 4}", b"\x01\x1e\x03\x05\x09\x09"),
+];
+
+#[test]
+fn libserialize_fixtures_are_reproduced() {
+    for (lua, input) in LIBSERIALIZE_FIXTURES {
+        // Shared tables are only preserved by compact values.
+        let value = Deserializer::from_slice(input)
+            .into_compact()
+            .deserialize_first()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            Serializer::serialize_compact(&value, None).unwrap(),
+            input,
+            "{lua}"
+        );
+    }
+}
+
+#[test]
+fn libserialize_fixtures_are_decoded() {
+    let key = |k: LuaValue| LuaMapKey::try_from(k).unwrap();
+    let decode = |input: &[u8]| {
+        Deserializer::from_slice(input)
+            .deserialize_first()
+            .unwrap()
+            .unwrap()
+    };
+
+    let LuaValue::Map(m) = decode(LIBSERIALIZE_FIXTURES[0].1) else {
+        panic!("expected a map");
+    };
+    assert_eq!(m.len(), 6);
+    assert_eq!(m.get(&key(5.0.into())), Some(&5.0.into()));
+    assert_eq!(m.get(&key("x".into())), Some(&true.into()));
+
+    let LuaValue::Array(v) = decode(LIBSERIALIZE_FIXTURES[1].1) else {
+        panic!("expected an array");
+    };
+    assert_eq!(v, [LuaValue::from(-1.5), LuaValue::from(-0.25)]);
+
+    for (_, input) in &LIBSERIALIZE_FIXTURES[2..4] {
+        let LuaValue::Array(v) = decode(input) else {
+            panic!("expected an array");
+        };
+        assert_eq!(format!("{:?}", v[0]), format!("{:?}", v[1]));
+    }
+
+    // Keep the shape that was written instead of turning it back into an array.
+    let LuaValue::Map(m) = Deserializer::from_slice(LIBSERIALIZE_FIXTURES[4].1)
+        .with_normalization(NormalizationPolicy::Preserve)
+        .deserialize_first()
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("expected a map");
+    };
+    assert_eq!(m.len(), 3);
+    assert_eq!(m.get(&key(3.0.into())), None);
+    assert_eq!(m.get(&key(4.0.into())), Some(&4.0.into()));
+}
+
+#[test]
+fn stable_output_sorts_keys() {
+    let key = |k: LuaValue| LuaMapKey::try_from(k).unwrap();
+    let entries = [
+        (key("b".into()), 1.0.into()),
+        (key("a".into()), 2.0.into()),
+        (key(true.into()), 3.0.into()),
+        (key(0.5.into()), 4.0.into()),
+    ];
+
+    let mut outputs = (0..entries.len()).map(|i| {
+        let mut entries = entries.clone();
+        entries.rotate_left(i);
+        let value = LuaValue::Map(LuaMap::from_iter(entries));
+        Serializer::serialize_one_with(&value, SerializeOptions::new().with_stable(true)).unwrap()
+    });
+
+    let first = outputs.next().unwrap();
+    assert_eq!(first, b"\x01\x46\x60\x07\x50\x030.5\x09\x12a\x05\x12b\x03");
+    assert!(outputs.all(|output| output == first));
+}
+
+#[test]
+fn unserializable_keys_are_rejected_or_skipped() {
+    let value = CompactLuaValue::Map(
+        [
+            (CompactLuaValue::Null, 1.0.into()),
+            ("a".into(), 2.0.into()),
+            (f64::NAN.into(), 3.0.into()),
+        ]
+        .into(),
+    );
+
+    assert_eq!(
+        Serializer::serialize_compact(&value, None),
+        Err(SerializationError::UnserializableKey)
+    );
+    assert_eq!(
+        Serializer::serialize_compact_with(
+            &value,
+            SerializeOptions::new().with_error_on_unserializable_type(false)
+        )
+        .unwrap(),
+        b"\x01\x16\x12a\x05"
+    );
+}
+
+#[test]
+fn filter_receives_key_paths() {
+    let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    let value = LuaValue::Map(LuaMap::from_iter([
+        (key("authorMode"), true.into()),
+        (
+            key("d"),
+            LuaValue::Map(LuaMap::from_iter([
+                (key("authorMode"), true.into()),
+                (key("c"), LuaValue::Array(vec![1.0.into(), 2.0.into()])),
+            ])),
+        ),
+    ]));
+
+    let mut seen = Vec::new();
+    let options = SerializeOptions::<LuaValue>::new().with_filter(|path, key, _| {
+        seen.push((path.to_vec(), key.clone()));
+        !matches!(key, LuaValue::String(s) if s == "authorMode")
+    });
+    let serialized = Serializer::serialize_one_with(&value, options).unwrap();
+
+    seen.sort_by_key(|(path, _)| path.len());
+    assert_eq!(seen.len(), 6);
+    assert!(
+        seen[4..]
+            .iter()
+            .all(|(path, _)| *path == [LuaValue::from("d"), LuaValue::from("c")])
+    );
+    assert_eq!(seen[4].1, LuaValue::from(1.0));
+    assert_eq!(seen[5].1, LuaValue::from(2.0));
+
+    // {d = {c = {1, 2}}}
+    assert_eq!(serialized, b"\x01\x16\x12d\x16\x12c\x2a\x03\x05");
+}
+
+#[test]
+fn filtered_array_elements_leave_holes() {
+    let value = LuaValue::Array(vec![1.0.into(), 2.0.into(), 3.0.into()]);
+    let options = SerializeOptions::new().with_filter(|_, _, value| *value != LuaValue::from(2.0));
+
+    // {1, [3] = 3}
+    assert_eq!(
+        Serializer::serialize_one_with(&value, options).unwrap(),
+        b"\x01\x0e\x03\x07\x07"
+    );
+}
+
+/// Feeds `input` in chunks of `chunk_len` bytes, taking steps of `max_items` items.
+fn deserialize_incrementally(
+    input: &[u8],
+    chunk_len: usize,
+    max_items: usize,
+) -> Result<Vec<LuaValue>, DeserializationError> {
+    let mut deserializer = IncrementalDeserializer::new();
+    let mut chunks = input.chunks(chunk_len);
+    let mut values = Vec::new();
+
+    loop {
+        match deserializer.step(max_items)? {
+            Step::Value(value) => values.push(value),
+            Step::NeedMoreInput => match chunks.next() {
+                Some(chunk) => deserializer.feed(chunk),
+                None => deserializer.finish(),
+            },
+            Step::Suspended => {}
+            Step::End => return Ok(values),
+        }
+    }
+}
+
+#[test]
+fn incremental_deserialization_matches_regular() {
+    let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    let value = LuaValue::Map(LuaMap::from_iter([
+        (key("name"), "a long enough string".into()),
+        (key("again"), "a long enough string".into()),
+        (key("float"), 0.1.into()),
+        (key("int"), (-100_000.0).into()),
+        (
+            key("nested"),
+            LuaValue::Array(vec![
+                LuaValue::Array(vec![]),
+                LuaValue::Null,
+                LuaValue::Map(LuaMap::from_iter([(key("x"), true.into())])),
+            ]),
+        ),
+    ]));
+
+    // Two top-level values, the second one is `{"a", "b", x = true}`.
+    let mut input = Serializer::serialize_one(&value, None).unwrap();
+    input.extend_from_slice(b"\x1e\x12a\x12b\x12x\x60");
+
+    let expected: Vec<_> = Deserializer::from_slice(&input)
+        .deserialize_all()
+        .unwrap()
+        .iter()
+        .map(|v| Serializer::serialize_one(v, None).unwrap())
+        .collect();
+    assert_eq!(expected.len(), 2);
+
+    for (chunk_len, max_items) in [(1, usize::MAX), (1, 1), (7, 3), (input.len(), 2)] {
+        let values = deserialize_incrementally(&input, chunk_len, max_items).unwrap();
+        let actual: Vec<_> = values
+            .iter()
+            .map(|v| Serializer::serialize_one(v, None).unwrap())
+            .collect();
+        assert_eq!(
+            actual, expected,
+            "chunk_len: {chunk_len}, max_items: {max_items}"
+        );
+    }
+}
+
+#[test]
+fn incremental_deserialization_reports_errors() {
+    assert_eq!(
+        deserialize_incrementally(b"\x01\x3a\x03\x05", 1, 1).map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::UnexpectedEof)
+    );
+    assert_eq!(
+        deserialize_incrementally(b"\x01\xd2Hello", 100, 1).map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::UnexpectedEof)
+    );
+    assert_eq!(
+        deserialize_incrementally(b"\x03", 1, 1).map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::InvalidPrefix)
+    );
+    assert_eq!(
+        deserialize_incrementally(b"\x01\xd0\x01", 1, 1).map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::InvalidStringReference)
+    );
+
+    // 200 nested arrays.
+    let mut nested = vec![FORMAT_VERSION];
+    nested.extend([0x1a; 200]);
+    assert_eq!(
+        deserialize_incrementally(&nested, 16, 16).map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::RecursionLimitExceeded)
+    );
+    assert_eq!(
+        Deserializer::from_slice(&nested)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::RecursionLimitExceeded)
+    );
+}
+
+#[test]
+fn incremental_deserialization_shares_compact_tables() {
+    // {{}, <reference to the first table>}
+    let mut deserializer = IncrementalDeserializer::new().into_compact();
+    deserializer.feed(b"\x01\x2a\x0a\xe8\x01");
+
+    let Ok(Step::Value(CompactLuaValue::Array(v))) = deserializer.next_value() else {
+        panic!("expected an array");
+    };
+    assert!(v[0].ptr_eq(&v[1]));
+}
+
+/// A writer that records the size of each write and fails after `capacity` bytes.
+struct LimitedWriter {
+    data: Vec<u8>,
+    writes: Vec<usize>,
+    capacity: usize,
+}
+
+impl io::Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.capacity {
+            return Err(io::ErrorKind::StorageFull.into());
+        }
+
+        self.data.extend_from_slice(buf);
+        self.writes.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streamed_output_matches_buffered() {
+    let value = LuaValue::Array(
+        (0..2000)
+            .map(|i| LuaValue::Array(vec![format!("string {i}").into(), (i as f64 / 8.0).into()]))
+            .collect(),
+    );
+    let expected = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut writer = LimitedWriter {
+        data: Vec::new(),
+        writes: Vec::new(),
+        capacity: usize::MAX,
+    };
+    Serializer::serialize_one_into(&value, &mut writer, SerializeOptions::new()).unwrap();
+    assert_eq!(writer.data, expected);
+    // The output is passed on in chunks.
+    assert!(writer.writes.len() > 1);
+    assert!(writer.writes.iter().all(|&len| len < expected.len() / 2));
+
+    let compact = CompactLuaValue::from(&value);
+    let mut output = Vec::new();
+    Serializer::serialize_compact_into(&compact, &mut output, SerializeOptions::new()).unwrap();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn writer_errors_are_reported() {
+    let value = LuaValue::Array((0..5000).map(|i| format!("string {i}").into()).collect());
+
+    let mut writer = LimitedWriter {
+        data: Vec::new(),
+        writes: Vec::new(),
+        capacity: 10_000,
+    };
+    assert!(matches!(
+        Serializer::serialize_one_into(&value, &mut writer, SerializeOptions::new()),
+        Err(WriteError::IoError(e)) if e.kind() == io::ErrorKind::StorageFull
+    ));
+
+    let nested = (0..200).fold(LuaValue::Null, |value, _| LuaValue::Array(vec![value]));
+    assert!(matches!(
+        Serializer::serialize_one_into(&nested, Vec::new(), SerializeOptions::new()),
+        Err(WriteError::SerializationError(
+            SerializationError::RecursionLimitExceeded
+        ))
+    ));
+}
+
+#[test]
+fn parser_describes_tables() {
+    // {{}, "abc", x = "abc", [{}] = <reference to the first table>}
+    let input = b"\x01\x5e\x0a\x32abc\x12x\xd0\x01\x0a\xe8\x01";
+    let events: Vec<_> = Parser::new(input).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(
+        events,
+        [
+            (
+                1,
+                Event::TableStart {
+                    array_len: 2,
+                    hash_len: 2
+                }
+            ),
+            (
+                2,
+                Event::TableStart {
+                    array_len: 0,
+                    hash_len: 0
+                }
+            ),
+            (3, Event::TableEnd),
+            (3, Event::String(b"abc")),
+            (7, Event::Key),
+            (7, Event::String(b"x")),
+            (
+                9,
+                Event::StringRef {
+                    index: 0,
+                    value: b"abc"
+                }
+            ),
+            (11, Event::Key),
+            (
+                11,
+                Event::TableStart {
+                    array_len: 0,
+                    hash_len: 0
+                }
+            ),
+            (12, Event::TableEnd),
+            (
+                12,
+                Event::TableRef {
+                    index: 0,
+                    offset: 2
+                }
+            ),
+            (14, Event::TableEnd),
+        ]
+    );
+}
+
+#[test]
+fn parser_reports_errors_once() {
+    let mut parser = Parser::new(b"\x01\x1a\xd0\x01");
+    assert!(matches!(
+        parser.next_event(),
+        Ok(Some((1, Event::TableStart { .. })))
+    ));
+    assert_eq!(
+        parser.next_event().map_err(|e| e.kind()),
+        Err(DeserializationErrorKind::InvalidStringReference)
+    );
+    assert_eq!(parser.next_event(), Ok(None));
+
+    let mut parser = Parser::new(b"\x01\x2a\x03");
+    assert_eq!(
+        parser.nth(2).map(|r| r.map_err(|e| e.kind())),
+        Some(Err(DeserializationErrorKind::UnexpectedEof))
+    );
+    assert_eq!(parser.next(), None);
+
+    assert_eq!(
+        Parser::new(b"\x05").next().map(|r| r.map_err(|e| e.kind())),
+        Some(Err(DeserializationErrorKind::InvalidPrefix))
+    );
+}
+
+#[test]
+fn emitted_output_matches_serializer() {
+    let value = LuaValue::Array(
+        (0..2000)
+            .map(|i| {
+                LuaValue::Array(vec![
+                    format!("string {}", i % 10).into(),
+                    (i as f64 / 8.0).into(),
+                ])
+            })
+            .collect(),
+    );
+    let expected = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(2000, 0).unwrap();
+    for i in 0..2000 {
+        emitter
+            .begin_table(2, 0)
+            .unwrap()
+            .string(format!("string {}", i % 10))
+            .unwrap()
+            .number(i as f64 / 8.0)
+            .unwrap()
+            .end_table()
+            .unwrap();
+    }
+    emitter.end_table().unwrap();
+    assert_eq!(emitter.finish().unwrap(), expected);
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(1, 1).unwrap().boolean(true).unwrap();
+    emitter.key().unwrap().string("key").unwrap();
+    emitter.begin_table(0, 0).unwrap().end_table().unwrap();
+    emitter.end_table().unwrap();
+    let output = emitter.finish().unwrap();
+
+    let Ok(Some(LuaValue::Map(map))) = Deserializer::from_slice(&output).deserialize_first() else {
+        panic!("expected a map");
+    };
+    assert_eq!(map.len(), 2);
+    assert_eq!(
+        map.get(&LuaMapKey::try_from(LuaValue::from(1.0)).unwrap()),
+        Some(&LuaValue::Boolean(true))
+    );
+    assert!(matches!(
+        map.get(&LuaMapKey::try_from(LuaValue::from("key")).unwrap()),
+        Some(LuaValue::Array(v)) if v.is_empty()
+    ));
+}
+
+#[test]
+fn emitter_checks_structure() {
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(1, 0).unwrap();
+    assert!(matches!(emitter.key(), Err(EmitError::CountMismatch)));
+    assert!(matches!(emitter.end_table(), Err(EmitError::CountMismatch)));
+    emitter.nil().unwrap();
+    assert!(matches!(emitter.nil(), Err(EmitError::CountMismatch)));
+    emitter.end_table().unwrap();
+    assert!(matches!(
+        emitter.end_table(),
+        Err(EmitError::UnexpectedTableEnd)
+    ));
+    assert!(matches!(emitter.key(), Err(EmitError::UnexpectedKey)));
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_table(0, 1).unwrap();
+    assert!(matches!(emitter.boolean(true), Err(EmitError::MissingKey)));
+    emitter.key().unwrap();
+    assert!(matches!(emitter.key(), Err(EmitError::UnexpectedKey)));
+    assert!(matches!(
+        emitter.nil(),
+        Err(EmitError::SerializationError(
+            SerializationError::UnserializableKey
+        ))
+    ));
+    assert!(matches!(
+        emitter.number(f64::NAN),
+        Err(EmitError::SerializationError(
+            SerializationError::UnserializableKey
+        ))
+    ));
+    emitter.number(1.0).unwrap();
+    assert!(matches!(emitter.end_table(), Err(EmitError::CountMismatch)));
+    emitter.nil().unwrap();
+    assert!(matches!(emitter.finish(), Err(EmitError::UnclosedTable)));
+}
+
+/// `{anchor = A, nested = {A, C}, color = C, again = <reference to nested>, point = "CENTER"}`,
+/// where `A` contains the first occurrence of "CENTER".
+fn shared_value() -> Vec<u8> {
+    let anchor = CompactLuaValue::Map(Arc::from([
+        ("point".into(), "CENTER".into()),
+        ("x".into(), 0.0.into()),
+    ]));
+    let color = CompactLuaValue::from(LuaValue::Array(vec![1.0.into(), 1.0.into()]));
+    let nested = CompactLuaValue::Array(Arc::from([anchor.clone(), color.clone()]));
+    let value = CompactLuaValue::Map(Arc::from([
+        ("anchor".into(), anchor),
+        ("nested".into(), nested.clone()),
+        ("color".into(), color),
+        ("again".into(), nested),
+        ("point".into(), "CENTER".into()),
+    ]));
+
+    Serializer::serialize_compact(&value, None).unwrap()
+}
+
+#[test]
+fn selecting_everything_matches_deserialization() {
+    let input = shared_value();
+    let stable = || SerializeOptions::new().with_stable(true);
+
+    let full = Deserializer::from_slice(&input)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let selected = Deserializer::from_slice(&input)
+        .deserialize_selected(&Selector::all())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Serializer::serialize_one_with(&selected, stable()).unwrap(),
+        Serializer::serialize_one_with(&full, stable()).unwrap()
+    );
+}
+
+#[test]
+fn references_to_skipped_values_are_resolved() {
+    let input = shared_value();
+    let selector = Selector::new()
+        .with_path([PathSegment::from("again"), 1.into(), "point".into()])
+        .with_path(["point"]);
+
+    let value = Deserializer::from_slice(&input)
+        .deserialize_selected(&selector)
+        .unwrap()
+        .unwrap();
+    // {again = {{point = "CENTER"}}, point = "CENTER"}
+    let expected = Serializer::serialize_one_with(
+        &LuaValue::Map(LuaMap::from_iter([
+            (
+                LuaMapKey::try_from(LuaValue::from("again")).unwrap(),
+                LuaValue::Array(vec![LuaValue::Map(LuaMap::from_iter([(
+                    LuaMapKey::try_from(LuaValue::from("point")).unwrap(),
+                    "CENTER".into(),
+                )]))]),
+            ),
+            (
+                LuaMapKey::try_from(LuaValue::from("point")).unwrap(),
+                "CENTER".into(),
+            ),
+        ])),
+        SerializeOptions::new().with_stable(true),
+    )
+    .unwrap();
+    assert_eq!(
+        Serializer::serialize_one_with(&value, SerializeOptions::new().with_stable(true)).unwrap(),
+        expected
+    );
+
+    // Whole tables are still shared.
+    let selector = Selector::new().with_path(["nested"]).with_path(["again"]);
+    let CompactLuaValue::Map(entries) = Deserializer::from_slice(&input)
+        .into_compact()
+        .deserialize_selected(&selector)
+        .unwrap()
+        .unwrap()
+    else {
+        panic!("expected a map");
+    };
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].1.ptr_eq(&entries[1].1));
+}
+
+/// `{a = {1, 1, ...}, c = {t[1], t[1], ...}}` with `len` elements in each array,
+/// where `t[1]` is a reference to `a`.
+fn repeated_references(len: u8) -> Vec<u8> {
+    let array = TypeTag::Array8.to_u8() << 3;
+    let mut input = vec![1, 0x26, 0x12, b'a', array, len];
+    input.extend(std::iter::repeat_n(0x03, len.into()));
+    input.extend_from_slice(&[0x12, b'c', array, len]);
+    for _ in 0..len {
+        input.extend_from_slice(&[TypeTag::MapRef8.to_u8() << 3, 1]);
+    }
+    input
+}
+
+#[test]
+fn parsing_referenced_tables_again_is_budgeted() {
+    let input = repeated_references(100);
+    // Nothing is selected within `a`, but it is parsed again for every reference to it.
+    let selector =
+        Selector::new().with_path([PathSegment::from("c"), PathSegment::Any, "id".into()]);
+    let limits = ResourceLimits::default().with_max_cloned_nodes(5_000);
+
+    let exceeded = Err(DeserializationErrorKind::ResourceLimitExceeded(
+        ResourceLimitExceeded::ClonedNodes,
+    ));
+    assert_eq!(
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_selected(&selector)
+            .map_err(|e| e.kind()),
+        exceeded
+    );
+    assert_eq!(
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_first()
+            .map_err(|e| e.kind()),
+        exceeded
+    );
+
+    // Every reference costs the 101 tokens of `a`.
+    let limits = limits.with_max_cloned_nodes(100 * 101);
+    assert!(
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_selected(&selector)
+            .is_ok()
+    );
+}
+
+#[test]
+fn copies_of_selected_tables_are_budgeted() {
+    // {{...{1}...}}, 10 tables deep, see `copies_kept_for_references_count_towards_the_limit`.
+    let mut value = LuaValue::from(1.0);
+    for _ in 0..10 {
+        value = LuaValue::Array(vec![value]);
+    }
+    let input = Serializer::serialize_one(&value, None).unwrap();
+    let select = |limits| {
+        Deserializer::from_slice(&input)
+            .with_limits(limits)
+            .deserialize_selected(&Selector::all())
+            .map(|_| ())
+            .map_err(|e| e.kind())
+    };
+
+    let limits = ResourceLimits::default().with_max_cloned_nodes(65);
+    assert_eq!(select(limits), Ok(()));
+    assert_eq!(
+        select(limits.with_max_cloned_nodes(64)),
+        Err(DeserializationErrorKind::ResourceLimitExceeded(
+            ResourceLimitExceeded::ClonedNodes
+        ))
+    );
+}
+
+#[test]
+fn skipped_values_are_validated() {
+    for (input, error) in [
+        (
+            &b"\x01\x2a\x12a\xd0\x01"[..],
+            DeserializationErrorKind::InvalidStringReference,
+        ),
+        (
+            b"\x01\x1a\xe8\x01",
+            DeserializationErrorKind::InvalidMapReference,
+        ),
+        (
+            b"\x01\x16\x00\x03",
+            DeserializationErrorKind::InvalidMapKeyType,
+        ),
+        (b"\x01\x1a\x2a\x03", DeserializationErrorKind::UnexpectedEof),
+    ] {
+        assert_eq!(
+            Deserializer::from_slice(input)
+                .deserialize_selected(&Selector::new())
+                .map_err(|e| e.kind()),
+            Err(error)
+        );
+    }
+}
+
+#[test]
+fn borrowed_values_match_owned_ones() {
+    fn assert_borrowed(value: &LuaValueRef) {
+        match value {
+            LuaValueRef::Map(entries) => entries.iter().for_each(|(k, v)| {
+                assert_borrowed(k);
+                assert_borrowed(v);
+            }),
+            LuaValueRef::Array(v) => v.iter().for_each(assert_borrowed),
+            LuaValueRef::String(s) => assert!(matches!(s, Cow::Borrowed(_))),
+            _ => {}
+        }
+    }
+
+    let input = shared_value();
+    let stable = || SerializeOptions::new().with_stable(true);
+
+    let full = Deserializer::from_slice(&input)
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    let borrowed = Deserializer::from_slice(&input)
+        .into_borrowed()
+        .deserialize_first()
+        .unwrap()
+        .unwrap();
+    assert_borrowed(&borrowed);
+    assert_eq!(
+        Serializer::serialize_one_with(&borrowed.to_owned(), stable()).unwrap(),
+        Serializer::serialize_one_with(&full, stable()).unwrap()
+    );
+
+    let selected = Deserializer::from_slice(&input)
+        .into_borrowed()
+        .deserialize_selected(&Selector::new().with_path(["again"]))
+        .unwrap()
+        .unwrap();
+    assert_borrowed(&selected);
+    assert!(matches!(selected, LuaValueRef::Map(ref entries) if entries.len() == 1));
+}
+
+#[test]
+fn errors_report_offsets_and_paths() {
+    // {a = {1, <invalid string reference>}}
+    let input = b"\x01\x16\x12a\x2a\x03\xd0\x01";
+    let path = [PathSegment::from("a"), PathSegment::Index(2.0)];
+
+    let errors = [
+        Deserializer::from_slice(input)
+            .deserialize_first()
+            .unwrap_err(),
+        Deserializer::from_slice(input)
+            .into_compact()
+            .deserialize_first()
+            .unwrap_err(),
+        Deserializer::from_slice(input)
+            .deserialize_selected(&Selector::new().with_path(["a"]))
+            .unwrap_err(),
+        Deserializer::from_slice(input)
+            .deserialize_selected(&Selector::new())
+            .unwrap_err(),
+        deserialize_incrementally(input, 1, 1).unwrap_err(),
+    ];
+    for error in errors {
+        assert_eq!(
+            error.kind(),
+            DeserializationErrorKind::InvalidStringReference
+        );
+        let location = error.location().unwrap();
+        assert_eq!((location.offset(), location.path()), (6, &path[..]));
+    }
+
+    let error = Parser::new(input).find_map(Result::err).unwrap();
+    let location = error.location().unwrap();
+    assert_eq!((location.offset(), location.path()), (6, &[][..]));
+
+    // {[nil] = 1} is reported at the key.
+    let error = Deserializer::from_slice(b"\x01\x16\x00\x03")
+        .deserialize_first()
+        .unwrap_err();
+    assert_eq!(error.kind(), DeserializationErrorKind::InvalidMapKeyType);
+    assert_eq!(error.location().unwrap().offset(), 2);
+
+    assert_eq!(
+        Deserializer::from_slice(input)
+            .deserialize_first()
+            .unwrap_err()
+            .to_string(),
+        "Invalid string reference at offset 6 in a[2]\n    \\x01\\x16\\x12a*\\x03\\xd0\\x01\n                      ^"
+    );
+}
+
+#[test]
+fn recovery_closes_tables_cut_short() {
+    let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    // {d = {a = "Aura", z = "print(1)"}}
+    let value = LuaValue::Map(LuaMap::from_iter([(
+        key("d"),
+        LuaValue::Map(LuaMap::from_iter([
+            (key("a"), "Aura".into()),
+            (key("z"), "print(1)".into()),
+        ])),
+    )]));
+    let input = Serializer::serialize_one(&value, None).unwrap();
+
+    let mut deserializer = Deserializer::from_slice(&input[..input.len() - 2]).with_recovery();
+    let LuaValue::Map(root) = deserializer.deserialize_next().unwrap().unwrap() else {
+        panic!("expected a map");
+    };
+    let Some(LuaValue::Map(d)) = root.get(&key("d")) else {
+        panic!("expected a map");
+    };
+    assert_eq!(d.len(), 1);
+    assert_eq!(d.get(&key("a")), Some(&"Aura".into()));
+
+    let error = deserializer.recovery_error().unwrap();
+    assert_eq!(error.kind(), DeserializationErrorKind::UnexpectedEof);
+    assert_eq!(
+        error.location().unwrap().path(),
+        [PathSegment::from("d"), PathSegment::from("z")]
+    );
+    assert_eq!(deserializer.deserialize_next(), Ok(None));
+
+    // Every truncation recovers, reporting the error that stops regular deserialization.
+    for len in 0..=input.len() {
+        let mut deserializer = Deserializer::from_slice(&input[..len]).with_recovery();
+        assert!(deserializer.deserialize_next().is_ok());
+        assert_eq!(
+            deserializer.recovery_error().cloned(),
+            Deserializer::from_slice(&input[..len])
+                .deserialize_first()
+                .err()
+        );
+    }
+}
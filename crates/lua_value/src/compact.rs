@@ -0,0 +1,448 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{LuaMap, LuaMapKey, LuaString, LuaValue, string::debug_bytes};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::Utf8Error,
+};
+use std::{borrow::Cow, sync::Arc};
+
+const INLINE_CAPACITY: usize = 22;
+
+/// An immutable Lua string optimized for memory usage.
+///
+/// Strings of up to 22 bytes are stored inline without allocating,
+/// longer strings are stored in an [Arc] and share their contents between clones.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::CompactString;
+///
+/// let short = CompactString::from("regionType");
+/// assert!(short.is_inline());
+///
+/// let long = CompactString::from("Interface\\AddOns\\WeakAuras\\Media\\Textures\\Circle_White");
+/// assert!(!long.is_inline());
+/// assert_eq!(long.clone(), long);
+/// ```
+#[derive(Clone)]
+pub struct CompactString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Shared(Arc<[u8]>),
+}
+
+impl CompactString {
+    /// Create a string from a slice of bytes.
+    pub fn new(bytes: &[u8]) -> Self {
+        if bytes.len() <= INLINE_CAPACITY {
+            let mut inline = [0; INLINE_CAPACITY];
+            inline[..bytes.len()].copy_from_slice(bytes);
+
+            Self(Repr::Inline {
+                len: bytes.len() as u8,
+                bytes: inline,
+            })
+        } else {
+            Self(Repr::Shared(Arc::from(bytes)))
+        }
+    }
+
+    /// Returns `true` if the string is stored inline.
+    #[inline(always)]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+
+    /// Get the contents of the string as a slice of bytes.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline { len, bytes } => &bytes[..*len as usize],
+            Repr::Shared(bytes) => bytes,
+        }
+    }
+
+    /// Get the contents of the string as `&str` if they are valid UTF-8.
+    #[inline(always)]
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        core::str::from_utf8(self.as_bytes())
+    }
+
+    /// Get the contents of the string as `&str`,
+    /// replacing invalid UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    #[inline(always)]
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+}
+
+impl Default for CompactString {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl Deref for CompactString {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for CompactString {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<[u8]> for CompactString {
+    #[inline(always)]
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<&[u8]> for CompactString {
+    #[inline(always)]
+    fn from(value: &[u8]) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for CompactString {
+    #[inline(always)]
+    fn from(value: &str) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl From<&LuaString> for CompactString {
+    #[inline(always)]
+    fn from(value: &LuaString) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl From<LuaString> for CompactString {
+    #[inline(always)]
+    fn from(value: LuaString) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl From<Arc<[u8]>> for CompactString {
+    /// Short strings are copied inline, longer ones keep sharing `value`.
+    fn from(value: Arc<[u8]>) -> Self {
+        if value.len() <= INLINE_CAPACITY {
+            Self::new(&value)
+        } else {
+            Self(Repr::Shared(value))
+        }
+    }
+}
+
+impl From<&CompactString> for LuaString {
+    #[inline(always)]
+    fn from(value: &CompactString) -> Self {
+        LuaString::from(value.as_bytes())
+    }
+}
+
+impl From<CompactString> for LuaString {
+    #[inline(always)]
+    fn from(value: CompactString) -> Self {
+        LuaString::from(value.as_bytes())
+    }
+}
+
+impl PartialEq for CompactString {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+impl Eq for CompactString {}
+
+impl PartialEq<str> for CompactString {
+    #[inline(always)]
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for CompactString {
+    #[inline(always)]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<[u8]> for CompactString {
+    #[inline(always)]
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == other
+    }
+}
+
+impl PartialOrd for CompactString {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for CompactString {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Hash for CompactString {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl Debug for CompactString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_bytes(self.as_bytes(), f)
+    }
+}
+
+impl Display for CompactString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.to_string_lossy(), f)
+    }
+}
+
+/// An immutable counterpart of [LuaValue] optimized for memory usage.
+///
+/// It takes 24 bytes, stores short strings inline, shares long strings
+/// between clones and keeps tables in exactly sized reference-counted slices
+/// instead of growable vectors and B-trees, which makes it suitable for keeping
+/// large decoded exports in memory. Convert it into [LuaValue] to modify it.
+///
+/// Like in Lua, the same table can appear several times in a value:
+/// cloning a table shares it instead of copying its contents,
+/// and [CompactLuaValue::ptr_eq] tells whether two tables are the same one.
+/// Converting into [LuaValue] makes a separate copy for every occurrence.
+///
+/// [CompactLuaValue::Map] stores entries in the order they were produced in,
+/// keys are never [CompactLuaValue::Null] and values of deserialized maps
+/// are never [CompactLuaValue::Null] either. Should a key occur more than once,
+/// the last entry wins when converting into [LuaValue].
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::{CompactLuaValue, LuaValue};
+///
+/// let value = LuaValue::Array(vec!["a".into(), 1.0.into()]);
+/// let compact = CompactLuaValue::from(&value);
+/// assert!(matches!(compact, CompactLuaValue::Array(ref v) if v.len() == 2));
+///
+/// let LuaValue::Array(v) = LuaValue::from(compact) else {
+///     unreachable!()
+/// };
+/// assert_eq!(v, [LuaValue::from("a"), LuaValue::from(1.0)]);
+/// ```
+#[allow(missing_docs)] // Variants are self-explanatory.
+#[derive(Clone, Debug)]
+pub enum CompactLuaValue {
+    Map(Arc<[(CompactLuaValue, CompactLuaValue)]>),
+    Array(Arc<[CompactLuaValue]>),
+    String(CompactString),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+impl CompactLuaValue {
+    /// Returns `true` if the value is [CompactLuaValue::Null].
+    #[inline(always)]
+    pub fn is_null(&self) -> bool {
+        matches!(self, CompactLuaValue::Null)
+    }
+
+    /// Returns `true` if both values are the same table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lua_value::{CompactLuaValue, LuaValue};
+    ///
+    /// let color = CompactLuaValue::from(LuaValue::Array(vec![1.0.into(), 0.5.into()]));
+    /// let copy = CompactLuaValue::from(LuaValue::from(&color));
+    ///
+    /// assert!(color.ptr_eq(&color.clone()));
+    /// assert!(!color.ptr_eq(&copy));
+    /// ```
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CompactLuaValue::Map(m1), CompactLuaValue::Map(m2)) => Arc::ptr_eq(m1, m2),
+            (CompactLuaValue::Array(v1), CompactLuaValue::Array(v2)) => Arc::ptr_eq(v1, v2),
+            _ => false,
+        }
+    }
+}
+
+impl From<&CompactLuaValue> for LuaValue {
+    fn from(value: &CompactLuaValue) -> Self {
+        match value {
+            CompactLuaValue::Map(entries) => {
+                let mut m = LuaMap::new();
+                for (key, value) in entries.iter() {
+                    if let Ok(key) = LuaMapKey::try_from(LuaValue::from(key)) {
+                        m.insert(key, value.into());
+                    }
+                }
+                LuaValue::Map(m)
+            }
+            CompactLuaValue::Array(v) => LuaValue::Array(v.iter().map(Into::into).collect()),
+            CompactLuaValue::String(s) => LuaValue::String(s.into()),
+            CompactLuaValue::Number(n) => LuaValue::Number(*n),
+            CompactLuaValue::Boolean(b) => LuaValue::Boolean(*b),
+            CompactLuaValue::Null => LuaValue::Null,
+        }
+    }
+}
+
+impl From<CompactLuaValue> for LuaValue {
+    #[inline(always)]
+    fn from(value: CompactLuaValue) -> Self {
+        LuaValue::from(&value)
+    }
+}
+
+impl From<&LuaValue> for CompactLuaValue {
+    fn from(value: &LuaValue) -> Self {
+        match value {
+            LuaValue::Map(m) => CompactLuaValue::Map(
+                m.iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k.as_value().into(), v.into()))
+                    .collect(),
+            ),
+            LuaValue::Array(v) => CompactLuaValue::Array(v.iter().map(Into::into).collect()),
+            LuaValue::String(s) => CompactLuaValue::String(s.into()),
+            LuaValue::Number(n) => CompactLuaValue::Number(*n),
+            LuaValue::Boolean(b) => CompactLuaValue::Boolean(*b),
+            LuaValue::Null => CompactLuaValue::Null,
+        }
+    }
+}
+
+impl From<LuaValue> for CompactLuaValue {
+    #[inline(always)]
+    fn from(value: LuaValue) -> Self {
+        CompactLuaValue::from(&value)
+    }
+}
+
+impl From<CompactString> for CompactLuaValue {
+    #[inline(always)]
+    fn from(value: CompactString) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for CompactLuaValue {
+    #[inline(always)]
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<f64> for CompactLuaValue {
+    #[inline(always)]
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<bool> for CompactLuaValue {
+    #[inline(always)]
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_values_are_small() {
+        assert_eq!(size_of::<CompactString>(), 24);
+        assert_eq!(size_of::<CompactLuaValue>(), 24);
+        assert!(size_of::<CompactLuaValue>() < size_of::<LuaValue>());
+    }
+
+    #[test]
+    fn strings_are_inlined_up_to_capacity() {
+        let s = CompactString::new(&[b'a'; INLINE_CAPACITY]);
+        assert!(s.is_inline());
+        assert_eq!(s.as_bytes(), [b'a'; INLINE_CAPACITY]);
+
+        let s = CompactString::new(&[b'a'; INLINE_CAPACITY + 1]);
+        assert!(!s.is_inline());
+        assert_eq!(s.len(), INLINE_CAPACITY + 1);
+
+        let shared: Arc<[u8]> = Arc::from(&b"abc"[..]);
+        assert!(CompactString::from(shared).is_inline());
+    }
+
+    #[test]
+    fn conversion_drops_null_entries() {
+        let mut m = LuaMap::new();
+        m.insert(
+            LuaMapKey::try_from(LuaValue::from("a")).unwrap(),
+            1.0.into(),
+        );
+        m.insert(
+            LuaMapKey::try_from(LuaValue::from("b")).unwrap(),
+            LuaValue::Null,
+        );
+
+        let CompactLuaValue::Map(entries) = CompactLuaValue::from(LuaValue::Map(m)) else {
+            panic!("expected a map");
+        };
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0].0, CompactLuaValue::String(ref s) if s == "a"));
+    }
+
+    #[test]
+    fn last_duplicate_key_wins() {
+        let value = CompactLuaValue::Map(Arc::new([
+            ("a".into(), 1.0.into()),
+            ("a".into(), 2.0.into()),
+            (CompactLuaValue::Null, 3.0.into()),
+        ]));
+
+        let LuaValue::Map(m) = LuaValue::from(value) else {
+            panic!("expected a map");
+        };
+        assert_eq!(m.len(), 1);
+        assert_eq!(
+            m.get(&LuaMapKey::try_from(LuaValue::from("a")).unwrap()),
+            Some(&LuaValue::from(2.0))
+        );
+    }
+}
@@ -31,3 +31,29 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 }
 
 impl error::Error for TryFromLuaValueError {}
+
+/// The error type returned when a [ResourceBudget](crate::ResourceBudget) is exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceLimitExceeded {
+    /// Too many values.
+    Nodes,
+    /// Too many bytes in strings.
+    StringBytes,
+    /// A table has too many entries.
+    CollectionLength,
+    /// Too many values created by copying referenced tables.
+    ClonedNodes,
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Nodes => write!(f, "Too many values"),
+            Self::StringBytes => write!(f, "Too many bytes in strings"),
+            Self::CollectionLength => write!(f, "A table has too many entries"),
+            Self::ClonedNodes => write!(f, "Too many values copied from referenced tables"),
+        }
+    }
+}
+
+impl error::Error for ResourceLimitExceeded {}
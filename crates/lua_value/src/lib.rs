@@ -6,21 +6,36 @@
 //! # Crate features
 //!
 //! * **arbitrary** - Implement `arbitrary::Arbitrary` for [`LuaValue`]. **Disabled** by default.
-//! * **fnv** - Use `fnv` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
-//! * **indexmap** - Use `indexmap` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
+//! * **fnv** - Make `fnv` available as an implementation of [`LuaMap`], see [`MapBackend::Fnv`]. **Disabled** by default.
+//! * **indexmap** - Make `indexmap` available as an implementation of [`LuaMap`], see [`MapBackend::IndexMap`]. **Disabled** by default.
 //! * **serde** - Allow serializing and deserializing [`LuaValue`] using `serde`. **Disabled** by default.
 
 #![deny(missing_docs)]
 
+mod compact;
 /// Error types.
 pub mod error;
-
-#[cfg(all(not(feature = "indexmap"), feature = "fnv"))]
-pub use fnv::FnvHashMap as Map;
-#[cfg(feature = "indexmap")]
-pub use indexmap::IndexMap as Map;
-#[cfg(not(any(feature = "indexmap", feature = "fnv")))]
-pub use std::collections::BTreeMap as Map;
+mod limits;
+mod location;
+/// Map implementation.
+pub mod map;
+mod non_finite;
+mod normalization;
+mod number;
+mod selector;
+mod string;
+mod value_ref;
+
+pub use compact::{CompactLuaValue, CompactString};
+pub use limits::{ResourceBudget, ResourceLimits};
+pub use location::ErrorLocation;
+pub use map::{LuaMap, MapBackend};
+pub use non_finite::{NonFinitePolicy, NonFiniteReport};
+pub use normalization::NormalizationPolicy;
+pub use number::format_g14;
+pub use selector::{PathSegment, Selector};
+pub use string::LuaString;
+pub use value_ref::LuaValueRef;
 
 use crate::error::TryFromLuaValueError;
 use core::convert::TryFrom;
@@ -35,33 +50,69 @@
 
 /// A tagged union representing all
 /// possible values in Lua.
+///
+/// Lua tables cannot store `nil`, so [LuaValue::Null] inside a table
+/// stands for a missing value:
+///
+/// * a `Null` element of an [Array](LuaValue::Array) is a hole, i.e. `{1, nil, 3}`
+///   is `Array(vec![1.0.into(), Null, 3.0.into()])`. Trailing holes are not
+///   part of the table;
+/// * a [Map](LuaValue::Map) entry with a `Null` value is equivalent to the key being absent.
+///
+/// Serializers follow these rules and deserializers produce values that follow them.
 #[allow(missing_docs)] // Variants are self-explanatory.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 pub enum LuaValue {
-    Map(Map<LuaMapKey, LuaValue>),
+    Map(LuaMap),
     Array(Vec<LuaValue>),
-    String(String),
+    String(LuaString),
     Number(f64),
     Boolean(bool),
     Null,
 }
 
+impl LuaValue {
+    /// Returns `true` if the value is [LuaValue::Null].
+    #[inline(always)]
+    pub fn is_null(&self) -> bool {
+        matches!(self, LuaValue::Null)
+    }
+}
+
+impl From<LuaString> for LuaValue {
+    fn from(value: LuaString) -> Self {
+        Self::String(value)
+    }
+}
+
 impl From<String> for LuaValue {
     fn from(value: String) -> Self {
-        Self::String(value)
+        Self::String(value.into())
     }
 }
 
 impl From<&str> for LuaValue {
     fn from(value: &str) -> Self {
-        Self::String(value.to_owned())
+        Self::String(value.into())
     }
 }
 
 impl From<&mut str> for LuaValue {
     fn from(value: &mut str) -> Self {
-        Self::String(value.to_owned())
+        Self::String((&*value).into())
+    }
+}
+
+impl From<Vec<u8>> for LuaValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<&[u8]> for LuaValue {
+    fn from(value: &[u8]) -> Self {
+        Self::String(value.into())
     }
 }
 
@@ -111,7 +162,7 @@ fn try_from(value: LuaValue) -> Result<Self, Self::Error> {
 impl Hash for LuaValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
-            LuaValue::Map(m) => state.write_usize((m as *const Map<LuaMapKey, LuaValue>).addr()),
+            LuaValue::Map(m) => state.write_usize((m as *const LuaMap).addr()),
             LuaValue::Array(v) => state.write_usize((v as *const Vec<LuaValue>).addr()),
             LuaValue::String(s) => s.hash(state),
             LuaValue::Number(n) => state.write_u64(n.to_bits()),
@@ -154,8 +205,8 @@ fn cmp(&self, other: &LuaValue) -> Ordering {
             (LuaValue::Map(_), LuaValue::Boolean(_))
             | (LuaValue::Array(_), LuaValue::Boolean(_)) => Ordering::Less,
             (LuaValue::Map(m1), LuaValue::Map(m2)) => {
-                let p1 = (m1 as *const Map<LuaMapKey, LuaValue>).addr();
-                let p2 = (m2 as *const Map<LuaMapKey, LuaValue>).addr();
+                let p1 = (m1 as *const LuaMap).addr();
+                let p2 = (m2 as *const LuaMap).addr();
                 p1.cmp(&p2)
             }
             (LuaValue::Map(_), LuaValue::Array(_)) => Ordering::Greater,
@@ -175,8 +226,8 @@ impl PartialEq for LuaValue {
     fn eq(&self, other: &LuaValue) -> bool {
         match (self, other) {
             (LuaValue::Map(m1), LuaValue::Map(m2)) => {
-                let p1 = (m1 as *const Map<LuaMapKey, LuaValue>).addr();
-                let p2 = (m2 as *const Map<LuaMapKey, LuaValue>).addr();
+                let p1 = (m1 as *const LuaMap).addr();
+                let p2 = (m2 as *const LuaMap).addr();
                 p1.eq(&p2)
             }
             (LuaValue::Array(v1), LuaValue::Array(v2)) => {
@@ -203,7 +254,7 @@ fn hash<H: Hasher>(&self, state: &mut H) {
 impl PartialOrd for LuaMapKey {
     #[inline(always)]
     fn partial_cmp(&self, other: &LuaMapKey) -> Option<Ordering> {
-        Some(self.0.cmp(&other.0))
+        Some(self.cmp(other))
     }
 }
 impl Ord for LuaMapKey {
@@ -226,7 +277,7 @@ impl LuaMapKey {
     #[cfg(feature = "serde")]
     fn to_string(&self) -> Cow<'_, str> {
         match self.0 {
-            LuaValue::String(ref v) => Cow::from(v),
+            LuaValue::String(ref v) => v.to_string_lossy(),
             LuaValue::Number(v) => Cow::from(v.to_string()),
             LuaValue::Boolean(v) => Cow::from(v.to_string()),
             LuaValue::Map(ref m) => Cow::from(format!("map at {m:p}")),
@@ -280,7 +331,10 @@ fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         use serde::ser::{SerializeMap, SerializeSeq};
 
         match self {
-            LuaValue::String(s) => serializer.serialize_str(s),
+            LuaValue::String(s) => match s.to_str() {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => serializer.serialize_bytes(s),
+            },
             LuaValue::Number(n) => serializer.serialize_f64(*n),
             LuaValue::Boolean(b) => serializer.serialize_bool(*b),
             LuaValue::Null => serializer.serialize_none(),
@@ -365,7 +419,15 @@ fn visit_str<E>(self, value: &str) -> Result<LuaValue, E>
             }
 
             fn visit_string<E>(self, value: String) -> Result<LuaValue, E> {
-                Ok(LuaValue::String(value))
+                Ok(LuaValue::String(value.into()))
+            }
+
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<LuaValue, E> {
+                Ok(LuaValue::String(value.into()))
+            }
+
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<LuaValue, E> {
+                Ok(LuaValue::String(value.into()))
             }
 
             fn visit_seq<V>(self, mut seq: V) -> Result<LuaValue, V::Error>
@@ -385,16 +447,13 @@ fn visit_map<V>(self, mut map: V) -> Result<LuaValue, V::Error>
             where
                 V: MapAccess<'de>,
             {
-                #[cfg(any(feature = "indexmap", feature = "fnv"))]
-                let mut result = Map::with_capacity(map.size_hint().unwrap_or(16));
-                #[cfg(not(any(feature = "indexmap", feature = "fnv")))]
-                let mut result = Map::new();
+                let mut result = LuaMap::new();
 
                 while let Some(key) = map.next_key()? {
                     let key = LuaMapKey::try_from(match key {
-                        LuaValue::String(s) => match s.parse::<i32>() {
-                            Ok(n) => LuaValue::Number(n as f64),
-                            Err(_) => LuaValue::String(s),
+                        LuaValue::String(s) => match s.to_str().map(str::parse::<i32>) {
+                            Ok(Ok(n)) => LuaValue::Number(n as f64),
+                            _ => LuaValue::String(s),
                         },
                         v => v,
                     })
@@ -0,0 +1,202 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::error::ResourceLimitExceeded;
+
+/// Limits on the resources a deserializer may consume.
+///
+/// Serialized data can describe values that are far larger than the data itself,
+/// e.g. by referencing the same large table many times. Deserializers keep
+/// a [ResourceBudget] based on these limits and fail once it is exhausted.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::ResourceLimits;
+///
+/// let limits = ResourceLimits::default()
+///     .with_max_nodes(10_000)
+///     .with_max_collection_len(1_000);
+///
+/// assert_eq!(limits.max_nodes(), 10_000);
+/// assert_eq!(ResourceLimits::unlimited().max_nodes(), usize::MAX);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceLimits {
+    max_nodes: usize,
+    max_string_bytes: usize,
+    max_collection_len: usize,
+    max_cloned_nodes: usize,
+}
+
+impl ResourceLimits {
+    /// Limits that never trigger.
+    pub const fn unlimited() -> Self {
+        Self {
+            max_nodes: usize::MAX,
+            max_string_bytes: usize::MAX,
+            max_collection_len: usize::MAX,
+            max_cloned_nodes: usize::MAX,
+        }
+    }
+
+    /// Set the maximum number of values, counting every table, key and value
+    /// at every level of nesting. The default is `2^22`.
+    pub const fn with_max_nodes(mut self, value: usize) -> Self {
+        self.max_nodes = value;
+        self
+    }
+
+    /// Set the maximum total length of all strings in bytes.
+    /// Every occurrence of a string counts, even if it is a reference
+    /// to an earlier one. The default is 64 MiB.
+    pub const fn with_max_string_bytes(mut self, value: usize) -> Self {
+        self.max_string_bytes = value;
+        self
+    }
+
+    /// Set the maximum number of entries in a single table. The default is `2^20`.
+    pub const fn with_max_collection_len(mut self, value: usize) -> Self {
+        self.max_collection_len = value;
+        self
+    }
+
+    /// Set the maximum total number of values created by copying
+    /// previously deserialized tables to resolve references to them.
+    /// The default is `2^20`.
+    pub const fn with_max_cloned_nodes(mut self, value: usize) -> Self {
+        self.max_cloned_nodes = value;
+        self
+    }
+
+    /// Returns the maximum number of values.
+    pub const fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    /// Returns the maximum total length of all strings in bytes.
+    pub const fn max_string_bytes(&self) -> usize {
+        self.max_string_bytes
+    }
+
+    /// Returns the maximum number of entries in a single table.
+    pub const fn max_collection_len(&self) -> usize {
+        self.max_collection_len
+    }
+
+    /// Returns the maximum total number of values created by copying tables.
+    pub const fn max_cloned_nodes(&self) -> usize {
+        self.max_cloned_nodes
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1 << 22,
+            max_string_bytes: 64 * 1024 * 1024,
+            max_collection_len: 1 << 20,
+            max_cloned_nodes: 1 << 20,
+        }
+    }
+}
+
+/// Resources left for a deserializer to consume.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::{ResourceBudget, ResourceLimits, error::ResourceLimitExceeded};
+///
+/// let mut budget = ResourceBudget::new(ResourceLimits::default().with_max_nodes(2));
+/// assert_eq!(budget.spend_nodes(2), Ok(()));
+/// assert_eq!(budget.spend_nodes(1), Err(ResourceLimitExceeded::Nodes));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ResourceBudget {
+    nodes: usize,
+    string_bytes: usize,
+    cloned_nodes: usize,
+    max_collection_len: usize,
+}
+
+impl ResourceBudget {
+    /// Create a budget with all of the resources allowed by `limits`.
+    pub const fn new(limits: ResourceLimits) -> Self {
+        Self {
+            nodes: limits.max_nodes,
+            string_bytes: limits.max_string_bytes,
+            cloned_nodes: limits.max_cloned_nodes,
+            max_collection_len: limits.max_collection_len,
+        }
+    }
+
+    /// Account for `count` new values.
+    #[inline]
+    pub fn spend_nodes(&mut self, count: usize) -> Result<(), ResourceLimitExceeded> {
+        spend(&mut self.nodes, count, ResourceLimitExceeded::Nodes)
+    }
+
+    /// Account for a string of `len` bytes.
+    #[inline]
+    pub fn spend_string_bytes(&mut self, len: usize) -> Result<(), ResourceLimitExceeded> {
+        spend(
+            &mut self.string_bytes,
+            len,
+            ResourceLimitExceeded::StringBytes,
+        )
+    }
+
+    /// Account for a copy of a table consisting of `count` values.
+    ///
+    /// The copied values count towards the limit on the number of values as well.
+    #[inline]
+    pub fn spend_cloned_nodes(&mut self, count: usize) -> Result<(), ResourceLimitExceeded> {
+        spend(
+            &mut self.cloned_nodes,
+            count,
+            ResourceLimitExceeded::ClonedNodes,
+        )?;
+        self.spend_nodes(count)
+    }
+
+    /// Account for copying a table consisting of `count` values where the copy
+    /// is not part of the result, e.g. the table being parsed again
+    /// to resolve a reference to it.
+    ///
+    /// Unlike [ResourceBudget::spend_cloned_nodes], this does not count towards
+    /// the limit on the number of values.
+    #[inline]
+    pub fn spend_copied_nodes(&mut self, count: usize) -> Result<(), ResourceLimitExceeded> {
+        spend(
+            &mut self.cloned_nodes,
+            count,
+            ResourceLimitExceeded::ClonedNodes,
+        )
+    }
+
+    /// Check that a table with `len` entries is allowed.
+    #[inline]
+    pub fn check_collection_len(&self, len: usize) -> Result<(), ResourceLimitExceeded> {
+        if len > self.max_collection_len {
+            Err(ResourceLimitExceeded::CollectionLength)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[inline(always)]
+fn spend(
+    remaining: &mut usize,
+    amount: usize,
+    error: ResourceLimitExceeded,
+) -> Result<(), ResourceLimitExceeded> {
+    match remaining.checked_sub(amount) {
+        Some(left) => {
+            *remaining = left;
+            Ok(())
+        }
+        None => Err(error),
+    }
+}
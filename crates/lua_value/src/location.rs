@@ -0,0 +1,242 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::PathSegment;
+use core::fmt::{self, Write};
+
+/// The number of bytes kept on each side of the offset for [ErrorLocation]'s `Display`.
+const SNIPPET_CONTEXT: usize = 24;
+
+/// Where in the input a decoding error occurred.
+///
+/// Consists of the byte offset within the input of the layer that failed,
+/// the path to the value that was being decoded, and a few bytes of the input
+/// around the offset. Its `Display` renders all of them, pointing at the offset
+/// with a caret.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::{ErrorLocation, PathSegment};
+///
+/// let location = ErrorLocation::new(b"^1^T^Sc^T^N1^X^t^t^^", 12)
+///     .with_path(vec![PathSegment::from("c"), PathSegment::Index(1.0)]);
+///
+/// assert_eq!(location.offset(), 12);
+/// assert_eq!(
+///     location.to_string(),
+///     "at offset 12 in c[1]\n    ^1^T^Sc^T^N1^X^t^t^^\n                ^"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorLocation {
+    offset: usize,
+    path: Vec<PathSegment>,
+    snippet: Option<Snippet>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Snippet {
+    bytes: Vec<u8>,
+    /// The position of the offset within `bytes`.
+    anchor: usize,
+    truncated_start: bool,
+    truncated_end: bool,
+}
+
+impl ErrorLocation {
+    /// Create a location at `offset` in `input`, keeping the bytes around it.
+    pub fn new(input: &[u8], offset: usize) -> Self {
+        let anchor = offset.min(input.len());
+        let start = anchor.saturating_sub(SNIPPET_CONTEXT);
+        let end = anchor.saturating_add(SNIPPET_CONTEXT).min(input.len());
+
+        Self {
+            offset,
+            path: Vec::new(),
+            snippet: Some(Snippet {
+                bytes: input[start..end].to_vec(),
+                anchor: anchor - start,
+                truncated_start: start > 0,
+                truncated_end: end < input.len(),
+            }),
+        }
+    }
+
+    /// Set the path to the value that was being decoded.
+    pub fn with_path(mut self, path: Vec<PathSegment>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Prepend `segment` to the path, e.g. while an error propagates out of a table.
+    pub fn with_parent(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// Shift the offset by `base`, for an input that starts `base` bytes into a larger one.
+    pub fn with_base_offset(mut self, base: usize) -> Self {
+        self.offset += base;
+        self
+    }
+
+    /// Get the byte offset within the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Get the path to the value that was being decoded, empty for a top-level value.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    /// Get a `Display` for the bytes around the offset and a caret pointing at it,
+    /// each on a line of its own, without the offset and the path.
+    ///
+    /// Renders nothing for a location created with `Default`.
+    pub fn snippet(&self) -> impl fmt::Display + '_ {
+        SnippetDisplay(self.snippet.as_ref())
+    }
+
+    /// Take the path, leaving an empty one.
+    pub fn take_path(&mut self) -> Vec<PathSegment> {
+        core::mem::take(&mut self.path)
+    }
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at offset {}", self.offset)?;
+
+        if !self.path.is_empty() {
+            f.write_str(" in ")?;
+            write_path(f, &self.path)?;
+        }
+
+        self.snippet().fmt(f)
+    }
+}
+
+/// The caret-annotated bytes around an offset, see [ErrorLocation::snippet].
+struct SnippetDisplay<'a>(Option<&'a Snippet>);
+
+impl fmt::Display for SnippetDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(snippet) = self.0 else {
+            return Ok(());
+        };
+
+        let mut line = String::from("    ");
+        if snippet.truncated_start {
+            line.push_str("...");
+        }
+        for &b in &snippet.bytes[..snippet.anchor] {
+            push_byte(&mut line, b, false);
+        }
+        let caret = line.len();
+        for &b in &snippet.bytes[snippet.anchor..] {
+            push_byte(&mut line, b, false);
+        }
+        if snippet.truncated_end {
+            line.push_str("...");
+        }
+
+        write!(f, "\n{line}\n{:caret$}^", "")
+    }
+}
+
+fn write_path(f: &mut fmt::Formatter, path: &[PathSegment]) -> fmt::Result {
+    for (i, segment) in path.iter().enumerate() {
+        match segment {
+            PathSegment::Key(key) if is_identifier(key.as_bytes()) => {
+                if i > 0 {
+                    f.write_char('.')?;
+                }
+                // Identifiers are ASCII.
+                f.write_str(key.to_str().unwrap_or_default())?;
+            }
+            PathSegment::Key(key) => {
+                let mut quoted = String::from("[\"");
+                for &b in key.as_bytes() {
+                    push_byte(&mut quoted, b, true);
+                }
+                quoted.push_str("\"]");
+                f.write_str(&quoted)?;
+            }
+            PathSegment::Index(n) => write!(f, "[{n}]")?,
+            PathSegment::Any => f.write_str("[?]")?,
+        }
+    }
+
+    Ok(())
+}
+
+fn is_identifier(bytes: &[u8]) -> bool {
+    match bytes.split_first() {
+        Some((first, rest)) => {
+            (first.is_ascii_alphabetic() || *first == b'_')
+                && rest.iter().all(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        }
+        None => false,
+    }
+}
+
+/// Pushes a printable ASCII byte as it is and any other byte as `\xNN`.
+fn push_byte(s: &mut String, b: u8, quoted: bool) {
+    match b {
+        b'"' | b'\\' if quoted => {
+            s.push('\\');
+            s.push(b as char);
+        }
+        0x20..=0x7E => s.push(b as char),
+        _ => {
+            let _ = write!(s, "\\x{b:02x}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_rendering() {
+        let location = ErrorLocation::default()
+            .with_parent(PathSegment::Any)
+            .with_parent(PathSegment::from("trigger"))
+            .with_parent(PathSegment::Index(1.0))
+            .with_parent(PathSegment::from("with \"quotes\""))
+            .with_parent(PathSegment::from("triggers"));
+
+        assert_eq!(
+            location.to_string(),
+            "at offset 0 in triggers[\"with \\\"quotes\\\"\"][1].trigger[?]"
+        );
+    }
+
+    #[test]
+    fn snippet_rendering() {
+        let input = b"\x01\x3a\x03\x05\xff";
+        assert_eq!(
+            ErrorLocation::new(input, 4).to_string(),
+            format!(
+                "at offset 4\n    \\x01:\\x03\\x05\\xff\n{}^",
+                " ".repeat(4 + 13)
+            )
+        );
+        assert_eq!(
+            ErrorLocation::new(input, 5).to_string(),
+            format!(
+                "at offset 5\n    \\x01:\\x03\\x05\\xff\n{}^",
+                " ".repeat(4 + 17)
+            )
+        );
+
+        let input = [b'a'; 100];
+        let rendered = ErrorLocation::new(&input, 50).to_string();
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[1], format!("    ...{}...", "a".repeat(48)));
+        assert_eq!(lines[2].len(), 4 + 3 + 24 + 1);
+    }
+}
@@ -0,0 +1,441 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{LuaMapKey, LuaValue};
+use core::fmt::{self, Debug};
+use std::collections::{BTreeMap, btree_map};
+
+#[cfg(feature = "fnv")]
+use fnv::FnvHashMap;
+#[cfg(feature = "fnv")]
+use std::collections::hash_map;
+
+#[cfg(feature = "indexmap")]
+use indexmap::IndexMap;
+
+/// An implementation of [LuaMap].
+///
+/// The implementation determines the iteration order of a map,
+/// and thus the order in which serializers write its entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MapBackend {
+    /// `BTreeMap`, iterates in the order of keys.
+    #[default]
+    BTreeMap,
+    /// `HashMap` with the FNV hasher, iterates in an unspecified order.
+    #[cfg(feature = "fnv")]
+    Fnv,
+    /// `IndexMap`, iterates in the order of insertion.
+    #[cfg(feature = "indexmap")]
+    IndexMap,
+}
+
+#[derive(Clone)]
+enum Inner {
+    BTreeMap(BTreeMap<LuaMapKey, LuaValue>),
+    #[cfg(feature = "fnv")]
+    Fnv(FnvHashMap<LuaMapKey, LuaValue>),
+    #[cfg(feature = "indexmap")]
+    IndexMap(IndexMap<LuaMapKey, LuaValue>),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $m:ident => $body:expr) => {
+        match $self {
+            Inner::BTreeMap($m) => $body,
+            #[cfg(feature = "fnv")]
+            Inner::Fnv($m) => $body,
+            #[cfg(feature = "indexmap")]
+            Inner::IndexMap($m) => $body,
+        }
+    };
+    ($self:expr, $m:ident => $body:expr, $wrapper:ident) => {
+        match $self {
+            Inner::BTreeMap($m) => $wrapper::BTreeMap($body),
+            #[cfg(feature = "fnv")]
+            Inner::Fnv($m) => $wrapper::Fnv($body),
+            #[cfg(feature = "indexmap")]
+            Inner::IndexMap($m) => $wrapper::IndexMap($body),
+        }
+    };
+}
+
+/// A Lua table with arbitrary keys.
+///
+/// The implementation is chosen at runtime, see [MapBackend].
+/// Maps with different backends can coexist in one program,
+/// and [LuaMap::into_backend] converts between them.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::{LuaMap, LuaMapKey, LuaValue, MapBackend};
+///
+/// let key = |s: &str| LuaMapKey::try_from(LuaValue::from(s)).unwrap();
+///
+/// let mut map = LuaMap::new();
+/// map.insert(key("b"), 2.0.into());
+/// map.insert(key("a"), 1.0.into());
+/// assert_eq!(map.backend(), MapBackend::BTreeMap);
+///
+/// let keys: Vec<_> = map.keys().map(|k| k.as_value().clone()).collect();
+/// assert_eq!(keys, [LuaValue::from("a"), LuaValue::from("b")]);
+/// ```
+#[derive(Clone)]
+pub struct LuaMap(Inner);
+
+impl LuaMap {
+    /// Create an empty map backed by a `BTreeMap`.
+    #[inline]
+    pub fn new() -> Self {
+        Self(Inner::BTreeMap(BTreeMap::new()))
+    }
+
+    /// Create an empty map using the provided implementation.
+    pub fn with_backend(backend: MapBackend) -> Self {
+        Self::with_capacity_and_backend(0, backend)
+    }
+
+    /// Create an empty map using the provided implementation
+    /// with space for at least `capacity` entries, if the implementation supports preallocation.
+    pub fn with_capacity_and_backend(capacity: usize, backend: MapBackend) -> Self {
+        Self(match backend {
+            MapBackend::BTreeMap => {
+                // BTreeMap does not support preallocation.
+                let _ = capacity;
+                Inner::BTreeMap(BTreeMap::new())
+            }
+            #[cfg(feature = "fnv")]
+            MapBackend::Fnv => Inner::Fnv(FnvHashMap::with_capacity_and_hasher(
+                capacity,
+                Default::default(),
+            )),
+            #[cfg(feature = "indexmap")]
+            MapBackend::IndexMap => Inner::IndexMap(IndexMap::with_capacity(capacity)),
+        })
+    }
+
+    /// Returns the implementation used by the map.
+    pub fn backend(&self) -> MapBackend {
+        match self.0 {
+            Inner::BTreeMap(_) => MapBackend::BTreeMap,
+            #[cfg(feature = "fnv")]
+            Inner::Fnv(_) => MapBackend::Fnv,
+            #[cfg(feature = "indexmap")]
+            Inner::IndexMap(_) => MapBackend::IndexMap,
+        }
+    }
+
+    /// Convert the map into one that uses the provided implementation.
+    ///
+    /// Nested maps are converted as well.
+    pub fn into_backend(self, backend: MapBackend) -> Self {
+        let mut result = Self::with_capacity_and_backend(self.len(), backend);
+        for (key, mut value) in self {
+            value.convert_maps(backend);
+            result.insert(key, value);
+        }
+        result
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        dispatch!(&self.0, m => m.len())
+    }
+
+    /// Returns `true` if the map has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline]
+    pub fn get(&self, key: &LuaMapKey) -> Option<&LuaValue> {
+        dispatch!(&self.0, m => m.get(key))
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline]
+    pub fn get_mut(&mut self, key: &LuaMapKey) -> Option<&mut LuaValue> {
+        dispatch!(&mut self.0, m => m.get_mut(key))
+    }
+
+    /// Returns `true` if the map contains the key.
+    #[inline]
+    pub fn contains_key(&self, key: &LuaMapKey) -> bool {
+        dispatch!(&self.0, m => m.contains_key(key))
+    }
+
+    /// Insert a key-value pair into the map, returning the old value if there was one.
+    #[inline]
+    pub fn insert(&mut self, key: LuaMapKey, value: LuaValue) -> Option<LuaValue> {
+        dispatch!(&mut self.0, m => m.insert(key, value))
+    }
+
+    /// Remove a key from the map, returning its value if there was one.
+    ///
+    /// Preserves the order of the remaining entries.
+    #[inline]
+    pub fn remove(&mut self, key: &LuaMapKey) -> Option<LuaValue> {
+        match &mut self.0 {
+            Inner::BTreeMap(m) => m.remove(key),
+            #[cfg(feature = "fnv")]
+            Inner::Fnv(m) => m.remove(key),
+            #[cfg(feature = "indexmap")]
+            Inner::IndexMap(m) => m.shift_remove(key),
+        }
+    }
+
+    /// Retain only the entries for which `f` returns `true`.
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&LuaMapKey, &mut LuaValue) -> bool,
+    {
+        dispatch!(&mut self.0, m => m.retain(|k, v| f(k, v)))
+    }
+
+    /// Remove all entries.
+    #[inline]
+    pub fn clear(&mut self) {
+        dispatch!(&mut self.0, m => m.clear())
+    }
+
+    /// An iterator over the entries of the map.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(dispatch!(&self.0, m => m.iter(), IterInner))
+    }
+
+    /// An iterator over the entries of the map with mutable references to values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(dispatch!(&mut self.0, m => m.iter_mut(), IterMutInner))
+    }
+
+    /// An iterator over the keys of the map.
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &LuaMapKey> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// An iterator over the values of the map.
+    #[inline]
+    pub fn values(&self) -> impl Iterator<Item = &LuaValue> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// An iterator over mutable references to the values of the map.
+    #[inline]
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut LuaValue> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl LuaValue {
+    /// Recursively convert maps to the provided implementation.
+    fn convert_maps(&mut self, backend: MapBackend) {
+        match self {
+            LuaValue::Map(m) => {
+                let m = core::mem::take(m);
+                *self = LuaValue::Map(m.into_backend(backend));
+            }
+            LuaValue::Array(v) => v.iter_mut().for_each(|v| v.convert_maps(backend)),
+            _ => {}
+        }
+    }
+}
+
+impl Default for LuaMap {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for LuaMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(LuaMapKey, LuaValue)> for LuaMap {
+    /// Collects entries into a map backed by a `BTreeMap`.
+    fn from_iter<T: IntoIterator<Item = (LuaMapKey, LuaValue)>>(iter: T) -> Self {
+        Self(Inner::BTreeMap(BTreeMap::from_iter(iter)))
+    }
+}
+
+impl Extend<(LuaMapKey, LuaValue)> for LuaMap {
+    fn extend<T: IntoIterator<Item = (LuaMapKey, LuaValue)>>(&mut self, iter: T) {
+        dispatch!(&mut self.0, m => m.extend(iter))
+    }
+}
+
+#[derive(Clone)]
+enum IterInner<'a> {
+    BTreeMap(btree_map::Iter<'a, LuaMapKey, LuaValue>),
+    #[cfg(feature = "fnv")]
+    Fnv(hash_map::Iter<'a, LuaMapKey, LuaValue>),
+    #[cfg(feature = "indexmap")]
+    IndexMap(indexmap::map::Iter<'a, LuaMapKey, LuaValue>),
+}
+
+/// An iterator over the entries of a [LuaMap].
+#[derive(Clone)]
+pub struct Iter<'a>(IterInner<'a>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a LuaMapKey, &'a LuaValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterInner::BTreeMap(i) => i.next(),
+            #[cfg(feature = "fnv")]
+            IterInner::Fnv(i) => i.next(),
+            #[cfg(feature = "indexmap")]
+            IterInner::IndexMap(i) => i.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IterInner::BTreeMap(i) => i.size_hint(),
+            #[cfg(feature = "fnv")]
+            IterInner::Fnv(i) => i.size_hint(),
+            #[cfg(feature = "indexmap")]
+            IterInner::IndexMap(i) => i.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+enum IterMutInner<'a> {
+    BTreeMap(btree_map::IterMut<'a, LuaMapKey, LuaValue>),
+    #[cfg(feature = "fnv")]
+    Fnv(hash_map::IterMut<'a, LuaMapKey, LuaValue>),
+    #[cfg(feature = "indexmap")]
+    IndexMap(indexmap::map::IterMut<'a, LuaMapKey, LuaValue>),
+}
+
+/// A mutable iterator over the entries of a [LuaMap].
+pub struct IterMut<'a>(IterMutInner<'a>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a LuaMapKey, &'a mut LuaValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterMutInner::BTreeMap(i) => i.next(),
+            #[cfg(feature = "fnv")]
+            IterMutInner::Fnv(i) => i.next(),
+            #[cfg(feature = "indexmap")]
+            IterMutInner::IndexMap(i) => i.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IterMutInner::BTreeMap(i) => i.size_hint(),
+            #[cfg(feature = "fnv")]
+            IterMutInner::Fnv(i) => i.size_hint(),
+            #[cfg(feature = "indexmap")]
+            IterMutInner::IndexMap(i) => i.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+enum IntoIterInner {
+    BTreeMap(btree_map::IntoIter<LuaMapKey, LuaValue>),
+    #[cfg(feature = "fnv")]
+    Fnv(hash_map::IntoIter<LuaMapKey, LuaValue>),
+    #[cfg(feature = "indexmap")]
+    IndexMap(indexmap::map::IntoIter<LuaMapKey, LuaValue>),
+}
+
+/// An owning iterator over the entries of a [LuaMap].
+pub struct IntoIter(IntoIterInner);
+
+impl Iterator for IntoIter {
+    type Item = (LuaMapKey, LuaValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IntoIterInner::BTreeMap(i) => i.next(),
+            #[cfg(feature = "fnv")]
+            IntoIterInner::Fnv(i) => i.next(),
+            #[cfg(feature = "indexmap")]
+            IntoIterInner::IndexMap(i) => i.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IntoIterInner::BTreeMap(i) => i.size_hint(),
+            #[cfg(feature = "fnv")]
+            IntoIterInner::Fnv(i) => i.size_hint(),
+            #[cfg(feature = "indexmap")]
+            IntoIterInner::IndexMap(i) => i.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl IntoIterator for LuaMap {
+    type Item = (LuaMapKey, LuaValue);
+    type IntoIter = IntoIter;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter {
+        IntoIter(dispatch!(self.0, m => m.into_iter(), IntoIterInner))
+    }
+}
+
+impl<'a> IntoIterator for &'a LuaMap {
+    type Item = (&'a LuaMapKey, &'a LuaValue);
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut LuaMap {
+    type Item = (&'a LuaMapKey, &'a mut LuaValue);
+    type IntoIter = IterMut<'a>;
+
+    #[inline]
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for LuaMap {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary_iter()?.collect()
+    }
+
+    fn arbitrary_take_rest(u: arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary_take_rest_iter()?.collect()
+    }
+
+    #[inline]
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+/// A policy for serializing NaNs or infinities.
+///
+/// Serializers accept separate policies for NaNs and for infinities.
+/// A value replaced with `nil` is handled like [LuaValue::Null](crate::LuaValue::Null):
+/// it becomes a hole in an array, and a table entry whose key or value is replaced is left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NonFinitePolicy {
+    /// Fail serialization.
+    Error,
+    /// Write `nil` instead.
+    ReplaceWithNil,
+    /// Write `0` instead.
+    ReplaceWithZero,
+    /// Write the value the way the Lua library does.
+    ///
+    /// LibSerialize writes every NaN as a positive quiet NaN. AceSerialize writes
+    /// NaNs the way `string.format("%.0f")` prints them, i.e. as `nan` or `-nan`.
+    Write,
+    /// Like [NonFinitePolicy::Write], but keep the bit patterns of NaNs where the format allows that.
+    ///
+    /// LibSerialize's format stores the bits as they are, AceSerialize's format only keeps the sign.
+    PreserveBits,
+}
+
+/// The non-finite numbers a deserializer has produced.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::NonFiniteReport;
+///
+/// let mut report = NonFiniteReport::default();
+/// for n in [1.0, f64::NAN, f64::INFINITY, -f64::NAN] {
+///     report.record(n);
+/// }
+///
+/// assert_eq!(report.nan(), 2);
+/// assert_eq!(report.positive_infinity(), 1);
+/// assert_eq!(report.negative_infinity(), 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NonFiniteReport {
+    nan: usize,
+    positive_infinity: usize,
+    negative_infinity: usize,
+}
+
+impl NonFiniteReport {
+    /// Account for `value` if it is not finite.
+    #[inline(always)]
+    pub fn record(&mut self, value: f64) {
+        if !value.is_finite() {
+            self.record_non_finite(value);
+        }
+    }
+
+    #[cold]
+    fn record_non_finite(&mut self, value: f64) {
+        if value.is_nan() {
+            self.nan += 1;
+        } else if value > 0.0 {
+            self.positive_infinity += 1;
+        } else {
+            self.negative_infinity += 1;
+        }
+    }
+
+    /// The amount of NaNs.
+    pub fn nan(&self) -> usize {
+        self.nan
+    }
+
+    /// The amount of positive infinities.
+    pub fn positive_infinity(&self) -> usize {
+        self.positive_infinity
+    }
+
+    /// The amount of negative infinities.
+    pub fn negative_infinity(&self) -> usize {
+        self.negative_infinity
+    }
+
+    /// Returns `true` if no non-finite numbers have been recorded.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{LuaMap, LuaMapKey, LuaValue};
+
+/// A policy for converting tables between [LuaValue::Array] and [LuaValue::Map].
+///
+/// Lua has a single table type, so the same table can be represented
+/// either way. The rules are:
+///
+/// * a map is converted into an array if all of its keys are positive integers
+///   and more than half of the slots `1..=n` are occupied, where `n` is the largest key.
+///   Missing keys become holes. This mirrors how Lua sizes the array part of a table;
+/// * an array is converted into a map with keys `1..=n`, where `n` is the length of the array,
+///   holes are skipped;
+/// * map entries with [LuaValue::Null] values and trailing holes of arrays are removed;
+/// * an empty table is an array if arrays are preferred and a map otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NormalizationPolicy {
+    /// Keep tables in the shape they were produced in.
+    #[default]
+    Preserve,
+    /// Convert maps with positive integer keys into arrays.
+    PreferArrays,
+    /// Convert arrays into maps.
+    PreferMaps,
+}
+
+impl LuaValue {
+    /// Recursively convert tables according to `policy`.
+    ///
+    /// Map keys are left untouched, only values are normalized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lua_value::{LuaMap, LuaMapKey, LuaValue, NormalizationPolicy};
+    ///
+    /// let mut value = LuaValue::Map(LuaMap::from_iter([
+    ///     (LuaMapKey::try_from(LuaValue::from(2.0)).unwrap(), "b".into()),
+    ///     (LuaMapKey::try_from(LuaValue::from(1.0)).unwrap(), "a".into()),
+    /// ]));
+    ///
+    /// value.normalize(NormalizationPolicy::PreferArrays);
+    /// assert!(matches!(value, LuaValue::Array(ref v) if *v == [LuaValue::from("a"), LuaValue::from("b")]));
+    ///
+    /// value.normalize(NormalizationPolicy::PreferMaps);
+    /// assert!(matches!(value, LuaValue::Map(ref m) if m.len() == 2));
+    /// ```
+    pub fn normalize(&mut self, policy: NormalizationPolicy) {
+        if policy == NormalizationPolicy::Preserve {
+            return;
+        }
+
+        match self {
+            LuaValue::Map(m) => m.values_mut().for_each(|v| v.normalize(policy)),
+            LuaValue::Array(v) => v.iter_mut().for_each(|v| v.normalize(policy)),
+            _ => return,
+        }
+
+        self.normalize_shallow(policy);
+    }
+
+    /// Convert the table itself according to `policy` without descending into its values.
+    ///
+    /// Does nothing if `self` is not a table.
+    pub fn normalize_shallow(&mut self, policy: NormalizationPolicy) {
+        match (policy, &mut *self) {
+            (NormalizationPolicy::Preserve, _) => {}
+            (NormalizationPolicy::PreferArrays, LuaValue::Map(m)) => {
+                let Some(len) = array_len(m) else {
+                    m.retain(|_, v| !v.is_null());
+                    return;
+                };
+
+                let LuaValue::Map(m) = core::mem::replace(self, LuaValue::Null) else {
+                    unreachable!()
+                };
+
+                let mut v = vec![LuaValue::Null; len];
+                for (key, value) in m.into_iter().filter(|(_, v)| !v.is_null()) {
+                    if let LuaValue::Number(n) = key.0 {
+                        v[n as usize - 1] = value;
+                    }
+                }
+
+                *self = LuaValue::Array(v);
+            }
+            (NormalizationPolicy::PreferArrays, LuaValue::Array(v)) => trim_holes(v),
+            (NormalizationPolicy::PreferMaps, LuaValue::Map(m)) => m.retain(|_, v| !v.is_null()),
+            (NormalizationPolicy::PreferMaps, LuaValue::Array(_)) => {
+                let LuaValue::Array(v) = core::mem::replace(self, LuaValue::Null) else {
+                    unreachable!()
+                };
+
+                *self = LuaValue::Map(
+                    v.into_iter()
+                        .zip(1..)
+                        .filter(|(v, _)| !v.is_null())
+                        .map(|(v, i)| (LuaMapKey(LuaValue::Number(i as f64)), v))
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns a border of a table, i.e. a value that Lua's length operator `#` may return,
+    /// or the length of a string in bytes. Returns `None` for other types.
+    ///
+    /// A border is any non-negative integer `n` such that `t[n + 1]` is `nil`
+    /// and either `n` is zero or `t[n]` is not `nil`. A table can have several borders,
+    /// in which case Lua may return any of them. For arrays, this method returns
+    /// the position of the last element that is not a hole, which is what Lua
+    /// returns for a table with such an array part. For maps, it returns the smallest border.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec_lua_value::{LuaMap, LuaMapKey, LuaValue};
+    ///
+    /// let key = |n: f64| LuaMapKey::try_from(LuaValue::from(n)).unwrap();
+    ///
+    /// // {1, 2, [4] = 4} has borders 2 and 4.
+    /// let value = LuaValue::Map(LuaMap::from_iter([
+    ///     (key(1.0), 1.0.into()),
+    ///     (key(2.0), 2.0.into()),
+    ///     (key(4.0), 4.0.into()),
+    /// ]));
+    /// assert_eq!(value.border(), Some(2));
+    ///
+    /// assert_eq!(LuaValue::Array(vec![true.into(); 3]).border(), Some(3));
+    /// // {1, nil, 3, nil} has borders 1 and 3.
+    /// let value = LuaValue::Array(vec![1.0.into(), LuaValue::Null, 3.0.into(), LuaValue::Null]);
+    /// assert_eq!(value.border(), Some(3));
+    /// assert_eq!(LuaValue::from("abc").border(), Some(3));
+    /// assert_eq!(LuaValue::from(1.0).border(), None);
+    /// ```
+    pub fn border(&self) -> Option<usize> {
+        match self {
+            LuaValue::Array(v) => Some(v.iter().rposition(|v| !v.is_null()).map_or(0, |i| i + 1)),
+            LuaValue::Map(m) => {
+                let mut n = 0;
+                while m
+                    .get(&LuaMapKey(LuaValue::Number((n + 1) as f64)))
+                    .is_some_and(|v| !v.is_null())
+                {
+                    n += 1;
+                }
+                Some(n)
+            }
+            LuaValue::String(s) => Some(s.len()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the length of an array that can hold the map
+/// if it qualifies for conversion into one.
+fn array_len(m: &LuaMap) -> Option<usize> {
+    let mut count = 0usize;
+    let mut max = 0usize;
+
+    for (key, _) in m.iter().filter(|(_, v)| !v.is_null()) {
+        match key.0 {
+            LuaValue::Number(n) if n >= 1.0 && n.fract() == 0.0 && n <= usize::MAX as f64 => {
+                count += 1;
+                max = max.max(n as usize);
+            }
+            _ => return None,
+        }
+    }
+
+    (count > max / 2 || max == 0).then_some(max)
+}
+
+fn trim_holes(v: &mut Vec<LuaValue>) {
+    let len = v.iter().rposition(|v| !v.is_null()).map_or(0, |i| i + 1);
+    v.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(n: f64) -> LuaMapKey {
+        LuaMapKey(LuaValue::Number(n))
+    }
+
+    fn map<const N: usize>(entries: [(LuaMapKey, LuaValue); N]) -> LuaValue {
+        LuaValue::Map(entries.into_iter().collect())
+    }
+
+    #[test]
+    fn border_of_a_sequence_is_its_length() {
+        assert_eq!(LuaValue::Array(Vec::new()).border(), Some(0));
+        assert_eq!(map([]).border(), Some(0));
+        assert_eq!(
+            map([(key(1.0), true.into()), (key(2.0), false.into())]).border(),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn border_ignores_non_sequence_keys() {
+        let value = map([
+            (key(0.0), true.into()),
+            (key(1.0), true.into()),
+            (key(1.5), true.into()),
+            (LuaMapKey("2".into()), true.into()),
+        ]);
+        assert_eq!(value.border(), Some(1));
+    }
+
+    #[test]
+    fn border_treats_nil_values_as_absent() {
+        let value = map([
+            (key(1.0), true.into()),
+            (key(2.0), LuaValue::Null),
+            (key(3.0), true.into()),
+        ]);
+        assert_eq!(value.border(), Some(1));
+        assert_eq!(map([(key(2.0), true.into())]).border(), Some(0));
+    }
+
+    #[test]
+    fn prefer_arrays_requires_positive_integer_keys() {
+        let mut value = map([(key(1.0), 1.0.into()), (key(2.5), 3.0.into())]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Map(_)));
+
+        let mut value = map([(key(1.0), 1.0.into()), (LuaMapKey("x".into()), 1.0.into())]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Map(_)));
+
+        let mut value = map([]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Array(ref v) if v.is_empty()));
+    }
+
+    #[test]
+    fn prefer_arrays_fills_holes_with_nulls() {
+        let mut value = map([(key(1.0), 1.0.into()), (key(3.0), 3.0.into())]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        let LuaValue::Array(ref v) = value else {
+            panic!("expected an array");
+        };
+        assert_eq!(
+            *v,
+            [LuaValue::from(1.0), LuaValue::Null, LuaValue::from(3.0)]
+        );
+    }
+
+    #[test]
+    fn prefer_arrays_keeps_sparse_maps() {
+        // Only one slot out of 1..=4 is occupied.
+        let mut value = map([(key(4.0), 4.0.into())]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Map(ref m) if m.len() == 1));
+
+        // Exactly half of the slots is not enough.
+        let mut value = map([(key(1.0), 1.0.into()), (key(4.0), 4.0.into())]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Map(ref m) if m.len() == 2));
+    }
+
+    #[test]
+    fn null_values_are_absent_entries() {
+        let mut value = map([
+            (key(1.0), 1.0.into()),
+            (key(2.0), LuaValue::Null),
+            (LuaMapKey("x".into()), LuaValue::Null),
+        ]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Array(ref v) if *v == [LuaValue::from(1.0)]));
+
+        let mut value = LuaValue::Array(vec![LuaValue::Null, 2.0.into(), LuaValue::Null]);
+        value.normalize(NormalizationPolicy::PreferArrays);
+        assert!(matches!(value, LuaValue::Array(ref v) if v.len() == 2));
+
+        value.normalize(NormalizationPolicy::PreferMaps);
+        let LuaValue::Map(ref m) = value else {
+            panic!("expected a map");
+        };
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.get(&key(2.0)), Some(&LuaValue::from(2.0)));
+    }
+
+    #[test]
+    fn border_of_an_array_skips_trailing_holes() {
+        let value = LuaValue::Array(vec![1.0.into(), LuaValue::Null, LuaValue::Null]);
+        assert_eq!(value.border(), Some(1));
+        assert_eq!(LuaValue::Array(vec![LuaValue::Null]).border(), Some(0));
+    }
+
+    #[test]
+    fn normalization_is_recursive() {
+        let mut value = LuaValue::Array(vec![
+            map([(key(1.0), "a".into())]),
+            LuaValue::Array(vec!["b".into()]),
+        ]);
+
+        value.normalize(NormalizationPolicy::PreferArrays);
+        let LuaValue::Array(ref v) = value else {
+            panic!("expected an array");
+        };
+        assert!(v.iter().all(|v| matches!(v, LuaValue::Array(_))));
+
+        value.normalize(NormalizationPolicy::PreferMaps);
+        let LuaValue::Map(ref m) = value else {
+            panic!("expected a map");
+        };
+        assert!(m.values().all(|v| matches!(v, LuaValue::Map(_))));
+    }
+}
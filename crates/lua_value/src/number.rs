@@ -0,0 +1,83 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use core::fmt::Write;
+
+/// Formats a finite `value` the way Lua 5.1's `tostring` does, i.e. using `%.14g`,
+/// replacing the contents of `out`.
+///
+/// Both serializers use this to write numbers that are not integers as strings.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::format_g14;
+///
+/// let mut s = String::new();
+/// format_g14(1.0 / 3.0, &mut s);
+/// assert_eq!(s, "0.33333333333333");
+///
+/// format_g14(1.5e-7, &mut s);
+/// assert_eq!(s, "1.5e-07");
+/// ```
+pub fn format_g14(value: f64, out: &mut String) {
+    const PRECISION: i32 = 14;
+
+    out.clear();
+    // The exponent must be taken after rounding to the requested precision.
+    write!(out, "{:.*e}", (PRECISION - 1) as usize, value).unwrap();
+    let exp_at = out.find('e').unwrap();
+    let exp: i32 = out[exp_at + 1..].parse().unwrap();
+
+    if !(-4..PRECISION).contains(&exp) {
+        out.truncate(exp_at);
+        strip_trailing_zeros(out);
+        write!(
+            out,
+            "e{}{:02}",
+            if exp < 0 { '-' } else { '+' },
+            exp.unsigned_abs()
+        )
+        .unwrap();
+    } else {
+        out.clear();
+        write!(out, "{:.*}", (PRECISION - 1 - exp) as usize, value).unwrap();
+        strip_trailing_zeros(out);
+    }
+}
+
+fn strip_trailing_zeros(s: &mut String) {
+    if s.contains('.') {
+        let len = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn g14(value: f64) -> String {
+        let mut s = String::new();
+        format_g14(value, &mut s);
+        s
+    }
+
+    #[test]
+    fn matches_printf() {
+        assert_eq!(g14(0.0), "0");
+        assert_eq!(g14(-0.0), "-0");
+        assert_eq!(g14(0.5), "0.5");
+        assert_eq!(g14(-1.25), "-1.25");
+        assert_eq!(g14(0.1), "0.1");
+        assert_eq!(g14(1.0 / 3.0), "0.33333333333333");
+        assert_eq!(g14(0.0001), "0.0001");
+        assert_eq!(g14(0.00001), "1e-05");
+        assert_eq!(g14(1.5e-7), "1.5e-07");
+        assert_eq!(g14(12345678901234.5), "12345678901234");
+        assert_eq!(g14(123456789012345.0), "1.2345678901234e+14");
+        assert_eq!(g14(99999999999999.99), "1e+14");
+        assert_eq!(g14(1e100), "1e+100");
+        assert_eq!(g14(f64::MIN_POSITIVE), "2.2250738585072e-308");
+    }
+}
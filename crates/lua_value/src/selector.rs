@@ -0,0 +1,220 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::LuaString;
+
+/// A segment of a path added to a [Selector].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// An entry with a string key.
+    Key(LuaString),
+    /// An entry with a number key, including elements of arrays.
+    Index(f64),
+    /// Any entry.
+    Any,
+}
+
+impl From<&str> for PathSegment {
+    fn from(value: &str) -> Self {
+        Self::Key(value.into())
+    }
+}
+
+impl From<&[u8]> for PathSegment {
+    fn from(value: &[u8]) -> Self {
+        Self::Key(value.into())
+    }
+}
+
+impl From<LuaString> for PathSegment {
+    fn from(value: LuaString) -> Self {
+        Self::Key(value)
+    }
+}
+
+impl From<f64> for PathSegment {
+    fn from(value: f64) -> Self {
+        Self::Index(value)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(value: usize) -> Self {
+        Self::Index(value as f64)
+    }
+}
+
+/// A set of paths to the parts of a value that should be deserialized.
+///
+/// Deserializers use selectors to skip everything else without building it.
+/// Selecting from a value produces:
+///
+/// * the whole value if it is at the end of a path;
+/// * a table with the selected parts of the entries that are on a path, if the value is a table.
+///   Entries whose values have nothing selected are left out;
+/// * nothing, i.e. `nil`, otherwise.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::{PathSegment, Selector};
+///
+/// // d.id, d.uid and c[*].id
+/// let selector = Selector::new()
+///     .with_path(["d", "id"])
+///     .with_path(["d", "uid"])
+///     .with_path([PathSegment::from("c"), PathSegment::Any, PathSegment::from("id")]);
+///
+/// let d = selector.string_child(b"d").unwrap();
+/// assert!(d.string_child(b"id").unwrap().selects_all());
+/// assert!(d.string_child(b"regionType").is_none());
+///
+/// let element = selector.string_child(b"c").unwrap().number_child(3.0).unwrap();
+/// assert!(element.string_child(b"id").is_some());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector {
+    all: bool,
+    /// Children for [PathSegment::Any] are merged into the other children,
+    /// so that a key never has to be looked up more than once.
+    children: Vec<(PathSegment, Selector)>,
+}
+
+impl Selector {
+    /// Create a selector that selects nothing.
+    pub const fn new() -> Self {
+        Self {
+            all: false,
+            children: Vec::new(),
+        }
+    }
+
+    /// Create a selector that selects values entirely.
+    pub const fn all() -> Self {
+        Self {
+            all: true,
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a path to the selection. An empty path selects values entirely.
+    pub fn with_path<I>(mut self, path: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<PathSegment>,
+    {
+        let path: Vec<PathSegment> = path.into_iter().map(Into::into).collect();
+        self.insert(&path);
+        self
+    }
+
+    /// Whether values are selected entirely.
+    #[inline(always)]
+    pub fn selects_all(&self) -> bool {
+        self.all
+    }
+
+    /// Get the selection within the value of an entry with a string key.
+    pub fn string_child(&self, key: &[u8]) -> Option<&Selector> {
+        self.child(|segment| matches!(segment, PathSegment::Key(k) if k.as_bytes() == key))
+    }
+
+    /// Get the selection within the value of an entry with a number key.
+    pub fn number_child(&self, key: f64) -> Option<&Selector> {
+        self.child(|segment| matches!(segment, PathSegment::Index(k) if *k == key))
+    }
+
+    /// Get the selection within the value of an entry with a key
+    /// that is neither a string nor a number.
+    pub fn any_child(&self) -> Option<&Selector> {
+        self.child(|segment| matches!(segment, PathSegment::Any))
+    }
+
+    fn child(&self, matches: impl Fn(&PathSegment) -> bool) -> Option<&Selector> {
+        if self.all {
+            return Some(self);
+        }
+
+        self.children
+            .iter()
+            .find(|(segment, _)| matches(segment))
+            .or_else(|| {
+                self.children
+                    .iter()
+                    .find(|(segment, _)| *segment == PathSegment::Any)
+            })
+            .map(|(_, child)| child)
+    }
+
+    fn insert(&mut self, path: &[PathSegment]) {
+        if self.all {
+            return;
+        }
+
+        let Some((segment, rest)) = path.split_first() else {
+            self.all = true;
+            self.children.clear();
+            return;
+        };
+
+        if *segment == PathSegment::Any {
+            for (_, child) in &mut self.children {
+                child.insert(rest);
+            }
+        }
+
+        match self.children.iter_mut().find(|(s, _)| s == segment) {
+            Some((_, child)) if *segment != PathSegment::Any => child.insert(rest),
+            Some(_) => {}
+            None => {
+                let mut child = self
+                    .children
+                    .iter()
+                    .find(|(s, _)| *s == PathSegment::Any)
+                    .map_or_else(Selector::new, |(_, any)| any.clone());
+                child.insert(rest);
+                self.children.push((segment.clone(), child));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_applies_to_all_keys() {
+        let before = Selector::new()
+            .with_path([PathSegment::Any, "id".into()])
+            .with_path([PathSegment::from(1), "uid".into()]);
+        let after = Selector::new()
+            .with_path([PathSegment::from(1), "uid".into()])
+            .with_path([PathSegment::Any, "id".into()]);
+
+        for selector in [before, after] {
+            let first = selector.number_child(1.0).unwrap();
+            assert!(first.string_child(b"id").is_some());
+            assert!(first.string_child(b"uid").is_some());
+
+            let second = selector.number_child(2.0).unwrap();
+            assert!(second.string_child(b"id").is_some());
+            assert!(second.string_child(b"uid").is_none());
+            assert!(selector.any_child().is_some());
+        }
+    }
+
+    #[test]
+    fn shorter_paths_select_more() {
+        let selector = Selector::new()
+            .with_path(["d", "id"])
+            .with_path(["d"])
+            .with_path(["d", "uid"]);
+        let d = selector.string_child(b"d").unwrap();
+
+        assert!(d.selects_all());
+        assert!(d.string_child(b"regionType").unwrap().selects_all());
+        assert!(selector.string_child(b"c").is_none());
+        assert!(Selector::new().with_path::<[&str; 0]>([]).selects_all());
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use core::{
+    borrow::Borrow,
+    fmt::{self, Debug, Display, Write},
+    ops::Deref,
+    str::Utf8Error,
+};
+use std::borrow::Cow;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
+
+/// A Lua string.
+///
+/// Lua strings are sequences of bytes that are not required to be valid UTF-8,
+/// so this type stores bytes and provides conversions to Rust strings.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec_lua_value::LuaString;
+///
+/// let valid = LuaString::from("Hello, world!");
+/// assert_eq!(valid.to_str(), Ok("Hello, world!"));
+///
+/// let invalid = LuaString::from(b"Interface\\\xff".as_slice());
+/// assert!(invalid.to_str().is_err());
+/// assert_eq!(invalid.as_bytes(), b"Interface\\\xff");
+/// assert_eq!(invalid.to_string_lossy(), "Interface\\\u{FFFD}");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub struct LuaString(Vec<u8>);
+
+impl LuaString {
+    /// Create an empty string.
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Get the contents of the string as a slice of bytes.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Convert the string into a vector of bytes.
+    #[inline(always)]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Get the contents of the string as `&str` if they are valid UTF-8.
+    #[inline(always)]
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        core::str::from_utf8(&self.0)
+    }
+
+    /// Get the contents of the string as `&str`,
+    /// replacing invalid UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    #[inline(always)]
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    /// Convert the string into a [String] if it is valid UTF-8.
+    /// Otherwise, the string is returned unchanged.
+    pub fn into_string(self) -> Result<String, Self> {
+        String::from_utf8(self.0).map_err(|e| Self(e.into_bytes()))
+    }
+}
+
+impl Deref for LuaString {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for LuaString {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Borrow<[u8]> for LuaString {
+    #[inline(always)]
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<String> for LuaString {
+    #[inline(always)]
+    fn from(value: String) -> Self {
+        Self(value.into_bytes())
+    }
+}
+
+impl From<&str> for LuaString {
+    #[inline(always)]
+    fn from(value: &str) -> Self {
+        Self(value.as_bytes().to_vec())
+    }
+}
+
+impl From<Vec<u8>> for LuaString {
+    #[inline(always)]
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&[u8]> for LuaString {
+    #[inline(always)]
+    fn from(value: &[u8]) -> Self {
+        Self(value.to_vec())
+    }
+}
+
+impl From<LuaString> for Vec<u8> {
+    #[inline(always)]
+    fn from(value: LuaString) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq<str> for LuaString {
+    #[inline(always)]
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for LuaString {
+    #[inline(always)]
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<[u8]> for LuaString {
+    #[inline(always)]
+    fn eq(&self, other: &[u8]) -> bool {
+        self.0 == other
+    }
+}
+
+impl Debug for LuaString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_bytes(&self.0, f)
+    }
+}
+
+/// Formats bytes as a string literal, escaping invalid UTF-8 as `\xNN`.
+pub(crate) fn debug_bytes(bytes: &[u8], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            Display::fmt(&c.escape_debug(), f)?;
+        }
+        for byte in chunk.invalid() {
+            write!(f, "\\x{:02x}", byte)?;
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for LuaString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.to_string_lossy(), f)
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{LuaMap, LuaMapKey, LuaValue};
+use std::borrow::Cow;
+
+/// A counterpart of [LuaValue] that borrows strings from the data it was produced from.
+///
+/// Deserializers borrow strings that are stored in their input as they are
+/// and only allocate the ones that have to be decoded, which makes this type suitable
+/// for read-only analysis of large inputs. Call [LuaValueRef::to_owned] to get a [LuaValue].
+///
+/// Tables are stored as vectors. [LuaValueRef::Map] stores entries in the order they were
+/// produced in, keys are never [LuaValueRef::Null] and values of deserialized maps
+/// are never [LuaValueRef::Null] either. Should a key occur more than once,
+/// the last entry wins when converting into [LuaValue].
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+/// use weakauras_codec_lua_value::{LuaValue, LuaValueRef};
+///
+/// let value = LuaValue::Array(vec!["a".into(), 1.0.into()]);
+/// let borrowed = LuaValueRef::from(&value);
+/// assert!(matches!(
+///     borrowed,
+///     LuaValueRef::Array(ref v) if matches!(v[0], LuaValueRef::String(Cow::Borrowed(b"a")))
+/// ));
+///
+/// let LuaValue::Array(v) = borrowed.to_owned() else {
+///     unreachable!()
+/// };
+/// assert_eq!(v, [LuaValue::from("a"), LuaValue::from(1.0)]);
+/// ```
+#[allow(missing_docs)] // Variants are self-explanatory.
+#[derive(Clone, Debug)]
+pub enum LuaValueRef<'a> {
+    Map(Vec<(LuaValueRef<'a>, LuaValueRef<'a>)>),
+    Array(Vec<LuaValueRef<'a>>),
+    String(Cow<'a, [u8]>),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+impl LuaValueRef<'_> {
+    /// Returns `true` if the value is [LuaValueRef::Null].
+    #[inline(always)]
+    pub fn is_null(&self) -> bool {
+        matches!(self, LuaValueRef::Null)
+    }
+
+    /// Convert into a [LuaValue], copying all strings.
+    #[allow(clippy::wrong_self_convention)]
+    #[inline(always)]
+    pub fn to_owned(&self) -> LuaValue {
+        LuaValue::from(self)
+    }
+}
+
+impl From<&LuaValueRef<'_>> for LuaValue {
+    fn from(value: &LuaValueRef<'_>) -> Self {
+        match value {
+            LuaValueRef::Map(entries) => {
+                let mut m = LuaMap::new();
+                for (key, value) in entries {
+                    if let Ok(key) = LuaMapKey::try_from(LuaValue::from(key)) {
+                        m.insert(key, value.into());
+                    }
+                }
+                LuaValue::Map(m)
+            }
+            LuaValueRef::Array(v) => LuaValue::Array(v.iter().map(Into::into).collect()),
+            LuaValueRef::String(s) => LuaValue::String(s.as_ref().into()),
+            LuaValueRef::Number(n) => LuaValue::Number(*n),
+            LuaValueRef::Boolean(b) => LuaValue::Boolean(*b),
+            LuaValueRef::Null => LuaValue::Null,
+        }
+    }
+}
+
+impl From<LuaValueRef<'_>> for LuaValue {
+    #[inline(always)]
+    fn from(value: LuaValueRef<'_>) -> Self {
+        LuaValue::from(&value)
+    }
+}
+
+impl<'a> From<&'a LuaValue> for LuaValueRef<'a> {
+    fn from(value: &'a LuaValue) -> Self {
+        match value {
+            LuaValue::Map(m) => LuaValueRef::Map(
+                m.iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k.as_value().into(), v.into()))
+                    .collect(),
+            ),
+            LuaValue::Array(v) => LuaValueRef::Array(v.iter().map(Into::into).collect()),
+            LuaValue::String(s) => LuaValueRef::String(Cow::Borrowed(s.as_bytes())),
+            LuaValue::Number(n) => LuaValueRef::Number(*n),
+            LuaValue::Boolean(b) => LuaValueRef::Boolean(*b),
+            LuaValue::Null => LuaValueRef::Null,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for LuaValueRef<'a> {
+    #[inline(always)]
+    fn from(value: &'a str) -> Self {
+        Self::String(Cow::Borrowed(value.as_bytes()))
+    }
+}
+
+impl From<f64> for LuaValueRef<'_> {
+    #[inline(always)]
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<bool> for LuaValueRef<'_> {
+    #[inline(always)]
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
@@ -0,0 +1,96 @@
+// Heavily inspired by a macro from memchr by BurntSushi
+// https://github.com/BurntSushi/memchr
+// Copyright 2018-2023 Andrew Gallant
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+//! This library is used by the other crates of weakauras-codec to choose
+//! SIMD implementations based on CPU features available at runtime.
+//! It is not meant to be used directly, no guarantees about following semver there.
+//!
+//! # Crate features
+//!
+//! * **std** - Enable runtime SIMD feature detection on x86_64 and x86 CPUs. Without it, the fallback implementation is always used. **Disabled** by default.
+
+#![no_std]
+#![deny(missing_docs)]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+#[doc(hidden)]
+pub use std::is_x86_feature_detected;
+
+/// Macro for generating the body of a function that chooses
+/// an implementation during runtime, based on available CPU features.
+///
+/// The arguments of the function are passed along with their types,
+/// e.g. `(input: &[u8], output: &mut [MaybeUninit<u8>])`.
+///
+/// Feature detection is run only on the first call.
+/// This is achieved by using an [AtomicPtr](core::sync::atomic::AtomicPtr)
+/// to store the address of a specific implementation.
+/// Without the `std` feature, the fallback implementation is always used.
+/// The feature is enabled for this crate rather than for the caller,
+/// which therefore does not need the standard library itself.
+///
+/// # Safety
+///
+/// * It must be used only on architectures where it is safe to transmute between
+///   data pointers and function pointers, such as `x86_64`.
+/// * The implementation of `$feature_module::$fn` must be safe to call on machines
+///   where `$feature_name` is present.
+/// * The implementation of `$fallback_module::$fn` must be safe to call on machines
+///   where none of the provided features is present.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! unsafe_runtime_dispatch {
+    (
+        $fn:ident,
+        $returnty:ty,
+        ($($arg:ident: $argty:ty),+ $(,)?),
+        $is_feature_detected:ident,
+        $(($feature_name:tt, $feature_module:ident)),+,
+        $fallback_module:ident,
+    ) => {{
+        use ::core::sync::atomic::{AtomicPtr, Ordering};
+
+        type FnType = unsafe fn($($argty),+) -> $returnty;
+        static FN_PTR: AtomicPtr<()> = AtomicPtr::new(init as *mut ());
+
+        unsafe fn init($($arg: $argty),+) -> $returnty {
+            let f = $(if $crate::$is_feature_detected!($feature_name) {
+                $feature_module::$fn as FnType
+            } else)+ {
+                $fallback_module::$fn as FnType
+            };
+
+            FN_PTR.store(f as *mut (), Ordering::Relaxed);
+            // SAFETY: We've chosen an implementation based on available CPU features.
+            unsafe { f($($arg),+) }
+        }
+
+        let f = FN_PTR.load(Ordering::Relaxed);
+        // SAFETY: According to the safety contract, this is used on an architecture
+        // where transmuting between data pointers and function pointers is safe.
+        unsafe { ::core::mem::transmute::<*mut (), FnType>(f)($($arg),+) }
+    }}
+}
+
+/// Refer to the definition with the `std` feature for documentation.
+#[cfg(not(feature = "std"))]
+#[macro_export]
+macro_rules! unsafe_runtime_dispatch {
+    (
+        $fn:ident,
+        $returnty:ty,
+        ($($arg:ident: $argty:ty),+ $(,)?),
+        $is_feature_detected:ident,
+        $(($feature_name:tt, $feature_module:ident)),+,
+        $fallback_module:ident,
+    ) => {{
+        // SAFETY: The fallback implementation is safe to call on any machine.
+        unsafe { $fallback_module::$fn($($arg),+) }
+    }}
+}
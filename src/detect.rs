@@ -0,0 +1,615 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{DEFAULT_MAX_SIZE, DecodeErrorKind, base64_len, inflate, sanitize};
+use std::fmt;
+use weakauras_codec_ace_serialize::{
+    DeserializationErrorKind as AceSerializeErrorKind, Deserializer as LegacyDeserializer,
+};
+use weakauras_codec_base64::error::DecodeError as Base64DecodeError;
+use weakauras_codec_lib_serialize::{DeserializationErrorKind, Deserializer};
+
+/// Prefixes of strings exported by other addons, and the names of those addons.
+const ADDON_PREFIXES: [(&str, &str); 2] = [("!PLATER:", "Plater"), ("!E1!", "ElvUI")];
+
+/// A format recognized by [detect].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// `!WA:2!` + base64-string of DEFLATE-compressed data serialized by LibSerialize.
+    BinarySerialization,
+    /// `!` + base64-string of DEFLATE-compressed data serialized by AceSerialize.
+    Deflate,
+    /// A base64-string of data compressed by LibCompress and serialized by AceSerialize.
+    Legacy,
+    /// A string exported by another addon.
+    OtherAddon,
+    /// A Lua table constructor, e.g. copied from SavedVariables.
+    LuaTable,
+    /// JSON.
+    Json,
+    /// A WeakAuras-compatible string that ends too early.
+    Truncated,
+    /// A WeakAuras-compatible string that is damaged.
+    Damaged,
+    /// Anything else.
+    Unknown,
+}
+
+/// How sure [detect] is about the [Format].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The format is a guess.
+    Low,
+    /// The input looks like the format, but was not checked thoroughly.
+    Medium,
+    /// The input was checked layer by layer.
+    High,
+}
+
+/// The result of [detect].
+#[derive(Clone, Debug)]
+pub struct Detection {
+    format: Format,
+    confidence: Confidence,
+    explanation: String,
+}
+
+impl Detection {
+    fn new(format: Format, confidence: Confidence, explanation: impl Into<String>) -> Self {
+        Self {
+            format,
+            confidence,
+            explanation: explanation.into(),
+        }
+    }
+
+    /// Get the detected format.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Get how sure the detection is.
+    pub fn confidence(&self) -> Confidence {
+        self.confidence
+    }
+
+    /// Get a human-readable explanation of the detection.
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.explanation)
+    }
+}
+
+/// Detects the format of `data`, explaining why it can or cannot be decoded.
+///
+/// Lua tables and JSON are recognized by how they start. Otherwise, the input is
+/// [sanitized](crate::sanitize) and WeakAuras-compatible strings are checked layer by layer,
+/// like [decode](crate::decode) does, but instead of the first error the result tells
+/// whether the string was cut short or damaged, and where. Strings of other addons
+/// are recognized by their prefixes.
+///
+/// `max_size` is the same as in [decode](crate::decode).
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::{Confidence, Format, detect};
+///
+/// let detection = detect(b"!WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa", None);
+/// assert_eq!(detection.format(), Format::BinarySerialization);
+/// assert_eq!(detection.confidence(), Confidence::High);
+///
+/// let detection = detect(b"!WA:2!JXl5rQ5Kt(6Oq5", None);
+/// assert_eq!(detection.format(), Format::Truncated);
+///
+/// let detection = detect(b"WeakAurasSaved = { [\"displays\"] = {} }", None);
+/// assert_eq!(detection.format(), Format::LuaTable);
+/// assert!(detection.explanation().starts_with("The input assigns a table to a variable"));
+/// ```
+pub fn detect(data: &[u8], max_size: Option<usize>) -> Detection {
+    // Sanitizing would remove the whitespace and quotes tables are recognized by.
+    if let Some(detection) = detect_table(data.trim_ascii()) {
+        return detection;
+    }
+
+    let sanitized = sanitize(data);
+    let data = sanitized.data();
+    let max_size = max_size.unwrap_or(DEFAULT_MAX_SIZE);
+
+    let mut detection = if data.is_empty() {
+        Detection::new(Format::Unknown, Confidence::High, "The input is empty.")
+    } else if data.starts_with(b"!WA:2!") {
+        detect_string(data, 6, Format::BinarySerialization, max_size)
+    } else if let Some(detection) = detect_addon_prefix(data) {
+        detection
+    } else if data.starts_with(b"!") {
+        detect_string(data, 1, Format::Deflate, max_size)
+    } else {
+        detect_legacy(data, max_size)
+    };
+
+    if !sanitized.is_clean() && detection.format != Format::Unknown {
+        detection
+            .explanation
+            .push_str(" Whitespace, invisible characters, quotes or code fences were ignored.");
+    }
+
+    detection
+}
+
+/// Checks a string with a WeakAuras prefix layer by layer.
+fn detect_string(data: &[u8], prefix_len: usize, format: Format, max_size: usize) -> Detection {
+    let (prefix, serializer) = match format {
+        Format::BinarySerialization => ("`!WA:2!`", "LibSerialize"),
+        _ => ("`!`", "AceSerialize"),
+    };
+    let base64_data = &data[prefix_len..];
+
+    if base64_data.is_empty() {
+        return Detection::new(
+            Format::Truncated,
+            Confidence::High,
+            format!("Nothing follows the {prefix} prefix."),
+        );
+    }
+
+    let compressed_data = match weakauras_codec_base64::decode_to_vec(base64_data) {
+        Ok(compressed_data) => compressed_data,
+        Err(Base64DecodeError::InvalidByte(invalid_byte_at)) => {
+            return Detection::new(
+                Format::Damaged,
+                Confidence::High,
+                format!(
+                    "The character at offset {} is not part of the base64 alphabet.",
+                    prefix_len + invalid_byte_at
+                ),
+            );
+        }
+        Err(Base64DecodeError::InvalidLength) => {
+            return Detection::new(
+                Format::Truncated,
+                Confidence::Medium,
+                "The base64-string has a length no string can have, \
+                 it was most likely cut short, or a character was added to its end.",
+            );
+        }
+    };
+
+    // Bits 1 and 2 of the first byte are the type of the first DEFLATE block.
+    if compressed_data[0] & 0b110 == 0b110 {
+        return Detection::new(
+            Format::Damaged,
+            Confidence::High,
+            format!(
+                "The string starts with the {prefix} prefix, \
+                 but its data does not start with a valid DEFLATE block."
+            ),
+        );
+    }
+
+    let inflated = inflate(&compressed_data, max_size);
+    match inflated.error {
+        Some(DecodeErrorKind::DataExceedsMaxSize) => {
+            return Detection::new(
+                format,
+                Confidence::Medium,
+                format!(
+                    "The string starts with the {prefix} prefix and contains valid DEFLATE data, \
+                     but it decompresses to more than {max_size} bytes, which were not checked."
+                ),
+            );
+        }
+        Some(_) if inflated.consumed == compressed_data.len() => {
+            return Detection::new(
+                Format::Truncated,
+                Confidence::High,
+                "The DEFLATE-compressed data ends too early, \
+                 the string was most likely cut short, e.g. by a length limit.",
+            );
+        }
+        Some(_) => {
+            return Detection::new(
+                Format::Damaged,
+                Confidence::High,
+                format!(
+                    "The DEFLATE-compressed data is invalid around offset {}.",
+                    prefix_len + base64_len(inflated.consumed)
+                ),
+            );
+        }
+        None => {}
+    }
+
+    let decoded = inflated.data;
+    let error = match format {
+        Format::BinarySerialization => {
+            if decoded.starts_with(b"^1") {
+                return mismatch(prefix, "AceSerialize", "`!`");
+            }
+            Deserializer::from_slice(&decoded)
+                .deserialize_first()
+                .err()
+                .map(|e| {
+                    (
+                        e.kind() == DeserializationErrorKind::UnexpectedEof,
+                        e.to_string(),
+                    )
+                })
+        }
+        _ => {
+            if matches!(decoded.first(), Some(1 | 2)) {
+                return mismatch(prefix, "LibSerialize", "`!WA:2!`");
+            }
+            LegacyDeserializer::from_bytes(&decoded)
+                .deserialize_first()
+                .err()
+                .map(|e| {
+                    (
+                        e.kind() == AceSerializeErrorKind::UnexpectedEof,
+                        e.to_string(),
+                    )
+                })
+        }
+    };
+
+    match error {
+        None => Detection::new(
+            format,
+            Confidence::High,
+            format!(
+                "The string starts with the {prefix} prefix \
+                 and contains DEFLATE-compressed data serialized by {serializer}."
+            ),
+        ),
+        Some((unexpected_eof, error)) => Detection::new(
+            if unexpected_eof {
+                Format::Truncated
+            } else {
+                Format::Damaged
+            },
+            Confidence::Medium,
+            format!(
+                "The string starts with the {prefix} prefix and contains valid DEFLATE data, \
+                 but not valid {serializer} data: {error}."
+            ),
+        ),
+    }
+}
+
+/// Describes a string whose data was serialized by the serializer of another prefix.
+fn mismatch(prefix: &str, serializer: &str, expected_prefix: &str) -> Detection {
+    Detection::new(
+        Format::Damaged,
+        Confidence::High,
+        format!(
+            "The string starts with the {prefix} prefix, but its data was serialized by \
+             {serializer}, which is used by strings with the {expected_prefix} prefix."
+        ),
+    )
+}
+
+/// Recognizes prefixes like `!NAME:1!`.
+fn detect_addon_prefix(data: &[u8]) -> Option<Detection> {
+    if let Some((_, addon)) = ADDON_PREFIXES
+        .iter()
+        .find(|(prefix, _)| data.starts_with(prefix.as_bytes()))
+    {
+        return Some(Detection::new(
+            Format::OtherAddon,
+            Confidence::High,
+            format!("The string was exported by {addon}, not by WeakAuras."),
+        ));
+    }
+
+    // Base64-strings never contain `!`, thus a second one ends a prefix.
+    let len = data[1..].iter().take(32).position(|&byte| byte == b'!')? + 2;
+    let prefix = std::str::from_utf8(&data[..len]).ok()?;
+    let (name, version) = prefix[1..len - 1].split_once(':')?;
+    if name.is_empty()
+        || !name.bytes().all(|byte| byte.is_ascii_alphanumeric())
+        || !version.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+
+    Some(if name == "WA" {
+        Detection::new(
+            Format::Unknown,
+            Confidence::Medium,
+            format!(
+                "The `{prefix}` prefix denotes a version of WeakAuras strings that is not supported."
+            ),
+        )
+    } else {
+        Detection::new(
+            Format::OtherAddon,
+            Confidence::Medium,
+            format!(
+                "The `{prefix}` prefix is not used by WeakAuras, but looks like a prefix of another addon."
+            ),
+        )
+    })
+}
+
+/// Recognizes Lua table constructors and JSON.
+fn detect_table(data: &[u8]) -> Option<Detection> {
+    let lua = |explanation: &str| {
+        Some(Detection::new(
+            Format::LuaTable,
+            Confidence::Medium,
+            format!("{explanation} WeakAuras imports exported strings rather than Lua tables."),
+        ))
+    };
+
+    // `WeakAurasSaved = {` or `local aura = {`
+    let assignment = data.strip_prefix(b"local ").unwrap_or(data);
+    let identifier_len = assignment
+        .iter()
+        .position(|&byte| !(byte.is_ascii_alphanumeric() || byte == b'_'))
+        .unwrap_or(assignment.len());
+    let value = assignment[identifier_len..]
+        .trim_ascii_start()
+        .strip_prefix(b"=")
+        .map(<[u8]>::trim_ascii_start);
+    if identifier_len > 0
+        && !assignment[0].is_ascii_digit()
+        && value.is_some_and(|value| value.starts_with(b"{"))
+    {
+        return lua("The input assigns a table to a variable, like SavedVariables files do.");
+    }
+
+    if data.starts_with(b"return") && data[6..].trim_ascii_start().starts_with(b"{") {
+        return lua("The input returns a Lua table.");
+    }
+
+    match data.first()? {
+        b'[' => Some(Detection::new(
+            Format::Json,
+            Confidence::Medium,
+            "The input starts with `[`, like a JSON array, which a Lua table cannot.",
+        )),
+        b'{' => {
+            let inner = data[1..].trim_ascii_start();
+            if inner.starts_with(b"\"") && data.windows(2).any(|window| window == b"\":") {
+                Some(Detection::new(
+                    Format::Json,
+                    Confidence::Medium,
+                    "The input is an object with quoted keys followed by `:`, like JSON.",
+                ))
+            } else if data.ends_with(b"}") {
+                lua("The input is enclosed in braces, like a Lua table constructor.")
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Checks whether a string without a prefix is a legacy string.
+fn detect_legacy(
+    data: &[u8],
+    #[cfg_attr(not(feature = "legacy-strings-decoding"), allow(unused_variables))] max_size: usize,
+) -> Detection {
+    let Ok(compressed_data) = weakauras_codec_base64::decode_to_vec(data) else {
+        return Detection::new(
+            Format::Unknown,
+            Confidence::Low,
+            "The input has no known prefix and is not a base64-string.",
+        );
+    };
+
+    // The first byte of LibCompress data is the compression method.
+    #[cfg_attr(feature = "legacy-strings-decoding", allow(unused_variables))]
+    let (method, confidence) = match compressed_data.first() {
+        Some(1) if compressed_data[1..].starts_with(b"^1") => {
+            ("stored without compression", Confidence::High)
+        }
+        Some(3) => ("compressed with Huffman coding", Confidence::Medium),
+        _ => {
+            return Detection::new(
+                Format::Unknown,
+                Confidence::Low,
+                "The input has no prefix, and its data does not start \
+                 with a compression method of LibCompress.",
+            );
+        }
+    };
+
+    #[cfg(feature = "legacy-strings-decoding")]
+    {
+        use weakauras_codec_lib_compress::error::DecompressionError;
+
+        match crate::decode(data, Some(max_size)) {
+            Ok(_) => Detection::new(
+                Format::Legacy,
+                Confidence::High,
+                format!("The input is a legacy string without a prefix, {method} by LibCompress."),
+            ),
+            Err(e) => {
+                let unexpected_eof = match e.kind() {
+                    DecodeErrorKind::LibCompressDecompressionError(e) => {
+                        *e == DecompressionError::UnexpectedEof
+                    }
+                    DecodeErrorKind::AceSerializeDeserializationError(e) => {
+                        e.kind() == AceSerializeErrorKind::UnexpectedEof
+                    }
+                    _ => false,
+                };
+                Detection::new(
+                    if unexpected_eof {
+                        Format::Truncated
+                    } else {
+                        Format::Damaged
+                    },
+                    Confidence::Medium,
+                    format!(
+                        "The input looks like a legacy string, {method} by LibCompress, \
+                         but it cannot be decoded: {e}."
+                    ),
+                )
+            }
+        }
+    }
+
+    #[cfg(not(feature = "legacy-strings-decoding"))]
+    {
+        Detection::new(
+            Format::Legacy,
+            confidence,
+            format!(
+                "The input looks like a legacy string without a prefix, {method} by LibCompress. \
+                 Decoding it requires the `legacy-strings-decoding` feature."
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LuaValue,
+        pipeline::{AceSerialize, Base64, Deflate, LibSerialize, Pipeline},
+    };
+
+    fn check(data: impl AsRef<[u8]>, format: Format, confidence: Confidence) -> Detection {
+        let detection = detect(data.as_ref(), None);
+        assert_eq!(
+            (detection.format(), detection.confidence()),
+            (format, confidence),
+            "{detection}"
+        );
+        detection
+    }
+
+    #[test]
+    fn strings_of_other_addons_are_recognized() {
+        let detection = check("!PLATER:2!abcd", Format::OtherAddon, Confidence::High);
+        assert!(detection.explanation().contains("Plater"));
+        check("!E1!abcd", Format::OtherAddon, Confidence::High);
+
+        let detection = check("!MyAddon:3!abcd", Format::OtherAddon, Confidence::Medium);
+        assert!(detection.explanation().contains("`!MyAddon:3!`"));
+        check("!WA:3!abcd", Format::Unknown, Confidence::Medium);
+
+        // Not a prefix, the `!` prefix is followed by a damaged string.
+        check("!My-Addon:3!abcd", Format::Damaged, Confidence::High);
+    }
+
+    #[test]
+    fn json_is_told_apart_from_lua_tables() {
+        check("[1, 2, 3]", Format::Json, Confidence::Medium);
+        check("{ \"id\": \"Aura\" }", Format::Json, Confidence::Medium);
+        check(
+            "{ [\"id\"] = \"Aura\" }",
+            Format::LuaTable,
+            Confidence::Medium,
+        );
+        check(
+            "return { id = \"Aura\" }",
+            Format::LuaTable,
+            Confidence::Medium,
+        );
+        check("local aura = {}", Format::LuaTable, Confidence::Medium);
+        // An unclosed brace is neither.
+        check("{ id = ", Format::Unknown, Confidence::Low);
+    }
+
+    #[test]
+    fn damage_is_located() {
+        let detection = check(
+            "!WA:2!JXl5rQ5Kt*6Oq55xuoPOiaa",
+            Format::Damaged,
+            Confidence::High,
+        );
+        assert!(detection.explanation().contains("offset 15"));
+
+        // A DEFLATE block of the reserved type.
+        check("!WA:2!gaaa", Format::Damaged, Confidence::High);
+
+        let detection = check(
+            "!WA:2!JXl5rQ5Kt(6Oq55xuoPOi",
+            Format::Truncated,
+            Confidence::Medium,
+        );
+        assert!(detection.explanation().contains("length"));
+
+        let detection = check(
+            "  !WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa\n",
+            Format::BinarySerialization,
+            Confidence::High,
+        );
+        assert!(detection.explanation().ends_with("were ignored."));
+    }
+
+    #[test]
+    fn serializers_of_other_prefixes_are_mismatches() {
+        let value = LuaValue::from("Hello, world!");
+
+        let ace = Pipeline::new(Base64, Deflate, AceSerialize).with_prefix("!WA:2!");
+        let detection = check(
+            ace.encode(&value).unwrap(),
+            Format::Damaged,
+            Confidence::High,
+        );
+        assert_eq!(
+            detection.explanation(),
+            mismatch("`!WA:2!`", "AceSerialize", "`!`").explanation()
+        );
+        assert!(
+            detection
+                .explanation()
+                .contains("serialized by AceSerialize")
+        );
+
+        let lib = Pipeline::new(Base64, Deflate, LibSerialize).with_prefix("!");
+        let detection = check(
+            lib.encode(&value).unwrap(),
+            Format::Damaged,
+            Confidence::High,
+        );
+        assert!(
+            detection
+                .explanation()
+                .ends_with("strings with the `!WA:2!` prefix.")
+        );
+    }
+
+    #[test]
+    fn legacy_strings_are_recognized_by_their_compression_method() {
+        use weakauras_codec_ace_serialize::Serializer as LegacySerializer;
+
+        let mut compressed = vec![1];
+        compressed.extend(LegacySerializer::serialize_one(&"Hello".into(), None).unwrap());
+        let legacy = weakauras_codec_base64::encode_to_string(&compressed).unwrap();
+
+        #[cfg(feature = "legacy-strings-decoding")]
+        {
+            check(&legacy, Format::Legacy, Confidence::High);
+            check(
+                &legacy[..legacy.len() - 4],
+                Format::Truncated,
+                Confidence::Medium,
+            );
+        }
+        #[cfg(not(feature = "legacy-strings-decoding"))]
+        {
+            let detection = check(&legacy, Format::Legacy, Confidence::High);
+            assert!(
+                detection
+                    .explanation()
+                    .contains("`legacy-strings-decoding`")
+            );
+        }
+
+        check("abcd", Format::Unknown, Confidence::Low);
+        check("Hello, world!", Format::Unknown, Confidence::Low);
+    }
+}
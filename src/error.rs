@@ -6,7 +6,7 @@
 
 use weakauras_codec_ace_serialize::error::{
     DeserializationError as AceSerializeDeserializationError,
-    SerializationError as AceSerializeSerializationError,
+    SerializationError as AceSerializeSerializationError, WriteError as AceSerializeWriteError,
 };
 use weakauras_codec_base64::error::{
     DecodeError as Base64DecodeError, EncodeError as Base64EncodeError,
@@ -15,17 +15,23 @@
 use weakauras_codec_lib_compress::error::DecompressionError as LibCompressDecompressionError;
 use weakauras_codec_lib_serialize::error::{
     DeserializationError as LibSerializeDeserializationError,
-    SerializationError as LibSerializeSerializationError,
+    SerializationError as LibSerializeSerializationError, WriteError as LibSerializeWriteError,
 };
+use weakauras_codec_lua_value::ErrorLocation;
 
-/// Errors than can occur while decoding.
+/// Kinds of errors than can occur while decoding.
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum DecodeError {
-    #[cfg(not(feature = "legacy-strings-decoding"))]
+pub enum DecodeErrorKind {
     /// The input does not start with a valid prefix.
+    ///
+    /// With the `legacy-strings-decoding` feature, [decode](crate::decode) treats
+    /// strings without a known prefix as legacy strings, thus only
+    /// a [Pipeline](crate::pipeline::Pipeline) returns this.
     InvalidPrefix,
     /// The input is not a valid base64-string.
+    ///
+    /// Offsets are within the base64-string, i.e. they do not include the prefix.
     Base64DecodeError(Base64DecodeError),
     #[cfg(feature = "legacy-strings-decoding")]
     /// The input is not valid data compressed by LibCompress.
@@ -40,14 +46,14 @@ pub enum DecodeError {
     LibSerializeDeserializationError(LibSerializeDeserializationError),
 }
 
-impl From<Base64DecodeError> for DecodeError {
+impl From<Base64DecodeError> for DecodeErrorKind {
     fn from(value: Base64DecodeError) -> Self {
         Self::Base64DecodeError(value)
     }
 }
 
 #[cfg(feature = "legacy-strings-decoding")]
-impl From<LibCompressDecompressionError> for DecodeError {
+impl From<LibCompressDecompressionError> for DecodeErrorKind {
     fn from(value: LibCompressDecompressionError) -> Self {
         match value {
             LibCompressDecompressionError::DataExceedsMaxSize => Self::DataExceedsMaxSize,
@@ -56,28 +62,27 @@ fn from(value: LibCompressDecompressionError) -> Self {
     }
 }
 
-impl From<IoError> for DecodeError {
+impl From<IoError> for DecodeErrorKind {
     fn from(value: IoError) -> Self {
         Self::IoError(value)
     }
 }
 
-impl From<AceSerializeDeserializationError> for DecodeError {
+impl From<AceSerializeDeserializationError> for DecodeErrorKind {
     fn from(value: AceSerializeDeserializationError) -> Self {
         Self::AceSerializeDeserializationError(value)
     }
 }
 
-impl From<LibSerializeDeserializationError> for DecodeError {
+impl From<LibSerializeDeserializationError> for DecodeErrorKind {
     fn from(value: LibSerializeDeserializationError) -> Self {
         Self::LibSerializeDeserializationError(value)
     }
 }
 
-impl fmt::Display for DecodeError {
+impl fmt::Display for DecodeErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            #[cfg(not(feature = "legacy-strings-decoding"))]
             Self::InvalidPrefix => write!(f, "Invalid prefix"),
             Self::Base64DecodeError(inner) => write!(f, "Failed to decode base64: {}", inner),
             #[cfg(feature = "legacy-strings-decoding")]
@@ -96,10 +101,9 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     }
 }
 
-impl error::Error for DecodeError {
+impl error::Error for DecodeErrorKind {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            #[cfg(not(feature = "legacy-strings-decoding"))]
             Self::InvalidPrefix => None,
             Self::Base64DecodeError(inner) => Some(inner),
             #[cfg(feature = "legacy-strings-decoding")]
@@ -112,13 +116,125 @@ fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     }
 }
 
+/// An error that occurred while decoding.
+///
+/// Errors in the prefix, in the base64-string or in the DEFLATE stream are located
+/// in the original string, deserialization errors carry their location in the decompressed data.
+///
+/// This used to be an enum of what is now [DecodeErrorKind]: match on
+/// [DecodeError::kind] instead of the error itself.
+///
+/// ```
+/// use weakauras_codec::{DecodeErrorKind, decode};
+///
+/// match decode(b"!WA:2!JXl5rQ5*t(6Oq55xuoPOiaa", None) {
+///     Err(e) => match e.kind() {
+///         DecodeErrorKind::Base64DecodeError(_) => {}
+///         kind => panic!("unexpected error: {kind}"),
+///     },
+///     Ok(_) => panic!("expected an error"),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+    // Boxed to keep results small.
+    location: Option<Box<ErrorLocation>>,
+}
+
+impl DecodeError {
+    /// Get the kind of the error.
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.kind
+    }
+
+    /// Convert the error into its kind.
+    pub fn into_kind(self) -> DecodeErrorKind {
+        self.kind
+    }
+
+    /// Get where the error occurred within the input of the layer that failed.
+    ///
+    /// That is the original string for errors in the prefix or in the base64-string,
+    /// and the decompressed data for deserialization errors. DEFLATE errors are located
+    /// in the original string where the inflater stopped reading, LibCompress errors
+    /// have no location.
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match &self.kind {
+            DecodeErrorKind::AceSerializeDeserializationError(inner) => inner.location(),
+            DecodeErrorKind::LibSerializeDeserializationError(inner) => inner.location(),
+            _ => self.location.as_deref(),
+        }
+    }
+
+    /// Get the offset within the original string, if the layer that failed
+    /// works on a part of it rather than on decompressed data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec::{DecodeErrorKind, decode};
+    /// use weakauras_codec_base64::error::DecodeError as Base64DecodeError;
+    ///
+    /// let error = decode(b"!WA:2!JXl5rQ5*t(6Oq55xuoPOiaa", None).unwrap_err();
+    ///
+    /// assert!(matches!(
+    ///     error.kind(),
+    ///     DecodeErrorKind::Base64DecodeError(Base64DecodeError::InvalidByte(7))
+    /// ));
+    /// assert_eq!(error.original_offset(), Some(13));
+    /// ```
+    pub fn original_offset(&self) -> Option<usize> {
+        self.location.as_deref().map(ErrorLocation::offset)
+    }
+
+    /// Locate the error at `offset` in `input`.
+    ///
+    /// Stages of a [Pipeline](crate::pipeline::Pipeline) locate errors in their input,
+    /// the pipeline then relocates them in the original string.
+    pub fn located_at(mut self, input: &[u8], offset: usize) -> Self {
+        self.location = Some(Box::new(ErrorLocation::new(input, offset)));
+        self
+    }
+}
+
+impl<T: Into<DecodeErrorKind>> From<T> for DecodeError {
+    fn from(value: T) -> Self {
+        Self {
+            kind: value.into(),
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "{} (offset {} in the original string){}",
+                self.kind,
+                location.offset(),
+                location.snippet()
+            ),
+            None => self.kind.fmt(f),
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
 /// Errors than can occur while encoding.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum EncodeError {
     /// The input cannot be base64-encoded.
     Base64EncodeError(Base64EncodeError),
-    /// An [io::Error](std::io::Error) occurred while compressing using DEFLATE.
+    /// An [io::Error](std::io::Error) occurred while compressing or writing the output.
     IoError(IoError),
     /// The input cannot be serialized using AceSerialize.
     AceSerializeSerializationError(AceSerializeSerializationError),
@@ -150,6 +266,26 @@ fn from(value: LibSerializeSerializationError) -> Self {
     }
 }
 
+impl From<AceSerializeWriteError> for EncodeError {
+    fn from(value: AceSerializeWriteError) -> Self {
+        match value {
+            AceSerializeWriteError::SerializationError(e) => e.into(),
+            AceSerializeWriteError::IoError(e) => e.into(),
+            e => IoError::other(e).into(),
+        }
+    }
+}
+
+impl From<LibSerializeWriteError> for EncodeError {
+    fn from(value: LibSerializeWriteError) -> Self {
+        match value {
+            LibSerializeWriteError::SerializationError(e) => e.into(),
+            LibSerializeWriteError::IoError(e) => e.into(),
+            e => IoError::other(e).into(),
+        }
+    }
+}
+
 impl fmt::Display for EncodeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -26,6 +26,9 @@
 //! }
 //! ```
 //!
+//! `DecodeError` is a struct that also locates the error; match on `DecodeError::kind`,
+//! which returns a `DecodeErrorKind`, where earlier versions matched on the error itself.
+//!
 //! # Encoding example
 //!
 //! This is how you can use the library to encode data as a WeakAuras-compatible string.
@@ -57,8 +60,8 @@
 //! * **flate2-zlib-ng-compat** - Enable the `zlib-ng-compat` feature in `flate2`. **Disabled** by default.
 //! * **flate2-cloudflare-zlib** - Enable the `cloudflare_zlib` feature in `flate2`. **Disabled** by default.
 //! * **lua-value-arbitrary** - Implement `arbitrary::Arbitrary` for [`LuaValue`]. **Disabled** by default.
-//! * **lua-value-fnv** - Use `fnv` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
-//! * **lua-value-indexmap** - Use `indexmap` instead of `BTreeMap` as the implementation of [`LuaValue::Map`]. **Disabled** by default.
+//! * **lua-value-fnv** - Make `fnv` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
+//! * **lua-value-indexmap** - Make `indexmap` available as an implementation of [`LuaValue::Map`], see [`MapBackend`]. **Disabled** by default.
 //! * **serde** - Allow serializing and deserializing [`LuaValue`] using `serde`. **Disabled** by default.
 //!
 //! [WeakAuras]: https://weakauras.wtf
@@ -69,13 +72,23 @@
 /// Error types.
 pub mod error;
 pub use error::*;
+mod detect;
+pub use detect::{Confidence, Detection, Format, detect};
+mod partial;
+pub use partial::{PartialDecode, decode_partial};
+mod repair;
+pub use repair::{Edit, Repair, repair};
+pub mod pipeline;
+pub use pipeline::{Compression, FinishWrite, Pipeline, Serialization, TextEncoding};
+mod sanitize;
+pub use sanitize::{Removal, RemovalKind, Sanitized, sanitize};
+mod scan;
+pub use scan::{ScannedString, scan};
+
+pub use weakauras_codec_lua_value::{LuaValue, MapBackend, NormalizationPolicy, ResourceLimits};
 
-use weakauras_codec_ace_serialize::{
-    Deserializer as LegacyDeserializer, Serializer as LegacySerializer,
-};
-use weakauras_codec_base64::error::DecodeError as Base64DecodeError;
-use weakauras_codec_lib_serialize::{Deserializer, Serializer};
-pub use weakauras_codec_lua_value::LuaValue;
+#[cfg(test)]
+mod tests;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum StringVersion {
@@ -100,6 +113,9 @@ pub enum OutputStringVersion {
 /// is compressed, it's possible to construct a payload that would consume a lot of memory
 /// after decompression. `None` is equivalent to 16 MiB.
 ///
+/// Tables are normalized with [NormalizationPolicy::PreferArrays] whatever the version
+/// of the string, so the same aura decodes into the same shape.
+///
 /// # Example
 ///
 /// ```
@@ -121,67 +137,139 @@ pub enum OutputStringVersion {
 /// }
 /// ```
 pub fn decode(data: &[u8], max_size: Option<usize>) -> Result<Option<LuaValue>, DecodeError> {
-    let (base64_data, version) = match data {
-        [b'!', b'W', b'A', b':', b'2', b'!', rest @ ..] => {
-            (rest, StringVersion::BinarySerialization)
+    decode_with_limits(data, max_size, ResourceLimits::default())
+}
+
+/// Decodes a WeakAuras-compatible string and returns a [LuaValue],
+/// enforcing `limits` while deserializing.
+///
+/// Unlike `max_size`, which bounds the size of the decompressed data,
+/// `limits` bound the size of the value described by that data.
+/// [decode] uses [ResourceLimits::default].
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::{DecodeErrorKind, ResourceLimits, decode_with_limits};
+///
+/// let limits = ResourceLimits::default().with_max_string_bytes(4);
+/// let error = decode_with_limits(b"!WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa", None, limits).unwrap_err();
+///
+/// assert!(matches!(
+///     error.kind(),
+///     DecodeErrorKind::LibSerializeDeserializationError(_)
+/// ));
+/// ```
+pub fn decode_with_limits(
+    data: &[u8],
+    max_size: Option<usize>,
+    limits: ResourceLimits,
+) -> Result<Option<LuaValue>, DecodeError> {
+    match split_prefix(data)?.1 {
+        #[cfg(feature = "legacy-strings-decoding")]
+        StringVersion::Legacy => pipeline::LEGACY.decode_with_limits(data, max_size, limits),
+        StringVersion::Deflate => pipeline::DEFLATE.decode_with_limits(data, max_size, limits),
+        StringVersion::BinarySerialization => {
+            pipeline::BINARY_SERIALIZATION.decode_with_limits(data, max_size, limits)
         }
-        [b'!', rest @ ..] => (rest, StringVersion::Deflate),
+    }
+}
+
+/// The maximum size of decompressed data used when none is provided.
+const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// Returns the length of the prefix and the version of the string it denotes.
+fn split_prefix(data: &[u8]) -> Result<(usize, StringVersion), DecodeError> {
+    match data {
+        [b'!', b'W', b'A', b':', b'2', b'!', ..] => Ok((6, StringVersion::BinarySerialization)),
+        [b'!', ..] => Ok((1, StringVersion::Deflate)),
         _ => {
             #[cfg(feature = "legacy-strings-decoding")]
             {
-                (data, StringVersion::Legacy)
+                Ok((0, StringVersion::Legacy))
             }
 
             #[cfg(not(feature = "legacy-strings-decoding"))]
-            return Err(DecodeError::InvalidPrefix);
+            Err(DecodeError::from(DecodeErrorKind::InvalidPrefix).located_at(data, 0))
         }
-    };
+    }
+}
+
+/// The outcome of [inflate].
+struct Inflated {
+    /// The data inflated before an error, if any.
+    data: Vec<u8>,
+    /// The number of compressed bytes consumed by the inflater.
+    consumed: usize,
+    error: Option<DecodeErrorKind>,
+}
+
+/// Inflates at most `max_size` bytes of DEFLATE-compressed data.
+fn inflate(compressed_data: &[u8], max_size: usize) -> Inflated {
+    use flate2::read::DeflateDecoder;
+    use std::io::prelude::*;
 
-    let compressed_data = match weakauras_codec_base64::decode_to_vec(base64_data) {
-        Ok(compressed_data) => compressed_data,
-        Err(Base64DecodeError::InvalidByte(invalid_byte_at)) => {
-            let prefix_len = base64_data.as_ptr().addr() - data.as_ptr().addr();
+    let mut data = Vec::new();
+    let mut inflater = DeflateDecoder::new(compressed_data).take(max_size as u64);
 
-            return Err(DecodeError::Base64DecodeError(
-                Base64DecodeError::InvalidByte(prefix_len + invalid_byte_at),
-            ));
+    let error = match inflater.read_to_end(&mut data) {
+        Err(e) => {
+            // The inflater discards the output and does not count the input
+            // of a call that fails, so it is redone in small steps.
+            let (data, consumed) = inflate_stepwise(compressed_data, max_size);
+            return Inflated {
+                data,
+                consumed,
+                error: Some(e.into()),
+            };
+        }
+        #[allow(clippy::unbuffered_bytes)] // inflater wraps in-memory data
+        Ok(_) if data.len() == max_size && inflater.get_mut().bytes().next().is_some() => {
+            Some(DecodeErrorKind::DataExceedsMaxSize)
         }
-        Err(e) => return Err(e.into()),
+        Ok(_) => None,
     };
 
-    let max_size = max_size.unwrap_or(16 * 1024 * 1024);
-    #[cfg(feature = "legacy-strings-decoding")]
-    {
-        if version == StringVersion::Legacy {
-            let decoded = weakauras_codec_lib_compress::decompress(&compressed_data, max_size)?;
-            return LegacyDeserializer::from_str(&String::from_utf8_lossy(&decoded))
-                .deserialize_first()
-                .map_err(Into::into);
-        }
+    Inflated {
+        data,
+        consumed: inflater.get_ref().total_in() as usize,
+        error,
     }
+}
 
-    let decoded = {
-        use flate2::read::DeflateDecoder;
-        use std::io::prelude::*;
+/// Inflates at most `len` bytes a few at a time, stopping at the first error.
+///
+/// Returns the inflated data and the number of compressed bytes consumed to produce it,
+/// both of which are close to exact even if the compressed data is damaged.
+#[cold]
+fn inflate_stepwise(compressed_data: &[u8], len: usize) -> (Vec<u8>, usize) {
+    use flate2::{Decompress, FlushDecompress, Status};
 
-        let mut result = Vec::new();
-        let mut inflater = DeflateDecoder::new(&compressed_data[..]).take(max_size as u64);
+    let mut inflater = Decompress::new(false);
+    let mut data = Vec::new();
+    let mut output = [0; 16];
 
-        inflater.read_to_end(&mut result)?;
+    while data.len() < len {
+        let input = &compressed_data[inflater.total_in() as usize..];
+        let wanted = (len - data.len()).min(output.len());
+        let (total_in, total_out) = (inflater.total_in(), inflater.total_out());
 
-        #[allow(clippy::unbuffered_bytes)] // inflater wraps in-memory data
-        if result.len() == max_size && inflater.into_inner().bytes().next().is_some() {
-            return Err(DecodeError::DataExceedsMaxSize);
+        let status = inflater.decompress(input, &mut output[..wanted], FlushDecompress::None);
+        data.extend_from_slice(&output[..(inflater.total_out() - total_out) as usize]);
+
+        match status {
+            Ok(Status::Ok)
+                if (total_in, total_out) != (inflater.total_in(), inflater.total_out()) => {}
+            _ => break,
         }
+    }
 
-        result
-    };
+    (data, inflater.total_in() as usize)
+}
 
-    Ok(if version == StringVersion::BinarySerialization {
-        Deserializer::from_slice(&decoded).deserialize_first()?
-    } else {
-        LegacyDeserializer::from_str(&String::from_utf8_lossy(&decoded)).deserialize_first()?
-    })
+/// Returns the number of base64 characters that encode the first `len` bytes.
+fn base64_len(len: usize) -> usize {
+    len / 3 * 4 + [0, 2, 3][len % 3]
 }
 
 /// Encodes a [LuaValue] into a WeakAuras-compatible string.
@@ -207,29 +295,8 @@ pub fn encode(
     value: &LuaValue,
     string_version: OutputStringVersion,
 ) -> Result<String, EncodeError> {
-    let (serialized, prefix) = match string_version {
-        OutputStringVersion::Deflate => (
-            LegacySerializer::serialize_one(value, None).map(|v| v.into_bytes())?,
-            "!",
-        ),
-        OutputStringVersion::BinarySerialization => {
-            (Serializer::serialize_one(value, None)?, "!WA:2!")
-        }
-    };
-
-    let compressed = {
-        use flate2::{Compression, read::DeflateEncoder};
-        use std::io::prelude::*;
-
-        let mut result = Vec::new();
-        let mut deflater = DeflateEncoder::new(serialized.as_slice(), Compression::best());
-
-        deflater.read_to_end(&mut result)?;
-        result
-    };
-
-    Ok(weakauras_codec_base64::encode_to_string_with_prefix(
-        &compressed,
-        prefix,
-    )?)
+    match string_version {
+        OutputStringVersion::Deflate => pipeline::DEFLATE.encode(value),
+        OutputStringVersion::BinarySerialization => pipeline::BINARY_SERIALIZATION.encode(value),
+    }
 }
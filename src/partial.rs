@@ -0,0 +1,361 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    DEFAULT_MAX_SIZE, DecodeError, LuaValue, MapBackend, ResourceLimits, StringVersion, base64_len,
+    inflate,
+    pipeline::{AceSerialize, LibSerialize},
+    split_prefix,
+};
+use weakauras_codec_base64::error::DecodeError as Base64DecodeError;
+
+/// The result of [decode_partial].
+#[derive(Debug)]
+pub struct PartialDecode {
+    value: Option<LuaValue>,
+    issues: Vec<DecodeError>,
+}
+
+impl PartialDecode {
+    /// Get the decoded value, possibly with tables that lack the entries
+    /// following the point where decoding stopped.
+    ///
+    /// `None` if not even the start of a value could be decoded.
+    pub fn value(&self) -> Option<&LuaValue> {
+        self.value.as_ref()
+    }
+
+    /// Convert the result into the decoded value, see [PartialDecode::value].
+    pub fn into_value(self) -> Option<LuaValue> {
+        self.value
+    }
+
+    /// Get the errors that were worked around, in the order of the layers they occurred in.
+    ///
+    /// Each layer works with what the previous one salvaged, so a truncated base64-string
+    /// usually leads to a DEFLATE error and a deserialization error as well.
+    /// The first issue is where the input is damaged.
+    pub fn issues(&self) -> &[DecodeError] {
+        &self.issues
+    }
+
+    /// Check whether the string was decoded without any issues.
+    pub fn is_complete(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Decodes as much of a damaged WeakAuras-compatible string as possible.
+///
+/// Rather than failing at the first error, each layer salvages what precedes it:
+/// the base64-string is decoded up to the first invalid character, as much data
+/// as possible is inflated, and deserialization closes the tables that are cut short,
+/// see [Deserializer::with_recovery](weakauras_codec_lib_serialize::Deserializer::with_recovery).
+/// The errors are reported in [PartialDecode::issues].
+///
+/// Values that are decoded completely are the same as those returned by
+/// [decode](crate::decode), e.g. tables are deserialized in the same shape.
+///
+/// Only a string without a valid prefix is an error. `max_size` is the same
+/// as in [decode](crate::decode), exceeding it truncates the decompressed data.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::{DecodeErrorKind, LuaValue, OutputStringVersion, decode_partial, encode};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let value = LuaValue::Array((1..=100).map(|i| format!("print({i})").into()).collect());
+///     let encoded = encode(&value, OutputStringVersion::BinarySerialization)?;
+///
+///     // The string was cut short by a copy-paste limit.
+///     let result = decode_partial(&encoded.as_bytes()[..encoded.len() / 2], None)?;
+///
+///     let Some(LuaValue::Array(elements)) = result.value() else {
+///         unreachable!()
+///     };
+///     assert!(!elements.is_empty() && elements.len() < 100);
+///     assert_eq!(elements[0], "print(1)".into());
+///
+///     assert!(!result.is_complete());
+///     assert!(
+///         result
+///             .issues()
+///             .iter()
+///             .any(|issue| matches!(issue.kind(), DecodeErrorKind::IoError(_)))
+///     );
+///     Ok(())
+/// }
+/// ```
+pub fn decode_partial(data: &[u8], max_size: Option<usize>) -> Result<PartialDecode, DecodeError> {
+    let (prefix_len, version) = split_prefix(data)?;
+    let base64_data = &data[prefix_len..];
+    let mut issues = Vec::new();
+
+    let compressed_data = salvage_base64(base64_data, |e, offset| {
+        issues.push(DecodeError::from(e).located_at(data, prefix_len + offset));
+    });
+
+    let max_size = max_size.unwrap_or(DEFAULT_MAX_SIZE);
+    let (limits, map_backend) = (ResourceLimits::default(), MapBackend::default());
+    #[cfg(feature = "legacy-strings-decoding")]
+    {
+        if version == StringVersion::Legacy {
+            // LibCompress does not produce data before an error.
+            let value = match weakauras_codec_lib_compress::decompress(&compressed_data, max_size) {
+                Ok(decoded) => {
+                    let mut deserializer =
+                        AceSerialize::deserializer(&decoded, limits, map_backend).with_recovery();
+                    let value = deserializer.deserialize_next();
+                    finish(value, deserializer.recovery_error().cloned(), &mut issues)
+                }
+                Err(e) => {
+                    issues.push(e.into());
+                    None
+                }
+            };
+
+            return Ok(PartialDecode { value, issues });
+        }
+    }
+
+    let inflated = inflate(&compressed_data, max_size);
+    if let Some(kind) = inflated.error {
+        let offset = prefix_len + base64_len(inflated.consumed);
+        issues.push(DecodeError::from(kind).located_at(data, offset));
+    }
+    let decoded = inflated.data;
+
+    let value = if version == StringVersion::BinarySerialization {
+        let mut deserializer =
+            LibSerialize::deserializer(&decoded, limits, map_backend).with_recovery();
+        let value = deserializer.deserialize_next();
+        finish(value, deserializer.recovery_error().cloned(), &mut issues)
+    } else {
+        let mut deserializer =
+            AceSerialize::deserializer(&decoded, limits, map_backend).with_recovery();
+        let value = deserializer.deserialize_next();
+        finish(value, deserializer.recovery_error().cloned(), &mut issues)
+    };
+
+    Ok(PartialDecode { value, issues })
+}
+
+/// Decodes the longest valid prefix of `base64_data`, reporting each error with its offset.
+pub(crate) fn salvage_base64(
+    base64_data: &[u8],
+    mut report: impl FnMut(Base64DecodeError, usize),
+) -> Vec<u8> {
+    let mut len = base64_data.len();
+
+    loop {
+        match weakauras_codec_base64::decode_to_vec(&base64_data[..len]) {
+            Ok(decoded) => return decoded,
+            Err(e) => {
+                let offset = match e {
+                    Base64DecodeError::InvalidByte(invalid_byte_at) => invalid_byte_at,
+                    Base64DecodeError::InvalidLength => len,
+                };
+                report(e, offset);
+
+                // A lone trailing character does not encode a whole byte.
+                len = offset - (offset % 4 == 1) as usize;
+            }
+        }
+    }
+}
+
+/// Records the errors of a deserializer in recovery mode.
+fn finish<E: Into<DecodeError>>(
+    value: Result<Option<LuaValue>, E>,
+    recovery_error: Option<E>,
+    issues: &mut Vec<DecodeError>,
+) -> Option<LuaValue> {
+    issues.extend(recovery_error.map(Into::into));
+    value.unwrap_or_else(|e| {
+        issues.push(e.into());
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecodeErrorKind, OutputStringVersion, encode};
+
+    fn encoded(version: OutputStringVersion) -> String {
+        let value = LuaValue::Array((1..=100).map(|i| format!("print({i})").into()).collect());
+        encode(&value, version).unwrap()
+    }
+
+    fn elements(result: &PartialDecode) -> &[LuaValue] {
+        match result.value() {
+            Some(LuaValue::Array(elements)) => elements,
+            value => panic!("expected an array, got {value:?}"),
+        }
+    }
+
+    #[test]
+    fn complete_strings_have_no_issues() {
+        for version in [
+            OutputStringVersion::Deflate,
+            OutputStringVersion::BinarySerialization,
+        ] {
+            let result = decode_partial(encoded(version).as_bytes(), None).unwrap();
+            assert!(result.is_complete());
+            assert_eq!(elements(&result).len(), 100);
+        }
+    }
+
+    #[test]
+    fn complete_strings_decode_like_decode() {
+        use weakauras_codec_lua_value::{LuaMap, LuaMapKey};
+
+        let key = |k: LuaValue| LuaMapKey::try_from(k).unwrap();
+        let table = |entries: Vec<(LuaValue, LuaValue)>| {
+            LuaValue::Map(LuaMap::from_iter(
+                entries.into_iter().map(|(k, v)| (key(k), v)),
+            ))
+        };
+        let values = [
+            table(vec![(1.0.into(), "a".into()), (2.0.into(), "b".into())]),
+            table(vec![
+                (1.0.into(), "a".into()),
+                (2.0.into(), "b".into()),
+                ("x".into(), 1.0.into()),
+            ]),
+            table(vec![(1.0.into(), "a".into()), (3.0.into(), "c".into())]),
+        ];
+
+        for value in values {
+            #[cfg_attr(not(feature = "legacy-strings-decoding"), allow(unused_mut))]
+            let mut strings = vec![
+                encode(&value, OutputStringVersion::Deflate).unwrap(),
+                encode(&value, OutputStringVersion::BinarySerialization).unwrap(),
+            ];
+            #[cfg(feature = "legacy-strings-decoding")]
+            {
+                use weakauras_codec_ace_serialize::Serializer as LegacySerializer;
+
+                // LibCompress stores short data uncompressed, after a 0x01 byte.
+                let mut compressed = vec![1];
+                compressed.extend(LegacySerializer::serialize_one(&value, None).unwrap());
+                strings.push(weakauras_codec_base64::encode_to_string(&compressed).unwrap());
+            }
+
+            for string in strings {
+                let result = decode_partial(string.as_bytes(), None).unwrap();
+                assert!(result.is_complete(), "{string}");
+                // Tables compare by identity.
+                assert_eq!(
+                    format!("{:?}", result.value()),
+                    format!("{:?}", crate::decode(string.as_bytes(), None).unwrap()),
+                    "{string}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lone_trailing_characters_are_dropped() {
+        let encoded = encoded(OutputStringVersion::BinarySerialization);
+        let data = &encoded.as_bytes()[..6 + 4 * 20 + 1];
+
+        let result = decode_partial(data, None).unwrap();
+        let issues = result.issues();
+        assert!(matches!(
+            issues[0].kind(),
+            DecodeErrorKind::Base64DecodeError(Base64DecodeError::InvalidLength)
+        ));
+        assert_eq!(issues[0].original_offset(), Some(data.len()));
+
+        // The remaining 60 bytes are not a complete DEFLATE stream.
+        assert!(matches!(issues[1].kind(), DecodeErrorKind::IoError(_)));
+        assert!(issues[1].original_offset().unwrap() < data.len());
+        assert!(!elements(&result).is_empty());
+        assert_eq!(elements(&result)[0], "print(1)".into());
+    }
+
+    #[test]
+    fn data_before_invalid_characters_is_decoded() {
+        let encoded = encoded(OutputStringVersion::Deflate);
+        let mut data = encoded.into_bytes();
+        data[1 + 80] = b'*';
+
+        let result = decode_partial(&data, None).unwrap();
+        let issues = result.issues();
+        assert!(matches!(
+            issues[0].kind(),
+            DecodeErrorKind::Base64DecodeError(Base64DecodeError::InvalidByte(80))
+        ));
+        assert_eq!(issues[0].original_offset(), Some(1 + 80));
+        assert!(issues.len() > 1);
+
+        let elements = elements(&result);
+        assert!(!elements.is_empty() && elements.len() < 100);
+    }
+
+    #[test]
+    fn data_inflated_before_an_error_is_deserialized() {
+        for (version, prefix_len) in [
+            (OutputStringVersion::Deflate, 1),
+            (OutputStringVersion::BinarySerialization, 6),
+        ] {
+            let encoded = encoded(version);
+            // A whole number of base64 quadruples, so that only inflating fails.
+            let data = &encoded.as_bytes()[..prefix_len + (encoded.len() - prefix_len) / 8 * 4];
+
+            let result = decode_partial(data, None).unwrap();
+            let issues = result.issues();
+            assert!(matches!(issues[0].kind(), DecodeErrorKind::IoError(_)));
+            assert!(issues[0].original_offset().unwrap() <= data.len());
+            assert!(issues.len() > 1);
+
+            let elements = elements(&result);
+            assert!(!elements.is_empty() && elements.len() < 100);
+            assert_eq!(elements[0], "print(1)".into());
+        }
+    }
+
+    #[test]
+    fn exceeding_max_size_truncates_the_data() {
+        let encoded = encoded(OutputStringVersion::BinarySerialization);
+
+        let result = decode_partial(encoded.as_bytes(), Some(256)).unwrap();
+        assert!(matches!(
+            result.issues()[0].kind(),
+            DecodeErrorKind::DataExceedsMaxSize
+        ));
+
+        let elements = elements(&result);
+        assert!(!elements.is_empty() && elements.len() < 100);
+    }
+
+    #[test]
+    fn only_invalid_prefixes_are_errors() {
+        #[cfg(not(feature = "legacy-strings-decoding"))]
+        assert!(matches!(
+            decode_partial(b"WA:2!abcd", None)
+                .map(|_| ())
+                .unwrap_err()
+                .kind(),
+            DecodeErrorKind::InvalidPrefix
+        ));
+
+        let result = decode_partial(b"!", None).unwrap();
+        assert!(result.value().is_none());
+        assert!(!result.is_complete());
+    }
+
+    #[cfg(feature = "legacy-strings-decoding")]
+    #[test]
+    fn legacy_strings_are_not_salvaged_past_decompression_errors() {
+        // Decodes to a LibCompress header with an unknown method.
+        let result = decode_partial(b"aaaa", None).unwrap();
+        assert!(result.value().is_none());
+        assert!(matches!(
+            result.issues(),
+            [issue] if matches!(issue.kind(), DecodeErrorKind::LibCompressDecompressionError(_))
+        ));
+    }
+}
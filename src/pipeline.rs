@@ -0,0 +1,613 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+//! Stages of encoding and decoding, and pipelines composing them.
+//!
+//! A WeakAuras-compatible string is a prefix followed by text-encoded, compressed,
+//! serialized data. [Pipeline] describes such a format with a [TextEncoding],
+//! a [Compression] and a [Serialization], so that formats of other addons,
+//! experimental combinations and test doubles can be decoded and encoded
+//! the same way [decode](crate::decode) and [encode](crate::encode) do.
+//!
+//! # Example
+//!
+//! ```
+//! use weakauras_codec::pipeline::{self, AceSerialize, Base64, Deflate, Pipeline};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let value = "Hello, world!".into();
+//!
+//!     let encoded = pipeline::BINARY_SERIALIZATION.encode(&value)?;
+//!     assert_eq!(weakauras_codec::decode(encoded.as_bytes(), None)?.unwrap(), value);
+//!
+//!     // A format that is not used by WeakAuras.
+//!     let other = Pipeline::new(Base64, Deflate, AceSerialize).with_prefix("!OTHER:1!");
+//!     let encoded = other.encode(&value)?;
+//!     assert!(encoded.starts_with("!OTHER:1!"));
+//!     assert_eq!(other.decode(encoded.as_bytes(), None)?.unwrap(), value);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    DEFAULT_MAX_SIZE, DecodeError, DecodeErrorKind, EncodeError, LuaValue, MapBackend,
+    NormalizationPolicy, ResourceLimits, base64_len, inflate,
+};
+use flate2::write::DeflateEncoder;
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+use weakauras_codec_ace_serialize::{
+    Deserializer as LegacyDeserializer, Serializer as LegacySerializer,
+};
+use weakauras_codec_base64::{EncoderWriter, error::DecodeError as Base64DecodeError};
+use weakauras_codec_lib_serialize::{Deserializer, SerializeOptions, Serializer};
+
+/// A writer that holds back some of the data written into it until it is finished,
+/// such as a compressor or a base64 encoder.
+pub trait FinishWrite: Write {
+    /// The underlying writer.
+    type Inner;
+
+    /// Write the held back data and return the underlying writer.
+    fn finish(self) -> io::Result<Self::Inner>;
+}
+
+impl<W: Write> FinishWrite for EncoderWriter<W> {
+    type Inner = W;
+
+    fn finish(self) -> io::Result<W> {
+        EncoderWriter::finish(self)
+    }
+}
+
+impl<W: Write> FinishWrite for DeflateEncoder<W> {
+    type Inner = W;
+
+    fn finish(self) -> io::Result<W> {
+        DeflateEncoder::finish(self)
+    }
+}
+
+/// A way of representing binary data as text.
+pub trait TextEncoding {
+    /// Returns a writer that encodes data as it is written and passes the text to `writer`.
+    ///
+    /// The text must be valid UTF-8.
+    fn encoder<W: Write>(&self, writer: W) -> impl FinishWrite<Inner = W>;
+
+    /// Decodes `text`.
+    ///
+    /// Errors [located](DecodeError::located_at) in `text` are relocated in the original string
+    /// by [Pipeline].
+    fn decode(&self, text: &[u8]) -> Result<Vec<u8>, DecodeError>;
+
+    /// Returns the number of characters that encode the first `len` bytes.
+    ///
+    /// This is used to relocate errors of [Compression] in the original string.
+    fn encoded_len(&self, len: usize) -> usize;
+}
+
+/// A way of compressing serialized data.
+pub trait Compression {
+    /// Returns a writer that compresses data as it is written and passes it to `writer`.
+    fn compressor<W: Write>(&self, writer: W) -> impl FinishWrite<Inner = W>;
+
+    /// Decompresses at most `max_size` bytes of `data`.
+    ///
+    /// Errors [located](DecodeError::located_at) in `data` are relocated in the original string
+    /// by [Pipeline], using [TextEncoding::encoded_len].
+    fn decompress<'a>(&self, data: &'a [u8], max_size: usize)
+    -> Result<Cow<'a, [u8]>, DecodeError>;
+}
+
+/// A way of serializing a [LuaValue].
+pub trait Serialization {
+    /// Serializes `value` into `writer`, passing the output along as it is produced.
+    fn serialize_into(&self, value: &LuaValue, writer: &mut dyn Write) -> Result<(), EncodeError>;
+
+    /// Deserializes the first value of `data`, enforcing `limits`
+    /// and producing maps implemented by `map_backend`.
+    ///
+    /// Errors are expected to be located in `data`.
+    fn deserialize(
+        &self,
+        data: &[u8],
+        limits: ResourceLimits,
+        map_backend: MapBackend,
+    ) -> Result<Option<LuaValue>, DecodeError>;
+}
+
+/// Base64 using the alphabet of WeakAuras, see [weakauras_codec_base64].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Base64;
+
+impl TextEncoding for Base64 {
+    fn encoder<W: Write>(&self, writer: W) -> impl FinishWrite<Inner = W> {
+        EncoderWriter::new(writer)
+    }
+
+    fn decode(&self, text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        weakauras_codec_base64::decode_to_vec(text).map_err(|e| {
+            let offset = match e {
+                Base64DecodeError::InvalidByte(invalid_byte_at) => invalid_byte_at,
+                Base64DecodeError::InvalidLength => text.len(),
+            };
+
+            DecodeError::from(e).located_at(text, offset)
+        })
+    }
+
+    fn encoded_len(&self, len: usize) -> usize {
+        base64_len(len)
+    }
+}
+
+/// Raw DEFLATE, see [flate2].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Deflate;
+
+impl Compression for Deflate {
+    fn compressor<W: Write>(&self, writer: W) -> impl FinishWrite<Inner = W> {
+        DeflateEncoder::new(writer, flate2::Compression::best())
+    }
+
+    fn decompress<'a>(
+        &self,
+        data: &'a [u8],
+        max_size: usize,
+    ) -> Result<Cow<'a, [u8]>, DecodeError> {
+        let inflated = inflate(data, max_size);
+        match inflated.error {
+            // Located where the inflater stopped reading.
+            Some(kind) => Err(DecodeError::from(kind).located_at(data, inflated.consumed)),
+            None => Ok(Cow::Owned(inflated.data)),
+        }
+    }
+}
+
+/// LibCompress, see [weakauras_codec_lib_compress].
+///
+/// Since only decompression is implemented by that crate, data is "compressed"
+/// using the method that stores it as it is, which LibCompress can decompress.
+#[cfg(feature = "legacy-strings-decoding")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LibCompress;
+
+#[cfg(feature = "legacy-strings-decoding")]
+impl Compression for LibCompress {
+    fn compressor<W: Write>(&self, writer: W) -> impl FinishWrite<Inner = W> {
+        StoredWriter {
+            writer,
+            method_written: false,
+        }
+    }
+
+    fn decompress<'a>(
+        &self,
+        data: &'a [u8],
+        max_size: usize,
+    ) -> Result<Cow<'a, [u8]>, DecodeError> {
+        Ok(weakauras_codec_lib_compress::decompress(data, max_size)?)
+    }
+}
+
+/// A writer of data stored by LibCompress without compression, see [LibCompress].
+#[cfg(feature = "legacy-strings-decoding")]
+struct StoredWriter<W> {
+    writer: W,
+    method_written: bool,
+}
+
+#[cfg(feature = "legacy-strings-decoding")]
+impl<W: Write> StoredWriter<W> {
+    fn write_method(&mut self) -> io::Result<()> {
+        if !self.method_written {
+            self.writer.write_all(&[1])?;
+            self.method_written = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "legacy-strings-decoding")]
+impl<W: Write> Write for StoredWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_method()?;
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "legacy-strings-decoding")]
+impl<W: Write> FinishWrite for StoredWriter<W> {
+    type Inner = W;
+
+    fn finish(mut self) -> io::Result<W> {
+        self.write_method()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// The policy [AceSerialize] and [LibSerialize] deserialize with, so that
+/// the same table has the same shape regardless of the serialization.
+const NORMALIZATION: NormalizationPolicy = NormalizationPolicy::PreferArrays;
+
+/// AceSerialize, see [weakauras_codec_ace_serialize].
+///
+/// Tables are deserialized with [NormalizationPolicy::PreferArrays].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AceSerialize;
+
+impl Serialization for AceSerialize {
+    fn serialize_into(&self, value: &LuaValue, writer: &mut dyn Write) -> Result<(), EncodeError> {
+        Ok(LegacySerializer::serialize_one_into(value, writer)?)
+    }
+
+    fn deserialize(
+        &self,
+        data: &[u8],
+        limits: ResourceLimits,
+        map_backend: MapBackend,
+    ) -> Result<Option<LuaValue>, DecodeError> {
+        Ok(Self::deserializer(data, limits, map_backend).deserialize_first()?)
+    }
+}
+
+impl AceSerialize {
+    /// Creates the deserializer used by [AceSerialize::deserialize].
+    pub(crate) fn deserializer(
+        data: &[u8],
+        limits: ResourceLimits,
+        map_backend: MapBackend,
+    ) -> LegacyDeserializer<'_> {
+        LegacyDeserializer::from_bytes(data)
+            .with_normalization(NORMALIZATION)
+            .with_map_backend(map_backend)
+            .with_limits(limits)
+    }
+}
+
+/// LibSerialize, see [weakauras_codec_lib_serialize].
+///
+/// Tables are deserialized with [NormalizationPolicy::PreferArrays].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LibSerialize;
+
+impl Serialization for LibSerialize {
+    fn serialize_into(&self, value: &LuaValue, writer: &mut dyn Write) -> Result<(), EncodeError> {
+        Ok(Serializer::serialize_one_into(
+            value,
+            writer,
+            SerializeOptions::new(),
+        )?)
+    }
+
+    fn deserialize(
+        &self,
+        data: &[u8],
+        limits: ResourceLimits,
+        map_backend: MapBackend,
+    ) -> Result<Option<LuaValue>, DecodeError> {
+        Ok(Self::deserializer(data, limits, map_backend).deserialize_first()?)
+    }
+}
+
+impl LibSerialize {
+    /// Creates the deserializer used by [LibSerialize::deserialize].
+    pub(crate) fn deserializer(
+        data: &[u8],
+        limits: ResourceLimits,
+        map_backend: MapBackend,
+    ) -> Deserializer<'_> {
+        Deserializer::from_slice(data)
+            .with_normalization(NORMALIZATION)
+            .with_map_backend(map_backend)
+            .with_limits(limits)
+    }
+}
+
+/// A format of strings: an optional prefix followed by text-encoded,
+/// compressed, serialized data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pipeline<T, C, S> {
+    prefix: Option<&'static str>,
+    map_backend: MapBackend,
+    text_encoding: T,
+    compression: C,
+    serialization: S,
+}
+
+impl<T, C, S> Pipeline<T, C, S> {
+    /// Create a pipeline of the given stages without a prefix.
+    pub const fn new(text_encoding: T, compression: C, serialization: S) -> Self {
+        Self {
+            prefix: None,
+            map_backend: MapBackend::BTreeMap,
+            text_encoding,
+            compression,
+            serialization,
+        }
+    }
+
+    /// Set the prefix strings start with.
+    pub const fn with_prefix(mut self, prefix: &'static str) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Get the prefix strings start with.
+    pub fn prefix(&self) -> Option<&'static str> {
+        self.prefix
+    }
+
+    /// Set the implementation of [LuaValue::Map]s produced by decoding.
+    ///
+    /// The default is [MapBackend::BTreeMap].
+    pub const fn with_map_backend(mut self, backend: MapBackend) -> Self {
+        self.map_backend = backend;
+        self
+    }
+
+    /// Get the implementation of [LuaValue::Map]s produced by decoding.
+    pub fn map_backend(&self) -> MapBackend {
+        self.map_backend
+    }
+
+    /// Check whether `data` starts with the prefix.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.starts_with(self.prefix.unwrap_or_default().as_bytes())
+    }
+}
+
+impl<T: TextEncoding, C: Compression, S: Serialization> Pipeline<T, C, S> {
+    /// Decodes a string and returns a [LuaValue], see [decode](crate::decode).
+    pub fn decode(
+        &self,
+        data: &[u8],
+        max_size: Option<usize>,
+    ) -> Result<Option<LuaValue>, DecodeError> {
+        self.decode_with_limits(data, max_size, ResourceLimits::default())
+    }
+
+    /// Decodes a string and returns a [LuaValue], enforcing `limits` while deserializing,
+    /// see [decode_with_limits](crate::decode_with_limits).
+    pub fn decode_with_limits(
+        &self,
+        data: &[u8],
+        max_size: Option<usize>,
+        limits: ResourceLimits,
+    ) -> Result<Option<LuaValue>, DecodeError> {
+        if !self.matches(data) {
+            return Err(DecodeError::from(DecodeErrorKind::InvalidPrefix).located_at(data, 0));
+        }
+        let prefix_len = self.prefix.map_or(0, str::len);
+
+        let compressed_data = self
+            .text_encoding
+            .decode(&data[prefix_len..])
+            .map_err(|e| relocate(e, data, |offset| prefix_len + offset))?;
+
+        let max_size = max_size.unwrap_or(DEFAULT_MAX_SIZE);
+        let decoded = self
+            .compression
+            .decompress(&compressed_data, max_size)
+            .map_err(|e| {
+                relocate(e, data, |offset| {
+                    prefix_len + self.text_encoding.encoded_len(offset)
+                })
+            })?;
+
+        self.serialization
+            .deserialize(&decoded, limits, self.map_backend)
+    }
+
+    /// Encodes a [LuaValue] into a string, see [encode](crate::encode).
+    pub fn encode(&self, value: &LuaValue) -> Result<String, EncodeError> {
+        let encoded = self.encode_into(value, Vec::new())?;
+        String::from_utf8(encoded).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
+    }
+
+    /// Encodes a [LuaValue] into `writer` and returns it, see [encode](crate::encode).
+    ///
+    /// Serialized data is compressed and encoded as it is produced,
+    /// so neither the serialized nor the compressed data is held in memory as a whole.
+    pub fn encode_into<W: Write>(&self, value: &LuaValue, mut writer: W) -> Result<W, EncodeError> {
+        writer.write_all(self.prefix.unwrap_or_default().as_bytes())?;
+
+        let encoder = self.text_encoding.encoder(writer);
+        let mut compressor = self.compression.compressor(encoder);
+        self.serialization.serialize_into(value, &mut compressor)?;
+        Ok(compressor.finish()?.finish()?)
+    }
+}
+
+/// Moves the location of an error in the input of a stage to the original string.
+fn relocate(e: DecodeError, data: &[u8], original_offset: impl Fn(usize) -> usize) -> DecodeError {
+    match e.original_offset() {
+        Some(offset) => e.located_at(data, original_offset(offset)),
+        None => e,
+    }
+}
+
+/// `!` + base64-string of DEFLATE-compressed data serialized by AceSerialize.
+pub const DEFLATE: Pipeline<Base64, Deflate, AceSerialize> =
+    Pipeline::new(Base64, Deflate, AceSerialize).with_prefix("!");
+
+/// `!WA:2!` + base64-string of DEFLATE-compressed data serialized by LibSerialize.
+pub const BINARY_SERIALIZATION: Pipeline<Base64, Deflate, LibSerialize> =
+    Pipeline::new(Base64, Deflate, LibSerialize).with_prefix("!WA:2!");
+
+/// Base64-string of data compressed by LibCompress and serialized by AceSerialize.
+#[cfg(feature = "legacy-strings-decoding")]
+pub const LEGACY: Pipeline<Base64, LibCompress, AceSerialize> =
+    Pipeline::new(Base64, LibCompress, AceSerialize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    /// The number of bytes [Chunks] serializes.
+    const SERIALIZED_LEN: usize = 256 * 1024;
+
+    /// Serializes any value into incompressible chunks, recording how many bytes
+    /// had reached the end of the pipeline before the last chunk was written.
+    struct Chunks {
+        written: Rc<Cell<usize>>,
+        written_before_last_chunk: Cell<usize>,
+    }
+
+    impl Serialization for Chunks {
+        fn serialize_into(&self, _: &LuaValue, writer: &mut dyn Write) -> Result<(), EncodeError> {
+            let mut state = 0x2545_f491_u32;
+            let mut chunk = [0; 1024];
+            for i in 0..SERIALIZED_LEN / chunk.len() {
+                chunk.fill_with(|| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                });
+                if i == SERIALIZED_LEN / chunk.len() - 1 {
+                    self.written_before_last_chunk.set(self.written.get());
+                }
+                writer.write_all(&chunk)?;
+            }
+            Ok(())
+        }
+
+        fn deserialize(
+            &self,
+            _: &[u8],
+            _: ResourceLimits,
+            _: MapBackend,
+        ) -> Result<Option<LuaValue>, DecodeError> {
+            Ok(None)
+        }
+    }
+
+    /// Counts the bytes written into it without keeping them.
+    struct Counter(Rc<Cell<usize>>);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.set(self.0.get() + buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encoding_streams_through_every_stage() {
+        let written = Rc::new(Cell::new(0));
+        let serialization = Chunks {
+            written: Rc::clone(&written),
+            written_before_last_chunk: Cell::new(0),
+        };
+        let pipeline = Pipeline::new(Base64, Deflate, serialization).with_prefix("!TEST!");
+
+        pipeline
+            .encode_into(&LuaValue::Null, Counter(Rc::clone(&written)))
+            .unwrap();
+
+        // Incompressible data grows by a third when it is base64-encoded.
+        let min_len = 6 + SERIALIZED_LEN * 4 / 3;
+        assert!(written.get() > min_len, "{}", written.get());
+        // All but the last few blocks of DEFLATE data must have been encoded
+        // by the time the last chunk was serialized.
+        let before_last_chunk = pipeline.serialization.written_before_last_chunk.get();
+        assert!(
+            before_last_chunk > min_len - 64 * 1024,
+            "{before_last_chunk}"
+        );
+    }
+
+    #[test]
+    fn encoded_strings_start_with_the_prefix() {
+        let value = LuaValue::from("Hello, world!");
+        let encoded = BINARY_SERIALIZATION.encode(&value).unwrap();
+        let written = BINARY_SERIALIZATION
+            .encode_into(&value, Vec::new())
+            .unwrap();
+        assert_eq!(encoded.as_bytes(), written);
+        assert!(encoded.starts_with("!WA:2!"));
+        assert_eq!(
+            BINARY_SERIALIZATION.decode(&written, None).unwrap(),
+            Some(value)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "lua-value-indexmap")]
+    fn every_format_decodes_into_the_requested_backend() {
+        use weakauras_codec_lua_value::{LuaMap, LuaMapKey};
+
+        let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+        let inner = LuaMap::from_iter([(key("y"), 1.0.into())]);
+        let value = LuaValue::Map(LuaMap::from_iter([
+            (key("b"), true.into()),
+            (key("a"), LuaValue::Map(inner)),
+        ]));
+
+        let check = |decoded: Option<LuaValue>| {
+            let Some(LuaValue::Map(m)) = decoded else {
+                panic!("expected a map, got {decoded:?}");
+            };
+            assert_eq!(m.backend(), MapBackend::IndexMap);
+            assert!(matches!(
+                m.get(&key("a")),
+                Some(LuaValue::Map(inner)) if inner.backend() == MapBackend::IndexMap
+            ));
+        };
+
+        let binary = BINARY_SERIALIZATION.with_map_backend(MapBackend::IndexMap);
+        check(
+            binary
+                .decode(binary.encode(&value).unwrap().as_bytes(), None)
+                .unwrap(),
+        );
+        let deflate = DEFLATE.with_map_backend(MapBackend::IndexMap);
+        check(
+            deflate
+                .decode(deflate.encode(&value).unwrap().as_bytes(), None)
+                .unwrap(),
+        );
+        #[cfg(feature = "legacy-strings-decoding")]
+        {
+            let legacy = LEGACY.with_map_backend(MapBackend::IndexMap);
+            check(
+                legacy
+                    .decode(legacy.encode(&value).unwrap().as_bytes(), None)
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(BINARY_SERIALIZATION.map_backend(), MapBackend::BTreeMap);
+    }
+
+    #[test]
+    #[cfg(feature = "legacy-strings-decoding")]
+    fn data_is_stored_after_the_compression_method() {
+        let value = LuaValue::from("Hello, world!");
+        let encoded = LEGACY.encode(&value).unwrap();
+        let compressed = weakauras_codec_base64::decode_to_vec(encoded.as_bytes()).unwrap();
+        assert_eq!(compressed, b"\x01^1^SHello,~`world!^^");
+        assert_eq!(
+            LEGACY.decode(encoded.as_bytes(), None).unwrap(),
+            Some(value)
+        );
+
+        let mut compressor = LibCompress.compressor(Vec::new());
+        compressor.flush().unwrap();
+        assert_eq!(compressor.finish().unwrap(), b"\x01");
+    }
+}
@@ -0,0 +1,508 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    DEFAULT_MAX_SIZE, DecodeError, DecodeErrorKind, LuaValue, StringVersion, base64_len,
+    decode_partial, inflate, inflate_stepwise, partial::salvage_base64, split_prefix,
+};
+use core::fmt;
+use weakauras_codec_ace_serialize::Deserializer as LegacyDeserializer;
+use weakauras_codec_base64::{ALPHABET, error::DecodeError as Base64DecodeError};
+use weakauras_codec_lib_serialize::Deserializer;
+use weakauras_codec_lua_value::LuaMapKey;
+
+/// The number of characters before the failure point where edits are tried.
+///
+/// Corruption usually surfaces a few bytes after the damaged character,
+/// since the inflater only notices it once the bits it produced stop making sense.
+const SEARCH_BEHIND: usize = 32;
+/// The number of characters after the failure point where edits are tried.
+const SEARCH_AHEAD: usize = 8;
+/// The maximum number of edited strings [repair] tries to decode.
+const MAX_ATTEMPTS: usize = 1024;
+
+/// Groups of characters that are easily mistaken for one another, e.g. by OCR.
+const CONFUSABLES: &[&[u8]] = &[
+    b"lI1|!i", b"O0oQD", b"S5s", b"B8", b"Z2z", b"G6", b"g9q", b"uv", b"cC", b"kK", b"pP", b"wW",
+    b"xX", b"([{<", b")]}>",
+];
+
+/// A single-character edit made by [repair].
+///
+/// Offsets are within the original string. A character is a UTF-8 sequence
+/// if the string holds a valid one at the offset and a single byte otherwise,
+/// so that e.g. a smart quote can be replaced as a whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// The character of `len` bytes at `offset` was replaced with `byte`.
+    Substitution {
+        /// The offset of the character.
+        offset: usize,
+        /// The length of the character in bytes.
+        len: usize,
+        /// The byte it was replaced with.
+        byte: u8,
+    },
+    /// `byte` was inserted at `offset`.
+    Insertion {
+        /// The offset the byte was inserted at.
+        offset: usize,
+        /// The inserted byte.
+        byte: u8,
+    },
+    /// The character of `len` bytes at `offset` was removed.
+    Deletion {
+        /// The offset of the character.
+        offset: usize,
+        /// The length of the character in bytes.
+        len: usize,
+    },
+}
+
+impl Edit {
+    /// Apply the edit to `data`.
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        let (offset, len, byte) = match *self {
+            Edit::Substitution { offset, len, byte } => (offset, len, Some(byte)),
+            Edit::Insertion { offset, byte } => (offset, 0, Some(byte)),
+            Edit::Deletion { offset, len } => (offset, len, None),
+        };
+
+        let mut result = Vec::with_capacity(data.len() + 1);
+        result.extend_from_slice(&data[..offset]);
+        result.extend(byte);
+        result.extend_from_slice(&data[offset + len..]);
+        result
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Edit::Substitution { offset, byte, .. } => {
+                write!(
+                    f,
+                    "replaced the character at offset {offset} with '{}'",
+                    byte as char
+                )
+            }
+            Edit::Insertion { offset, byte } => {
+                write!(f, "inserted '{}' at offset {offset}", byte as char)
+            }
+            Edit::Deletion { offset, .. } => {
+                write!(f, "removed the character at offset {offset}")
+            }
+        }
+    }
+}
+
+/// The result of a successful [repair].
+#[derive(Debug)]
+pub struct Repair {
+    value: LuaValue,
+    edit: Option<Edit>,
+    repaired: Vec<u8>,
+}
+
+impl Repair {
+    /// Get the decoded value.
+    pub fn value(&self) -> &LuaValue {
+        &self.value
+    }
+
+    /// Convert the result into the decoded value.
+    pub fn into_value(self) -> LuaValue {
+        self.value
+    }
+
+    /// Get the edit that made the string decode, `None` if it decoded as it was.
+    pub fn edit(&self) -> Option<Edit> {
+        self.edit
+    }
+
+    /// Get the string with the edit applied.
+    pub fn repaired(&self) -> &[u8] {
+        &self.repaired
+    }
+}
+
+/// Searches for a single-character edit that makes a damaged WeakAuras-compatible string
+/// decode into an export.
+///
+/// A character outside the base64 alphabet is tried with substitutions by every character
+/// of the alphabet and with its removal. Otherwise, the search starts at the character
+/// holding the compressed byte where the inflater stopped, see [decode_partial].
+/// Going back from there, and then a few characters ahead, each position is tried
+/// with substitutions, with the removal of the character and with insertions
+/// of every character of the alphabet.
+///
+/// The likely edits, i.e. substitutions by the characters that are easily mistaken
+/// for the damaged one, such as `l`, `I` and `1`, and removals, are tried at every position
+/// before the other ones.
+///
+/// The first edit that produces a string which [decodes](crate::decode) into an export
+/// is returned. A value is considered an export if it is a table whose `d` is
+/// an aura, i.e. a table with a string `id` and `regionType`, and whose `c`,
+/// if present, is an array of auras.
+/// Returns `None` if no edit near the failure point works, or if the string
+/// does not start with a valid prefix.
+///
+/// More than one edit often works, and raw DEFLATE data has no checksum
+/// to tell them apart, so the value should be reviewed before it is used.
+///
+/// # Cost
+///
+/// Every edit is tried by decoding the whole edited string, which is usually rejected
+/// once the inflater reaches the damage. At most 1024 edits are tried, so a call costs
+/// up to about as much as 1024 calls to [decode](crate::decode) on a string
+/// that is damaged near its end.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::{Edit, LuaValue, OutputStringVersion, decode, encode, repair};
+/// use weakauras_codec_lua_value::{LuaMap, LuaMapKey};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+///     let data = LuaMap::from_iter([(key("id"), "Aura".into()), (key("regionType"), "icon".into())]);
+///     let export = LuaValue::Map(LuaMap::from_iter([(key("d"), LuaValue::Map(data))]));
+///     let encoded = encode(&export, OutputStringVersion::BinarySerialization)?;
+///
+///     // A character was misread by OCR.
+///     let damaged = encoded.replacen('l', "|", 1);
+///     assert!(decode(damaged.as_bytes(), None).is_err());
+///
+///     let repair = repair(damaged.as_bytes(), None).unwrap();
+///     assert!(matches!(repair.edit(), Some(Edit::Substitution { byte: b'l', .. })));
+///     assert_eq!(repair.repaired(), encoded.as_bytes());
+///     Ok(())
+/// }
+/// ```
+pub fn repair(data: &[u8], max_size: Option<usize>) -> Option<Repair> {
+    let (prefix_len, version) = split_prefix(data).ok()?;
+
+    if let Some(value) = decode_export(data, max_size) {
+        return Some(Repair {
+            value,
+            edit: None,
+            repaired: data.to_vec(),
+        });
+    }
+
+    let partial = decode_partial(data, max_size).ok()?;
+    let invalid_byte = partial.issues().iter().find(|issue| {
+        matches!(
+            issue.kind(),
+            DecodeErrorKind::Base64DecodeError(Base64DecodeError::InvalidByte(_))
+        )
+    });
+    let offsets: Vec<usize> = match invalid_byte {
+        // A character outside the alphabet is the damaged one.
+        Some(issue) => vec![issue.original_offset()?],
+        None => {
+            let failure = failure_point(data, prefix_len, version, partial.issues())?;
+            let behind = (prefix_len..=failure).rev().take(SEARCH_BEHIND + 1);
+            let ahead = (failure + 1..=data.len()).take(SEARCH_AHEAD);
+            behind.chain(ahead).collect()
+        }
+    };
+
+    let likely = offsets
+        .iter()
+        .flat_map(|&offset| likely_edits(data, offset));
+    let other = offsets.iter().flat_map(|&offset| other_edits(data, offset));
+    for edit in likely.chain(other).take(MAX_ATTEMPTS) {
+        let repaired = edit.apply(data);
+        if let Some(value) = decode_export(&repaired, max_size) {
+            return Some(Repair {
+                value,
+                edit: Some(edit),
+                repaired,
+            });
+        }
+    }
+
+    None
+}
+
+/// Returns the earliest offset in `data` where one of the layers noticed the damage.
+///
+/// Deserialization errors are mapped back to the character holding the compressed byte
+/// the inflater consumed last before producing the offending byte, since garbage
+/// produced by the inflater is often noticed by the deserializer first.
+fn failure_point(
+    data: &[u8],
+    prefix_len: usize,
+    #[cfg_attr(not(feature = "legacy-strings-decoding"), allow(unused_variables))]
+    version: StringVersion,
+    issues: &[DecodeError],
+) -> Option<usize> {
+    let compressed_data = salvage_base64(&data[prefix_len..], |_, _| {});
+
+    issues
+        .iter()
+        .filter_map(|issue| match issue.original_offset() {
+            Some(offset) => Some(offset),
+            // LibCompress does not let decompressed offsets be mapped back.
+            #[cfg(feature = "legacy-strings-decoding")]
+            None if version == StringVersion::Legacy => None,
+            None => {
+                let consumed = inflate_stepwise(&compressed_data, issue.location()?.offset()).1;
+                Some(prefix_len + base64_len(consumed))
+            }
+        })
+        .min()
+}
+
+/// Returns the likely edits to try at `offset`: substitutions by the characters
+/// the one at `offset` is easily mistaken for, and then its removal.
+fn likely_edits(data: &[u8], offset: usize) -> impl Iterator<Item = Edit> + '_ {
+    let len = char_len(data, offset);
+    let similar = similar_characters(&data[offset..offset + len]);
+
+    let substitutions = similar
+        .iter()
+        .filter(move |byte| ALPHABET.contains(byte) && data[offset..offset + len] != [**byte])
+        .map(move |&byte| Edit::Substitution { offset, len, byte });
+    let deletion = (len > 0).then_some(Edit::Deletion { offset, len });
+
+    substitutions.chain(deletion)
+}
+
+/// Returns the rest of the edits to try at `offset`, see [likely_edits]:
+/// substitutions by other characters of the alphabet, and then insertions.
+fn other_edits(data: &[u8], offset: usize) -> impl Iterator<Item = Edit> + '_ {
+    let len = char_len(data, offset);
+    let current = &data[offset..offset + len];
+    let similar = similar_characters(current);
+
+    let substitutions = ALPHABET
+        .iter()
+        .filter(move |byte| len > 0 && !similar.contains(byte) && current != [**byte])
+        .map(move |&byte| Edit::Substitution { offset, len, byte });
+    let insertions = ALPHABET
+        .iter()
+        .map(move |&byte| Edit::Insertion { offset, byte });
+
+    substitutions.chain(insertions)
+}
+
+/// Returns the characters that are easily mistaken for `current`, including itself.
+fn similar_characters(current: &[u8]) -> &'static [u8] {
+    match current {
+        [byte] => CONFUSABLES
+            .iter()
+            .find(|group| group.contains(byte))
+            .map_or(&[], |group| group),
+        _ => &[],
+    }
+}
+
+/// Returns the length of the character at `offset`, 0 if `offset` is at the end
+/// or in the middle of a UTF-8 sequence.
+fn char_len(data: &[u8], offset: usize) -> usize {
+    let Some(&first) = data.get(offset) else {
+        return 0;
+    };
+
+    // Continuation bytes are edited along with the start of their sequence.
+    if (0x80..=0xBF).contains(&first)
+        && (1..=offset.min(3)).any(|back| sequence_len(data, offset - back) > back)
+    {
+        return 0;
+    }
+
+    sequence_len(data, offset)
+}
+
+/// Returns the length of the valid UTF-8 sequence at `offset`, 1 if there is none.
+fn sequence_len(data: &[u8], offset: usize) -> usize {
+    let len = match data[offset] {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    };
+
+    match data.get(offset..offset + len).map(str::from_utf8) {
+        Some(Ok(_)) => len,
+        _ => 1,
+    }
+}
+
+/// Decodes `data` and returns the value if it is an export.
+///
+/// Unlike [decode](crate::decode), this requires each layer to consume all of its input,
+/// which rules out most edits that merely produce another valid DEFLATE stream.
+fn decode_export(data: &[u8], max_size: Option<usize>) -> Option<LuaValue> {
+    let (prefix_len, version) = split_prefix(data).ok()?;
+    let compressed_data = weakauras_codec_base64::decode_to_vec(&data[prefix_len..]).ok()?;
+    let max_size = max_size.unwrap_or(DEFAULT_MAX_SIZE);
+
+    #[cfg(feature = "legacy-strings-decoding")]
+    let decoded = if version == StringVersion::Legacy {
+        weakauras_codec_lib_compress::decompress(&compressed_data, max_size)
+            .ok()?
+            .into_owned()
+    } else {
+        inflate_all(&compressed_data, max_size)?
+    };
+    #[cfg(not(feature = "legacy-strings-decoding"))]
+    let decoded = inflate_all(&compressed_data, max_size)?;
+
+    let mut values = if version == StringVersion::BinarySerialization {
+        Deserializer::from_slice(&decoded).deserialize_all().ok()?
+    } else {
+        LegacyDeserializer::from_bytes(&decoded)
+            .deserialize_all()
+            .ok()?
+    };
+
+    match values.pop() {
+        Some(value) if values.is_empty() && is_export(&value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Inflates `compressed_data`, unless the DEFLATE stream ends before it does.
+fn inflate_all(compressed_data: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let inflated = inflate(compressed_data, max_size);
+    (inflated.error.is_none() && inflated.consumed == compressed_data.len())
+        .then_some(inflated.data)
+}
+
+/// Checks the shape of an export, see [repair].
+fn is_export(value: &LuaValue) -> bool {
+    let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+    let LuaValue::Map(root) = value else {
+        return false;
+    };
+
+    let is_aura = |value: &LuaValue| match value {
+        LuaValue::Map(data) => ["id", "regionType"]
+            .iter()
+            .all(|k| matches!(data.get(&key(k)), Some(LuaValue::String(_)))),
+        _ => false,
+    };
+
+    root.get(&key("d")).is_some_and(is_aura)
+        && match root.get(&key("c")) {
+            None => true,
+            Some(LuaValue::Array(children)) => children.iter().all(is_aura),
+            Some(_) => false,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputStringVersion, encode};
+    use weakauras_codec_lua_value::LuaMap;
+
+    fn encoded() -> String {
+        let key = |k: &str| LuaMapKey::try_from(LuaValue::from(k)).unwrap();
+        let data = LuaMap::from_iter([
+            (key("id"), "Repaired aura".into()),
+            (key("regionType"), "icon".into()),
+            (key("desc"), "Shows when the aura can be repaired".into()),
+        ]);
+        let export = LuaValue::Map(LuaMap::from_iter([(key("d"), LuaValue::Map(data))]));
+        encode(&export, OutputStringVersion::BinarySerialization).unwrap()
+    }
+
+    fn substitutions(edits: impl Iterator<Item = Edit>) -> Vec<u8> {
+        edits
+            .map(|edit| match edit {
+                Edit::Substitution { byte, .. } => byte,
+                edit => panic!("expected a substitution, got {edit:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn confusable_characters_are_tried_first() {
+        let edits: Vec<_> = likely_edits(b"!O", 1).collect();
+        assert_eq!(substitutions(edits[..4].iter().copied()), b"0oQD");
+        assert_eq!(edits[4], Edit::Deletion { offset: 1, len: 1 });
+        assert_eq!(edits.len(), 5);
+
+        // Characters outside the alphabet are left out, and so is the damaged one.
+        let edits: Vec<_> = likely_edits(b"!|", 1).collect();
+        assert_eq!(substitutions(edits[..4].iter().copied()), b"lI1i");
+        assert_eq!(edits.len(), 5);
+
+        let others = substitutions(
+            other_edits(b"!O", 1).take_while(|edit| matches!(edit, Edit::Substitution { .. })),
+        );
+        assert_eq!(others.len(), ALPHABET.len() - 5);
+        assert!(!others.iter().any(|byte| b"0oQDO".contains(byte)));
+    }
+
+    #[test]
+    fn multi_byte_characters_are_edited_as_a_whole() {
+        let data = "!a\u{201D}b".as_bytes();
+        assert_eq!(char_len(data, 1), 1);
+        assert_eq!(char_len(data, 2), 3);
+        assert_eq!(char_len(data, 3), 0);
+        assert_eq!(char_len(data, 4), 0);
+        assert_eq!(char_len(data, 5), 1);
+        assert_eq!(char_len(data, 6), 0);
+        // Bytes outside of valid sequences are edited one by one.
+        assert_eq!(char_len(b"!\xE2\x80b", 1), 1);
+        assert_eq!(char_len(b"!\xE2\x80b", 2), 1);
+        assert!(other_edits(data, 3).all(|edit| matches!(edit, Edit::Insertion { .. })));
+
+        let encoded = encoded();
+        let offset = encoded.len() / 2;
+        let mut damaged = encoded.clone();
+        damaged.replace_range(offset..offset + 1, "\u{201D}");
+
+        // Other characters than the original one may work, so only the edit is checked.
+        let repair = repair(damaged.as_bytes(), None).unwrap();
+        assert!(matches!(
+            repair.edit(),
+            Some(Edit::Substitution { offset: o, len: 3, .. }) if o == offset
+        ));
+        assert_eq!(repair.repaired().len(), encoded.len());
+        assert!(is_export(repair.value()));
+    }
+
+    #[test]
+    fn characters_can_be_inserted_at_the_end() {
+        let data = b"!WA:2!abc";
+        assert_eq!(likely_edits(data, data.len()).count(), 0);
+        let edits: Vec<_> = other_edits(data, data.len()).collect();
+        assert_eq!(edits.len(), ALPHABET.len());
+        assert!(
+            edits
+                .iter()
+                .all(|edit| matches!(edit, Edit::Insertion { offset: 9, .. }))
+        );
+
+        let encoded = encoded();
+        let damaged = &encoded.as_bytes()[..encoded.len() - 1];
+        assert!(decode_export(damaged, None).is_none());
+
+        // Inserting the character before the last one works as well.
+        let repair = repair(damaged, None).unwrap();
+        assert!(matches!(
+            repair.edit(),
+            Some(Edit::Insertion { offset, .. }) if offset >= damaged.len() - 1
+        ));
+        assert_eq!(repair.repaired().len(), encoded.len());
+        assert!(is_export(repair.value()));
+    }
+
+    #[test]
+    fn hopeless_strings_are_not_repaired() {
+        let encoded = encoded();
+        let mut damaged = encoded.into_bytes();
+        let half = damaged.len() / 2;
+        damaged[half..].fill(b'A');
+
+        assert!(repair(&damaged, None).is_none());
+        assert!(repair(b"!WA:3!AAAA", None).is_none());
+        assert!(repair(b"", None).is_none());
+    }
+}
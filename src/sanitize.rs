@@ -0,0 +1,372 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use std::{borrow::Cow, ops::Range};
+use weakauras_codec_base64::decode::ignorable_len;
+
+/// Quotes [sanitize] removes from around the string.
+const QUOTES: [(&str, &str); 4] = [("\"", "\""), ("'", "'"), ("“", "”"), ("‘", "’")];
+
+/// A kind of characters removed by [sanitize].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemovalKind {
+    /// Spaces, tabs and line breaks, including their Unicode counterparts.
+    Whitespace,
+    /// Zero-width characters, soft hyphens and byte order marks.
+    InvisibleCharacter,
+    /// Quotes around the string.
+    Quotes,
+    /// A Markdown code block or inline code around the string, e.g. from Discord.
+    CodeFence,
+}
+
+/// A range of characters removed by [sanitize].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Removal {
+    range: Range<usize>,
+    kind: RemovalKind,
+}
+
+impl Removal {
+    /// Get the range of the removed characters in the original input.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Get the kind of the removed characters.
+    pub fn kind(&self) -> RemovalKind {
+        self.kind
+    }
+}
+
+/// The result of [sanitize].
+#[derive(Debug)]
+pub struct Sanitized<'a> {
+    data: Cow<'a, [u8]>,
+    removals: Vec<Removal>,
+}
+
+impl Sanitized<'_> {
+    /// Get the sanitized string.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the removed characters, in the order they appear in the original input.
+    pub fn removals(&self) -> &[Removal] {
+        &self.removals
+    }
+
+    /// Check whether nothing was removed.
+    pub fn is_clean(&self) -> bool {
+        self.removals.is_empty()
+    }
+
+    /// Map an offset in the sanitized string, e.g. of a [DecodeError](crate::DecodeError),
+    /// to the offset in the original input.
+    ///
+    /// The end of the sanitized string is mapped to the end of its last character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use weakauras_codec::{decode, sanitize};
+    ///
+    /// let sanitized = sanitize(b"'!WA:2!JXl5rQ5Kt(6Oq5\r\n5xuoPOia'");
+    /// let error = decode(sanitized.data(), None).unwrap_err();
+    ///
+    /// assert_eq!(error.location().unwrap().offset(), 28);
+    /// assert_eq!(sanitized.original_offset(28), 31);
+    /// ```
+    pub fn original_offset(&self, offset: usize) -> usize {
+        if offset > 0 && offset == self.data.len() {
+            return self.original_offset(offset - 1) + 1;
+        }
+
+        let mut original = offset;
+        for removal in &self.removals {
+            if removal.range.start > original {
+                break;
+            }
+            original += removal.range.len();
+        }
+
+        original
+    }
+}
+
+/// Removes what commonly gets pasted along with a WeakAuras-compatible string.
+///
+/// Code fences and quotes around the string are removed, as are
+/// whitespace, line breaks and invisible characters anywhere in it,
+/// see [ignorable_len](weakauras_codec_base64::decode::ignorable_len).
+/// Every removal is reported in [Sanitized::removals].
+///
+/// [decode](crate::decode) remains strict, sanitizing is up to the caller.
+/// The data is borrowed if nothing had to be removed from the middle of it.
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::{DecodeError, RemovalKind, decode, sanitize};
+///
+/// fn main() -> Result<(), DecodeError> {
+///     let pasted = "```\n\u{feff}!WA:2!JXl5rQ5Kt(6Oq5\r\n5xuoPOiaa\n```";
+///     assert!(decode(pasted.as_bytes(), None).is_err());
+///
+///     let sanitized = sanitize(pasted.as_bytes());
+///     assert_eq!(sanitized.data(), b"!WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa");
+///
+///     let kinds: Vec<_> = sanitized.removals().iter().map(|r| r.kind()).collect();
+///     assert_eq!(
+///         kinds,
+///         [
+///             RemovalKind::CodeFence,
+///             RemovalKind::Whitespace,
+///             RemovalKind::InvisibleCharacter,
+///             RemovalKind::Whitespace,
+///             RemovalKind::Whitespace,
+///             RemovalKind::CodeFence,
+///         ]
+///     );
+///
+///     assert_eq!(
+///         decode(sanitized.data(), None)?.unwrap(),
+///         "Hello, world!".into()
+///     );
+///     Ok(())
+/// }
+/// ```
+pub fn sanitize(data: &[u8]) -> Sanitized<'_> {
+    let mut removals = Vec::new();
+    let mut range = 0..data.len();
+
+    loop {
+        trim(data, &mut range, &mut removals);
+        if !strip_wrapper(data, &mut range, &mut removals) {
+            break;
+        }
+    }
+
+    let mut inner = Vec::new();
+    let mut offset = range.start;
+    while offset < range.end {
+        match ignorable_len(&data[offset..range.end]) {
+            0 => offset += 1,
+            len => {
+                let kind = ignorable_kind(&data[offset..]);
+                push_removal(&mut inner, offset..offset + len, kind);
+                offset += len;
+            }
+        }
+    }
+
+    let data = if inner.is_empty() {
+        Cow::Borrowed(&data[range])
+    } else {
+        let mut sanitized = Vec::with_capacity(range.len());
+        let mut start = range.start;
+        for removal in &inner {
+            sanitized.extend_from_slice(&data[start..removal.range.start]);
+            start = removal.range.end;
+        }
+        sanitized.extend_from_slice(&data[start..range.end]);
+        Cow::Owned(sanitized)
+    };
+
+    removals.extend(inner);
+    removals.sort_by_key(|removal| removal.range.start);
+    Sanitized { data, removals }
+}
+
+/// Removes ignorable characters from both ends of `range`.
+fn trim(data: &[u8], range: &mut Range<usize>, removals: &mut Vec<Removal>) {
+    while let len @ 1.. = ignorable_len(&data[range.start..range.end]) {
+        let kind = ignorable_kind(&data[range.start..]);
+        push_removal(removals, range.start..range.start + len, kind);
+        range.start += len;
+    }
+
+    let mut trailing = Vec::new();
+    'outer: while range.start < range.end {
+        // Ignorable characters are at most 3 bytes long.
+        for len in 1..=3.min(range.len()) {
+            let start = range.end - len;
+            if ignorable_len(&data[start..range.end]) == len {
+                trailing.push(Removal {
+                    range: start..range.end,
+                    kind: ignorable_kind(&data[start..]),
+                });
+                range.end = start;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    for removal in trailing.into_iter().rev() {
+        push_removal(removals, removal.range, removal.kind);
+    }
+}
+
+/// Removes a code fence or quotes around `range`, returning whether there were any.
+fn strip_wrapper(data: &[u8], range: &mut Range<usize>, removals: &mut Vec<Removal>) -> bool {
+    let inner = &data[range.clone()];
+
+    let (opening, closing, kind) =
+        if inner.len() >= 6 && inner.starts_with(b"```") && inner.ends_with(b"```") {
+            // A code block may name a language on the line it starts at.
+            let tag = inner[3..inner.len() - 3]
+                .iter()
+                .position(|&byte| !byte.is_ascii_alphanumeric())
+                .filter(|&tag_len| matches!(inner[3 + tag_len], b'\n' | b'\r'))
+                .unwrap_or(0);
+            (3 + tag, 3, RemovalKind::CodeFence)
+        } else if inner.len() >= 2 && inner.starts_with(b"`") && inner.ends_with(b"`") {
+            (1, 1, RemovalKind::CodeFence)
+        } else {
+            match QUOTES.iter().find(|(opening, closing)| {
+                inner.len() >= opening.len() + closing.len()
+                    && inner.starts_with(opening.as_bytes())
+                    && inner.ends_with(closing.as_bytes())
+            }) {
+                Some((opening, closing)) => (opening.len(), closing.len(), RemovalKind::Quotes),
+                None => return false,
+            }
+        };
+
+    removals.push(Removal {
+        range: range.start..range.start + opening,
+        kind,
+    });
+    removals.push(Removal {
+        range: range.end - closing..range.end,
+        kind,
+    });
+    range.start += opening;
+    range.end -= closing;
+    true
+}
+
+/// Classifies the ignorable character `data` starts with.
+fn ignorable_kind(data: &[u8]) -> RemovalKind {
+    match data {
+        // U+00A0, U+2028, U+2029
+        [0..=0x7f, ..] | [0xc2, 0xa0, ..] | [0xe2, 0x80, 0xa8 | 0xa9, ..] => {
+            RemovalKind::Whitespace
+        }
+        _ => RemovalKind::InvisibleCharacter,
+    }
+}
+
+/// Records a removal, merging it into the last one if they are adjacent and of the same kind.
+fn push_removal(removals: &mut Vec<Removal>, range: Range<usize>, kind: RemovalKind) {
+    match removals.last_mut() {
+        Some(last) if last.kind == kind && last.range.end == range.start => {
+            last.range.end = range.end;
+        }
+        _ => removals.push(Removal { range, kind }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(sanitized: &Sanitized) -> Vec<(Range<usize>, RemovalKind)> {
+        sanitized
+            .removals()
+            .iter()
+            .map(|removal| (removal.range(), removal.kind()))
+            .collect()
+    }
+
+    #[test]
+    fn clean_strings_are_borrowed() {
+        let sanitized = sanitize(b"!WA:2!abcd");
+        assert!(sanitized.is_clean());
+        assert!(matches!(sanitized.data, Cow::Borrowed(_)));
+        assert_eq!(sanitized.original_offset(4), 4);
+        assert_eq!(sanitized.original_offset(10), 10);
+    }
+
+    #[test]
+    fn offsets_skip_merged_removals() {
+        let sanitized = sanitize(b"!WA:2!ab \t\r\n cd");
+        assert_eq!(sanitized.data(), b"!WA:2!abcd");
+        assert_eq!(ranges(&sanitized), [(8..13, RemovalKind::Whitespace)]);
+
+        assert_eq!(sanitized.original_offset(7), 7);
+        assert_eq!(sanitized.original_offset(8), 13);
+        assert_eq!(sanitized.original_offset(9), 14);
+        assert_eq!(sanitized.original_offset(10), 15);
+    }
+
+    #[test]
+    fn offsets_skip_adjacent_removals_of_different_kinds() {
+        let data = "\u{feff} !WA:2!ab \u{200b}\n\u{ad}cd\n".as_bytes();
+        let sanitized = sanitize(data);
+        assert_eq!(sanitized.data(), b"!WA:2!abcd");
+        assert_eq!(
+            ranges(&sanitized),
+            [
+                (0..3, RemovalKind::InvisibleCharacter),
+                (3..4, RemovalKind::Whitespace),
+                (12..13, RemovalKind::Whitespace),
+                (13..16, RemovalKind::InvisibleCharacter),
+                (16..17, RemovalKind::Whitespace),
+                (17..19, RemovalKind::InvisibleCharacter),
+                (21..22, RemovalKind::Whitespace),
+            ]
+        );
+
+        assert_eq!(sanitized.original_offset(0), 4);
+        assert_eq!(sanitized.original_offset(7), 11);
+        assert_eq!(sanitized.original_offset(8), 19);
+        assert_eq!(sanitized.original_offset(9), 20);
+        // The end is mapped past the last character, not past the trailing line break.
+        assert_eq!(sanitized.original_offset(10), 21);
+    }
+
+    #[test]
+    fn code_blocks_may_name_a_language() {
+        let sanitized = sanitize(b"```lua\r\n!WA:2!abcd\n```");
+        assert_eq!(sanitized.data(), b"!WA:2!abcd");
+        assert!(matches!(sanitized.data, Cow::Borrowed(_)));
+        assert_eq!(
+            ranges(&sanitized),
+            [
+                (0..6, RemovalKind::CodeFence),
+                (6..8, RemovalKind::Whitespace),
+                (18..19, RemovalKind::Whitespace),
+                (19..22, RemovalKind::CodeFence),
+            ]
+        );
+        assert_eq!(sanitized.original_offset(0), 8);
+        assert_eq!(sanitized.original_offset(10), 18);
+
+        // Without a line break, the characters after the fence are part of the string.
+        let sanitized = sanitize(b"```lua!WA:2!abcd```");
+        assert_eq!(sanitized.data(), b"lua!WA:2!abcd");
+        let sanitized = sanitize(b"```abcd```");
+        assert_eq!(sanitized.data(), b"abcd");
+    }
+
+    #[test]
+    fn nested_wrappers_are_removed() {
+        let sanitized = sanitize("`“!WA:2!abcd”`".as_bytes());
+        assert_eq!(sanitized.data(), b"!WA:2!abcd");
+        assert_eq!(
+            ranges(&sanitized),
+            [
+                (0..1, RemovalKind::CodeFence),
+                (1..4, RemovalKind::Quotes),
+                (14..17, RemovalKind::Quotes),
+                (17..18, RemovalKind::CodeFence),
+            ]
+        );
+
+        // Unbalanced quotes are left alone.
+        assert_eq!(sanitize(b"\"!WA:2!abcd'").data(), b"\"!WA:2!abcd'");
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{DecodeError, LuaValue, decode};
+use std::ops::Range;
+use weakauras_codec_base64::decode::find_invalid_byte;
+
+/// The minimum number of base64 characters following `!` for [scan] to try decoding.
+///
+/// This keeps exclamations in prose from being reported.
+const MIN_DEFLATE_LEN: usize = 16;
+
+/// The minimum number of base64 characters for [scan] to try decoding a run without a prefix.
+#[cfg(feature = "legacy-strings-decoding")]
+const MIN_LEGACY_LEN: usize = 64;
+
+/// A string found by [scan].
+#[derive(Debug)]
+pub struct ScannedString {
+    range: Range<usize>,
+    result: Result<Option<LuaValue>, DecodeError>,
+}
+
+impl ScannedString {
+    /// Get the range of the string in the scanned data.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Get the result of [decode] for the string.
+    ///
+    /// The offsets of errors are relative to the start of the string.
+    pub fn result(&self) -> Result<Option<&LuaValue>, &DecodeError> {
+        self.result.as_ref().map(Option::as_ref)
+    }
+
+    /// Convert into the result of [decode] for the string, see [ScannedString::result].
+    pub fn into_result(self) -> Result<Option<LuaValue>, DecodeError> {
+        self.result
+    }
+}
+
+/// Finds and decodes every WeakAuras-compatible string embedded in `data`,
+/// e.g. a forum post or a chat log.
+///
+/// A string starts at `!WA:2!` or `!` and ends before the first character outside
+/// of the base64 alphabet, which is found using the SIMD routines of the base64 decoder.
+/// Since `(` and `)` belong to the alphabet, closing parentheses at the end of a string
+/// are left out if they close parentheses opened before it on the same line,
+/// e.g. `(!WA:2!...)`, unless the string only decodes with them.
+/// Strings with the `!WA:2!` prefix are always reported, strings with the `!` prefix
+/// only if they are at least 16 characters long, so that exclamations are skipped.
+/// With the `legacy-strings-decoding` feature, runs of at least 64 base64 characters
+/// without a prefix are reported as well, but only if they decode successfully.
+///
+/// Strings split into lines are not joined, [sanitize](crate::sanitize) a single string
+/// before decoding it instead. `max_size` is the same as in [decode].
+///
+/// # Example
+///
+/// ```
+/// use weakauras_codec::scan;
+///
+/// let post = "Hi! Here's the bar: !WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa\n\
+///             And the icon (old version): !lodJlypsnNCYxN6sO88lkNuumU4aaa. Enjoy!";
+/// let found = scan(post, None);
+///
+/// assert_eq!(found.len(), 2);
+/// assert_eq!(&post[found[0].range()], "!WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa");
+/// assert_eq!(&post[found[1].range()], "!lodJlypsnNCYxN6sO88lkNuumU4aaa");
+/// for string in &found {
+///     assert_eq!(string.result().unwrap(), Some(&"Hello, world!".into()));
+/// }
+/// ```
+pub fn scan(data: &(impl AsRef<[u8]> + ?Sized), max_size: Option<usize>) -> Vec<ScannedString> {
+    let data = data.as_ref();
+    let mut found = Vec::new();
+    let mut offset = 0;
+    // Parentheses opened outside of the strings found so far, counted up to `counted`.
+    let (mut open, mut counted) = (0, 0);
+
+    while let Some(start) = next_candidate(data, offset) {
+        let prefix_len = match &data[start..] {
+            [b'!', b'W', b'A', b':', b'2', b'!', ..] => 6,
+            [b'!', ..] => 1,
+            _ => 0,
+        };
+
+        let base64_data = &data[start + prefix_len..];
+        let len = find_invalid_byte(base64_data).unwrap_or(base64_data.len());
+        offset = (start + prefix_len + len).max(start + 1);
+
+        let closing = base64_data[..len]
+            .iter()
+            .rev()
+            .take_while(|&&byte| byte == b')')
+            .count();
+        open = open_parentheses(open, &data[counted..start]);
+        counted = start;
+        let trimmed_len = len - closing.min(open);
+
+        let decoded = || {
+            let range = start..start + prefix_len + trimmed_len;
+            let result = decode(&data[range.clone()], max_size);
+            if result.is_err() && trimmed_len < len {
+                let full_range = start..start + prefix_len + len;
+                if let Ok(value) = decode(&data[full_range.clone()], max_size) {
+                    return (full_range, Ok(value));
+                }
+            }
+            (range, result)
+        };
+        let (range, result) = match prefix_len {
+            6 if trimmed_len > 0 => decoded(),
+            1 if trimmed_len >= MIN_DEFLATE_LEN => decoded(),
+            // Words and identifiers are too common to report failures.
+            #[cfg(feature = "legacy-strings-decoding")]
+            0 if trimmed_len >= MIN_LEGACY_LEN => match decoded() {
+                (range, Ok(Some(value))) => (range, Ok(Some(value))),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        counted = range.end;
+        found.push(ScannedString { range, result });
+    }
+
+    found
+}
+
+/// Returns the number of parentheses left open on the current line
+/// after `text`, given the number left open before it.
+fn open_parentheses(open: usize, text: &[u8]) -> usize {
+    text.iter().fold(open, |open, &byte| match byte {
+        b'(' => open + 1,
+        b')' => open.saturating_sub(1),
+        b'\n' => 0,
+        _ => open,
+    })
+}
+
+/// Returns the offset of the next possible start of a string.
+fn next_candidate(data: &[u8], offset: usize) -> Option<usize> {
+    let position = data[offset..].iter().position(|&byte| {
+        #[cfg(feature = "legacy-strings-decoding")]
+        {
+            // The preceding byte is outside of the alphabet, or a string ended there.
+            byte == b'!' || byte.is_ascii_alphanumeric() || matches!(byte, b'(' | b')')
+        }
+
+        #[cfg(not(feature = "legacy-strings-decoding"))]
+        {
+            byte == b'!'
+        }
+    })?;
+
+    Some(offset + position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BINARY: &str = "!WA:2!JXl5rQ5Kt(6Oq55xuoPOiaa";
+    const DEFLATE: &str = "!lodJlypsnNCYxN6sO88lkNuumU4aaa";
+
+    fn ranges(found: &[ScannedString]) -> Vec<Range<usize>> {
+        found.iter().map(ScannedString::range).collect()
+    }
+
+    #[test]
+    fn short_strings_without_the_full_prefix_are_skipped() {
+        let data = "Wow!abcdefghijklmno, ";
+        assert!(scan(data, None).is_empty());
+
+        let data = "Wow!abcdefghijklmnop, ";
+        let found = scan(data, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range(), 3..20);
+        assert!(found[0].result().is_err());
+
+        // The full prefix is reported regardless of the length, but not on its own.
+        let found = scan("!WA:2!a !WA:2! ", None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range(), 0..7);
+        assert!(found[0].result().is_err());
+    }
+
+    #[test]
+    fn closing_parentheses_after_strings_are_left_out() {
+        let data = format!("({BINARY}) (see {DEFLATE}), {BINARY})");
+        let found = scan(&data, None);
+        let second = data.find(DEFLATE).unwrap();
+        let third = data.rfind(BINARY).unwrap();
+        assert_eq!(
+            ranges(&found),
+            [
+                1..1 + BINARY.len(),
+                second..second + DEFLATE.len(),
+                // Nothing is left open before it.
+                third..data.len()
+            ]
+        );
+        for string in found {
+            assert_eq!(string.into_result().unwrap(), Some("Hello, world!".into()));
+        }
+    }
+
+    #[test]
+    fn closing_parentheses_are_kept_if_strings_need_them() {
+        // A stored DEFLATE block, whose last byte is partly encoded by the `)`.
+        let data = "(!WA:2!bqaa7)FaIG3)";
+        let found = scan(data, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range(), 1..data.len());
+        assert_eq!(
+            found[0].result().unwrap(),
+            Some(&LuaValue::from(&b"x\xff"[..]))
+        );
+    }
+
+    #[test]
+    fn adjacent_strings_are_split_at_the_prefix() {
+        let data = format!("{BINARY}{DEFLATE}{BINARY}");
+        let found = scan(&data, None);
+        let first = BINARY.len();
+        let second = first + DEFLATE.len();
+        assert_eq!(
+            ranges(&found),
+            [0..first, first..second, second..data.len()]
+        );
+        for string in found {
+            assert_eq!(string.into_result().unwrap(), Some("Hello, world!".into()));
+        }
+    }
+
+    #[test]
+    fn strings_start_at_the_last_exclamation_mark() {
+        let data = format!("Look!!{BINARY}!!!");
+        let found = scan(&data, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range(), 6..6 + BINARY.len());
+        assert!(found[0].result().unwrap().is_some());
+
+        // `!WA:2` is a short run of base64 characters, not a prefix.
+        assert!(scan("!WA:2JXl5rQ5Kt(6Oq55xuoPOiaa", None).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "legacy-strings-decoding")]
+    fn runs_without_a_prefix_are_reported_only_if_they_decode() {
+        use weakauras_codec_ace_serialize::Serializer as LegacySerializer;
+
+        // LibCompress stores short data uncompressed, after a 0x01 byte.
+        let message = "Hello, world! This string is long enough to be found.";
+        let mut compressed = vec![1];
+        compressed.extend(LegacySerializer::serialize_one(&message.into(), None).unwrap());
+        let legacy = weakauras_codec_base64::encode_to_string(&compressed).unwrap();
+        assert!(legacy.len() >= MIN_LEGACY_LEN);
+
+        let run = "a".repeat(MIN_LEGACY_LEN);
+        let data = format!("{run} {legacy}, {DEFLATE}");
+        let found = scan(&data, None);
+        let start = run.len() + 1;
+        assert_eq!(
+            ranges(&found),
+            [
+                start..start + legacy.len(),
+                data.len() - DEFLATE.len()..data.len()
+            ]
+        );
+        assert_eq!(found[0].result().unwrap(), Some(&message.into()));
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2025 Velithris
+// SPDX-License-Identifier: MIT
+
+use crate::{LuaValue, OutputStringVersion, decode, encode};
+use weakauras_codec_lua_value::{LuaMap, LuaMapKey};
+
+fn key(key: impl Into<LuaValue>) -> LuaMapKey {
+    LuaMapKey::try_from(key.into()).unwrap()
+}
+
+#[test]
+fn string_versions_decode_into_the_same_shape() {
+    // { id = "aura", triggers = { [1] = { event = "Health" }, [2] = { event = "Power" } },
+    //   color = { 1, 0.5, 0, 1 }, load = {} }
+    let trigger = |event: &str| LuaValue::Map(LuaMap::from_iter([(key("event"), event.into())]));
+    let value = LuaValue::Map(LuaMap::from_iter([
+        (key("id"), "aura".into()),
+        (
+            key("triggers"),
+            LuaValue::Map(LuaMap::from_iter([
+                (key(1.0), trigger("Health")),
+                (key(2.0), trigger("Power")),
+            ])),
+        ),
+        (
+            key("color"),
+            LuaValue::Array(vec![1.0.into(), 0.5.into(), 0.0.into(), 1.0.into()]),
+        ),
+        (key("load"), LuaValue::Map(LuaMap::new())),
+    ]));
+
+    let deflate = encode(&value, OutputStringVersion::Deflate).unwrap();
+    let binary = encode(&value, OutputStringVersion::BinarySerialization).unwrap();
+    let from_deflate = decode(deflate.as_bytes(), None).unwrap().unwrap();
+    let from_binary = decode(binary.as_bytes(), None).unwrap().unwrap();
+
+    // Tables are compared by identity, like in Lua.
+    assert_eq!(format!("{from_deflate:?}"), format!("{from_binary:?}"));
+
+    let LuaValue::Map(ref aura) = from_binary else {
+        panic!("expected a map");
+    };
+    assert!(matches!(aura.get(&key("triggers")), Some(LuaValue::Array(v)) if v.len() == 2));
+    assert!(matches!(aura.get(&key("color")), Some(LuaValue::Array(v)) if v.len() == 4));
+    assert!(matches!(aura.get(&key("load")), Some(LuaValue::Array(v)) if v.is_empty()));
+}
+
+#[test]
+fn standalone_deserializers_produce_the_same_shape_by_default() {
+    use weakauras_codec_ace_serialize::{
+        Deserializer as AceDeserializer, Serializer as AceSerializer,
+    };
+    use weakauras_codec_lib_serialize::{
+        Deserializer as LibDeserializer, SerializeOptions, Serializer as LibSerializer,
+    };
+
+    // { [1] = "a", [2] = "b" }, { "a", "b", x = 1 } and { [1] = "a", [3] = "c" }
+    let values = [
+        LuaValue::Map(LuaMap::from_iter([
+            (key(1.0), "a".into()),
+            (key(2.0), "b".into()),
+        ])),
+        LuaValue::Map(LuaMap::from_iter([
+            (key(1.0), "a".into()),
+            (key(2.0), "b".into()),
+            (key("x"), 1.0.into()),
+        ])),
+        LuaValue::Map(LuaMap::from_iter([
+            (key(1.0), "a".into()),
+            (key(3.0), "c".into()),
+        ])),
+    ];
+
+    for value in values {
+        let ace = AceSerializer::serialize_one(&value, None).unwrap();
+        let lib = LibSerializer::serialize_one_with(&value, SerializeOptions::new()).unwrap();
+        let from_ace = AceDeserializer::from_bytes(&ace)
+            .deserialize_first()
+            .unwrap();
+        let from_lib = LibDeserializer::from_slice(&lib)
+            .deserialize_first()
+            .unwrap();
+        assert_eq!(
+            format!("{from_ace:?}"),
+            format!("{from_lib:?}"),
+            "{value:?}"
+        );
+    }
+}
+
+/// Builds a table resembling a WeakAuras export of a group with `children` children.
+fn group_export(children: usize) -> LuaValue {
+    let map = |entries: Vec<(&str, LuaValue)>| {
+        LuaValue::Map(LuaMap::from_iter(
+            entries.into_iter().map(|(k, v)| (key(k), v)),
+        ))
+    };
+    let color = || LuaValue::Array(vec![1.0.into(), 0.5.into(), 0.0.into(), 1.0.into()]);
+    let child = |i: usize| {
+        map(vec![
+            ("id", format!("Cooldown {i}").into()),
+            ("regionType", "aurabar".into()),
+            (
+                "triggers",
+                LuaValue::Array(vec![map(vec![
+                    (
+                        "trigger",
+                        map(vec![
+                            ("type", "aura2".into()),
+                            (
+                                "auranames",
+                                LuaValue::Array(vec![format!("Spell {i}").into()]),
+                            ),
+                        ]),
+                    ),
+                    ("untrigger", map(vec![])),
+                ])]),
+            ),
+            (
+                "subRegions",
+                LuaValue::Array(vec![
+                    map(vec![("type", "subbackground".into())]),
+                    map(vec![("type", "subtext".into()), ("text_color", color())]),
+                ]),
+            ),
+            (
+                "conditions",
+                LuaValue::Array(vec![map(vec![(
+                    "changes",
+                    LuaValue::Array(vec![map(vec![
+                        ("property", "barColor".into()),
+                        ("value", color()),
+                    ])]),
+                )])]),
+            ),
+            (
+                "load",
+                map(vec![("class", map(vec![("multi", map(vec![]))]))]),
+            ),
+        ])
+    };
+
+    map(vec![
+        (
+            "d",
+            map(vec![
+                ("id", "Raid Cooldowns".into()),
+                ("regionType", "dynamicgroup".into()),
+            ]),
+        ),
+        ("c", LuaValue::Array((0..children).map(child).collect())),
+    ])
+}
+
+#[test]
+fn large_exports_decode_under_the_default_limits() {
+    let value = group_export(5000);
+
+    for version in [
+        OutputStringVersion::Deflate,
+        OutputStringVersion::BinarySerialization,
+    ] {
+        let encoded = encode(&value, version).unwrap();
+        let decoded = decode(encoded.as_bytes(), None).unwrap().unwrap();
+        assert!(matches!(
+            &decoded,
+            LuaValue::Map(root) if matches!(root.get(&key("c")), Some(LuaValue::Array(c)) if c.len() == 5000)
+        ));
+    }
+}